@@ -12,9 +12,9 @@ use warg::graphics::{parametric, Layer, Paint, Sprite, SpriteImage, SpriteImageR
 use warg::input::keyboard::{attach_keyboard, Keyboard};
 use warg::input::mouse::{attach_mouse, Mouse, MouseInteractionKind};
 use warg::input::Dpad;
-use warg::physics::box2d::{Box2DPhysics, HitBox, Mass, Mov, Object, ObjectId};
+use warg::physics::box2d::{Box2DPhysics, HitBox, Mass, Mov, Object, ObjectId, SleepThreshold};
 use warg::physics::identity::{Identity, ObjectKey};
-use warg::physics::universe::{Space, Universe};
+use warg::physics::universe::{Interpolate, Space, Universe};
 use warg::vector::v2::V2;
 
 use self::input::Keys;
@@ -40,7 +40,14 @@ impl BouncyBoxWorld {
             height,
         );
 
-        let mut universe = Universe::new(Box2DPhysics::new(cor));
+        let mut physics = Box2DPhysics::new(cor);
+        // Boxes which came to rest stop costing anything
+        physics.set_sleep_threshold(Some(SleepThreshold {
+            speed: 0.002,
+            steps: 60,
+        }));
+        let mut universe = Universe::new(physics);
+        universe.set_fixed_step(1000.0 / 120.0, 8);
         let space = universe.space_mut();
         add_outer_walls(space, width - 50, height - 50, 1_000_000);
 
@@ -70,15 +77,17 @@ impl BouncyBoxWorld {
             let interactions = mouse.interactions();
             while let Some(event) = interactions.recv() {
                 match event.kind {
-                    MouseInteractionKind::Click => {
-                        clicks.push((event.pos, event.button));
+                    MouseInteractionKind::Click(button)
+                    | MouseInteractionKind::DoubleClick(button) => {
+                        clicks.push((event.scene_pos, button));
                     },
-                    MouseInteractionKind::Drag => {
-                        drags.push((event.pos, event.button));
+                    MouseInteractionKind::Drag(button) => {
+                        drags.push((event.scene_pos, button));
                     },
-                    MouseInteractionKind::Drop => {
-                        drops.push((event.pos, event.button));
+                    MouseInteractionKind::Drop(button) => {
+                        drops.push((event.scene_pos, button));
                     },
+                    _ => (),
                 }
             }
         }
@@ -95,13 +104,13 @@ impl BouncyBoxWorld {
 
     fn on_mouse_drag(&mut self, pos: V2<f32>, button: MouseButton) {
         if let MouseButton::Left = button {
-            self.drag_start = self.viewport.relative_pos(pos)
+            self.drag_start = self.viewport.scene_to_world(pos)
         }
     }
 
     fn on_mouse_drop(&mut self, pos: V2<f32>, button: MouseButton) {
         if let MouseButton::Left = button {
-            let drag_end = self.viewport.relative_pos(pos);
+            let drag_end = self.viewport.scene_to_world(pos);
             let (x_min, x_max) = match self.drag_start.x.cmp(&drag_end.x) {
                 Ordering::Less => (self.drag_start.x, drag_end.x),
                 _ => (drag_end.x, self.drag_start.x),
@@ -127,22 +136,43 @@ impl BouncyBoxWorld {
     fn on_mouse_click(&mut self, pos: V2<f32>, button: MouseButton) {
         match button {
             MouseButton::Left => {
+                let world_pos = self.viewport.scene_to_world(pos);
+                if self.pop_box(world_pos) {
+                    return;
+                }
                 let size = self.scale;
-                let center_pos = self.viewport.relative_pos(pos);
-                let tl_pos = center_pos - V2::new((size as i64) / 2, (size as i64) / 2);
+                let tl_pos = world_pos - V2::new((size as i64) / 2, (size as i64) / 2);
                 self.universe
                     .space_mut()
                     .add(bouncybox(size, size, tl_pos, 1.0, Cm4::C2));
             },
             MouseButton::Right => {
                 let size = self.scale;
-                let center_pos = self.viewport.relative_pos(pos);
+                let center_pos = self.viewport.scene_to_world(pos);
                 self.universe.space_mut().add(wall(center_pos, size, size));
             },
             _ => (),
         }
     }
 
+    // Remove the box under pos, if there is one other than the player
+    fn pop_box(&mut self, pos: V2<i64>) -> bool {
+        let space = self.universe.space_mut();
+        let picked = space
+            .at_point(V2::new(pos.x as f32, pos.y as f32))
+            .into_iter()
+            .find(|key| {
+                *key != self.player_key && space.get(*key).unwrap().hitbox.mass().is_some()
+            });
+        match picked {
+            Some(key) => {
+                space.destroy(key);
+                true
+            },
+            None => false,
+        }
+    }
+
     fn update_player_accel(&mut self) {
         if let Some(input) = &self.keyboard {
             let space = self.universe.space_mut();
@@ -171,7 +201,7 @@ impl Game for BouncyBoxWorld {
     }
 
     fn renderer(&self) -> Box<dyn Renderer> {
-        let mut scene = self.universe.space().paint(&self.viewport);
+        let mut scene = self.universe.paint(&self.viewport);
         scene.set_background(self.background.clone());
         Box::new(scene)
     }
@@ -214,19 +244,18 @@ fn default_palette() -> ColorMap4 {
 }
 
 fn bouncybox(width: usize, height: usize, pos: V2<i64>, density: f32, color: Cm4) -> Rectangle {
-    Rectangle::new(
+    let mut rect = Rectangle::new(
         width,
         height,
-        Mov {
-            pos: V2::new(pos.x as f32, pos.y as f32),
-            vel: V2::zero(),
-            acc: V2::zero(),
-        },
+        Mov::new(V2::new(pos.x as f32, pos.y as f32), V2::zero(), V2::zero()),
         Mass::Density(density),
         color,
         Cm4::C7,
         Layer::L1,
-    )
+    );
+    // Thrown boxes can be fast enough to go through each other
+    rect.hitbox.bullet = true;
+    rect
 }
 
 fn wall(center: V2<i64>, width: usize, height: usize) -> Rectangle {
@@ -234,11 +263,11 @@ fn wall(center: V2<i64>, width: usize, height: usize) -> Rectangle {
         center.x - (width as i64) / 2,
         center.y - (height as i64) / 2,
     );
-    let mov: Mov<f32> = Mov {
-        pos: V2::new(tl.x as f32, tl.y as f32),
-        vel: V2::new(0.0, 0.0),
-        acc: V2::new(0.0, 0.0),
-    };
+    let mov: Mov<f32> = Mov::new(
+        V2::new(tl.x as f32, tl.y as f32),
+        V2::new(0.0, 0.0),
+        V2::new(0.0, 0.0),
+    );
     Rectangle::new(
         width,
         height,
@@ -280,12 +309,7 @@ impl Rectangle {
     ) -> Self {
         Self {
             id: ObjectId::new(),
-            hitbox: HitBox {
-                width,
-                height,
-                mov,
-                mass,
-            },
+            hitbox: HitBox::new(width, height, mov, mass),
             image: rectangle_image(width, height, fill_color, outline_color),
             layer,
         }
@@ -300,8 +324,14 @@ impl Rectangle {
 
 impl Paint for Rectangle {
     fn paint(&self) -> Option<Sprite> {
+        self.paint_interpolated(1.0)
+    }
+}
+
+impl Interpolate for Rectangle {
+    fn paint_interpolated(&self, alpha: f32) -> Option<Sprite> {
         Some(Sprite::new(
-            self.hitbox.mov.pos.round(),
+            self.hitbox.mov.lerp_pos(alpha).round(),
             self.layer,
             self.image.clone(),
         ))
@@ -7,6 +7,8 @@ export class Game {
     #renderContext;
     #fpsCounter;
     #resizeObserver;
+    #gamepadPoller;
+    #textInputElement;
     #logFps = false;
     #finished = false;
     #listeners = [];
@@ -36,13 +38,60 @@ export class Game {
         return this;
     }
     withKeyboard() {
-        this.#listeners = this.#listeners.concat(keyboardEventQueueListeners(this.#rootElement, this.#eventQueue));
+        this.#listeners = this.#listeners.concat(keyboardEventQueueListeners(
+            this.#rootElement,
+            this.#eventQueue,
+            () => this.#textInputElement != null,
+        ));
         return this;
     }
     withMouse() {
         this.#listeners = this.#listeners.concat(mouseEventQueueListeners(this.#canvasElement, this.#eventQueue));
         return this;
     }
+    withPointerLock() {
+        this.#listeners = this.#listeners.concat(pointerLockListeners(this.#canvasElement, this.#gameHandle));
+        return this;
+    }
+    requestPointerLock() {
+        this.#gameHandle.request_pointer_lock();
+        this.#canvasElement.requestPointerLock();
+        return this;
+    }
+    releasePointerLock() {
+        this.#gameHandle.release_pointer_lock();
+        if (document.pointerLockElement === this.#canvasElement) {
+            document.exitPointerLock();
+        }
+        return this;
+    }
+    withTextInput() {
+        // IME composition only happens in editable elements, so typing
+        // goes to a hidden input which takes the focus of the root
+        const inputElement = document.createElement("input");
+        inputElement.type = "text";
+        inputElement.autocomplete = "off";
+        inputElement.style.position = "absolute";
+        inputElement.style.width = "1px";
+        inputElement.style.height = "1px";
+        inputElement.style.opacity = "0";
+        inputElement.style.pointerEvents = "none";
+        this.#rootElement.appendChild(inputElement);
+        this.#textInputElement = inputElement;
+        this.#listeners = this.#listeners.concat(
+            textInputListeners(this.#rootElement, inputElement, this.#eventQueue)
+        );
+        return this;
+    }
+    withTouch() {
+        this.#listeners = this.#listeners.concat(touchEventQueueListeners(this.#canvasElement, this.#eventQueue));
+        return this;
+    }
+    withGamepad() {
+        this.#gamepadPoller = new GamepadPoller(this.#eventQueue);
+        this.#listeners = this.#listeners.concat(this.#gamepadPoller.listeners());
+        return this;
+    }
     withFileInput(name, bindKey = null, maxSizeMB = 8, accept = null) {
         const inputElement = document.createElement("input");
         inputElement.type = "file";
@@ -76,7 +125,13 @@ export class Game {
             if (this.#finished) {
                 return;
             }
+            if (this.#gamepadPoller != null) {
+                this.#gamepadPoller.poll();
+            }
             let response = gameHandle.tick(now());
+            if (document.pointerLockElement === canvas && !gameHandle.window().pointer_lock_requested()) {
+                document.exitPointerLock();
+            }
             if (response == "Finished") {
                 this.finish();
             } else if (response == "RequestRedraw") {
@@ -143,28 +198,141 @@ class Listener {
     }
 }
 
+// canvasPos converts a client position into canvas pixels;
+// the canvas may be offset and stretched by CSS
+function canvasPos(canvas, clientX, clientY) {
+    const rect = canvas.getBoundingClientRect();
+    return [
+        (clientX - rect.left) * canvas.width / rect.width,
+        (clientY - rect.top) * canvas.height / rect.height,
+    ];
+}
+
+function canvasDelta(canvas, dx, dy) {
+    const rect = canvas.getBoundingClientRect();
+    return [dx * canvas.width / rect.width, dy * canvas.height / rect.height];
+}
+
+// The browser only grants pointer lock during a user gesture,
+// so a lock requested by the game is acquired on the next click
+function pointerLockListeners(canvas, gameHandle) {
+    return [
+        new Listener(canvas, "click", () => {
+            if (gameHandle.window().pointer_lock_requested() && document.pointerLockElement !== canvas) {
+                canvas.requestPointerLock();
+            }
+        }),
+        new Listener(document, "pointerlockchange", () => {
+            if (document.pointerLockElement !== canvas) {
+                gameHandle.release_pointer_lock();
+            }
+        }),
+    ];
+}
+
 function mouseEventQueueListeners(element, eventQueue) {
     return [
         new Listener(element, "mousedown", (e) => {
-            eventQueue.send_mouse_button(now(), e.layerX / element.width, e.layerY / element.height, e.button, false);
+            eventQueue.send_mouse_button(now(), ...canvasPos(element, e.clientX, e.clientY), e.button, false);
         }),
         new Listener(element, "mouseup", (e) => {
-            eventQueue.send_mouse_button(now(), e.layerX / element.width, e.layerY / element.height, e.button, true);
+            eventQueue.send_mouse_button(now(), ...canvasPos(element, e.clientX, e.clientY), e.button, true);
         }),
         new Listener(element, "mousemove", (e) => {
-            eventQueue.send_mouse_move(now(), e.layerX / element.width, e.layerY / element.height);
+            if (document.pointerLockElement === element) {
+                const [dx, dy] = canvasDelta(element, e.movementX, e.movementY);
+                eventQueue.send_mouse_relative_move(now(), ...canvasPos(element, e.clientX, e.clientY), dx, dy);
+            } else {
+                eventQueue.send_mouse_move(now(), ...canvasPos(element, e.clientX, e.clientY));
+            }
+        }),
+        new Listener(element, "wheel", (e) => {
+            eventQueue.send_mouse_wheel(now(), ...canvasPos(element, e.clientX, e.clientY), e.deltaX, e.deltaY);
+            e.preventDefault();
         }),
         new Listener(element, "contextmenu", (e) => { e.preventDefault(); })
     ];
 }
 
-function keyboardEventQueueListeners(element, eventQueue) {
+function touchEventQueueListeners(element, eventQueue) {
+    const sendTouches = (e, send) => {
+        for (let i = 0; i < e.changedTouches.length; i++) {
+            const touch = e.changedTouches[i];
+            const [x, y] = canvasPos(element, touch.clientX, touch.clientY);
+            send.call(eventQueue, now(), touch.identifier, x, y);
+        }
+        e.preventDefault();
+    };
+    return [
+        new Listener(element, "touchstart", (e) => { sendTouches(e, eventQueue.send_touch_start); }),
+        new Listener(element, "touchmove", (e) => { sendTouches(e, eventQueue.send_touch_move); }),
+        new Listener(element, "touchend", (e) => { sendTouches(e, eventQueue.send_touch_end); }),
+        new Listener(element, "touchcancel", (e) => { sendTouches(e, eventQueue.send_touch_cancel); }),
+    ];
+}
+
+// The Gamepad API has no button or axis events, so gamepads are polled
+// once per frame and changes are sent to the event queue.
+class GamepadPoller {
+    #eventQueue;
+    #states = new Map();
+
+    constructor(eventQueue) {
+        this.#eventQueue = eventQueue;
+    }
+    listeners() {
+        return [
+            new Listener(window, "gamepadconnected", (e) => {
+                this.#eventQueue.send_gamepad_connect(now(), e.gamepad.index);
+            }),
+            new Listener(window, "gamepaddisconnected", (e) => {
+                this.#states.delete(e.gamepad.index);
+                this.#eventQueue.send_gamepad_disconnect(now(), e.gamepad.index);
+            }),
+        ];
+    }
+    poll() {
+        const ts = now();
+        for (const gamepad of navigator.getGamepads()) {
+            if (gamepad == null) {
+                continue;
+            }
+            let state = this.#states.get(gamepad.index);
+            if (state == null) {
+                state = { buttons: [], axes: [] };
+                this.#states.set(gamepad.index, state);
+            }
+            gamepad.buttons.forEach((button, i) => {
+                if (button.pressed !== (state.buttons[i] === true)) {
+                    state.buttons[i] = button.pressed;
+                    this.#eventQueue.send_gamepad_button(ts, gamepad.index, i, button.pressed);
+                }
+            });
+            gamepad.axes.forEach((value, i) => {
+                if (value !== state.axes[i]) {
+                    state.axes[i] = value;
+                    this.#eventQueue.send_gamepad_axis(ts, gamepad.index, i, value);
+                }
+            });
+        }
+    }
+}
+
+function keyboardEventQueueListeners(element, eventQueue, hasTextInput) {
     return [
         new Listener(element, "keydown", (e) => {
+            // Keys typed into an IME belong to the composition
+            if (e.isComposing || e.keyCode === 229) {
+                return;
+            }
             if (!e.repeat) {
                 eventQueue.send_key_down(e.keyCode, now());
             }
-            e.preventDefault();
+            // With text input, let shortcuts like Ctrl+V through,
+            // so paste events can fire
+            if (!(hasTextInput() && (e.ctrlKey || e.metaKey))) {
+                e.preventDefault();
+            }
         }),
         new Listener(element, "keyup", (e) => {
             eventQueue.send_key_up(e.keyCode, now());
@@ -173,6 +341,37 @@ function keyboardEventQueueListeners(element, eventQueue) {
     ];
 }
 
+// Key events of inputElement bubble up to rootElement,
+// so keyboard listeners still get them
+function textInputListeners(rootElement, inputElement, eventQueue) {
+    return [
+        new Listener(rootElement, "focus", () => { inputElement.focus(); }),
+        new Listener(inputElement, "keydown", (e) => {
+            if (e.isComposing) {
+                return;
+            }
+            if ((e.ctrlKey || e.metaKey) && e.key === "a") {
+                eventQueue.send_text_edit(now(), "SelectAll", false);
+                e.preventDefault();
+            } else if ([...e.key].length === 1 && !e.ctrlKey && !e.metaKey) {
+                // A single character, possibly outside the BMP like emojis
+                eventQueue.send_text_input(now(), e.key);
+                e.preventDefault();
+            } else if (!e.ctrlKey && !e.metaKey) {
+                eventQueue.send_text_edit(now(), e.key, e.shiftKey);
+            }
+        }),
+        new Listener(inputElement, "compositionend", (e) => {
+            eventQueue.send_text_input(now(), e.data);
+            inputElement.value = "";
+        }),
+        new Listener(inputElement, "paste", (e) => {
+            eventQueue.send_text_paste(now(), e.clipboardData.getData("text"));
+            e.preventDefault();
+        }),
+    ];
+}
+
 function fileInputListeners(rootElement, inputElement, eventQueue, name, bindKey = null, maxSizeMB = 8) {
     let listeners = [];
 
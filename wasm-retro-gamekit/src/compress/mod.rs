@@ -38,7 +38,7 @@ impl<'a, T: 'static + Eq + Clone> Iterator for DataIter<'a, T> {
 }
 
 #[derive(Debug)]
-pub struct DataError(String);
+pub struct DataError(pub String);
 
 pub type Result<T> = std::result::Result<T, DataError>;
 
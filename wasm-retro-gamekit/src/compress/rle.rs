@@ -70,7 +70,7 @@ impl<L: UInt + 'static, T: Eq + Clone> RleVec<L, T> {
         panic!("RleVec length is corrupted?")
     }
 
-    pub fn iter(&self) -> RleVecIter<L, T> {
+    pub fn iter(&self) -> RleVecIter<'_, L, T> {
         RleVecIter {
             rle_v: self,
             idx: 0,
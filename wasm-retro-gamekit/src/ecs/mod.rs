@@ -0,0 +1,333 @@
+pub mod physics;
+mod storage;
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use self::storage::{AnyStorage, Storage};
+use crate::graphics::{Paint, Scene, Viewport};
+use crate::physics::universe::paint_sprites;
+
+/// Entity identifies a set of components in a World.
+///
+/// The index of a despawned entity is reused, but with another
+/// generation, so the old Entity doesn't find the new components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    fn new(index: usize, generation: u32) -> Self {
+        Self {
+            index: index as u32,
+            generation,
+        }
+    }
+}
+
+struct EntitySlot {
+    generation: u32,
+    alive: bool,
+}
+
+/// World stores entities and their components, with one storage per
+/// component type; any 'static type can be a component.
+///
+/// Queries iterate over the entities having all the components asked
+/// for, in the order of their indices.
+pub struct World {
+    entities: Vec<EntitySlot>,
+    free: Vec<u32>,
+    storages: Vec<Box<dyn AnyStorage>>,
+    storage_by_type: HashMap<TypeId, usize>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            entities: vec![],
+            free: vec![],
+            storages: vec![],
+            storage_by_type: HashMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.entities[index as usize];
+                slot.generation = slot.generation.wrapping_add(1);
+                slot.alive = true;
+                Entity::new(index as usize, slot.generation)
+            },
+            None => {
+                self.entities.push(EntitySlot {
+                    generation: 0,
+                    alive: true,
+                });
+                Entity::new(self.entities.len() - 1, 0)
+            },
+        }
+    }
+
+    /// despawn removes the entity and all its components;
+    /// false if it was already despawned
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.iter_mut() {
+            storage.remove_entity(entity);
+        }
+        self.entities[entity.index as usize].alive = false;
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        match self.entities.get(entity.index as usize) {
+            Some(slot) => slot.alive && slot.generation == entity.generation,
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.alive)
+            .map(|(index, slot)| Entity::new(index, slot.generation))
+    }
+
+    /// insert adds a component to the entity, returning the one of
+    /// the same type it replaces, if any
+    pub fn insert<C: 'static>(&mut self, entity: Entity, component: C) -> Option<C> {
+        if !self.is_alive(entity) {
+            panic!("component inserted into despawned {:?}", entity)
+        }
+        let index = match self.storage_by_type.get(&TypeId::of::<C>()) {
+            Some(index) => *index,
+            None => {
+                self.storages.push(Box::new(Storage::<C>::new()));
+                self.storage_by_type
+                    .insert(TypeId::of::<C>(), self.storages.len() - 1);
+                self.storages.len() - 1
+            },
+        };
+        downcast_mut::<C>(&mut self.storages[index])?.insert(entity, component)
+    }
+
+    pub fn remove<C: 'static>(&mut self, entity: Entity) -> Option<C> {
+        self.storage_mut::<C>()?.remove(entity)
+    }
+
+    pub fn get<C: 'static>(&self, entity: Entity) -> Option<&C> {
+        self.storage::<C>()?.get(entity)
+    }
+
+    pub fn get_mut<C: 'static>(&mut self, entity: Entity) -> Option<&mut C> {
+        self.storage_mut::<C>()?.get_mut(entity)
+    }
+
+    pub fn has<C: 'static>(&self, entity: Entity) -> bool {
+        self.get::<C>(entity).is_some()
+    }
+
+    /// query iterates over the entities with a component of type A
+    pub fn query<A: 'static>(&self) -> impl Iterator<Item = (Entity, &A)> {
+        self.storage::<A>().into_iter().flat_map(Storage::iter)
+    }
+
+    pub fn query_mut<A: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut A)> {
+        self.storage_mut::<A>()
+            .into_iter()
+            .flat_map(Storage::iter_mut)
+    }
+
+    /// query2 iterates over the entities with components of both A and B
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let b = self.storage::<B>();
+        self.query::<A>()
+            .filter_map(move |(entity, a)| Some((entity, a, b?.get(entity)?)))
+    }
+
+    /// query2_mut is query2 with the components of A mutable;
+    /// A and B must be different types
+    pub fn query2_mut<A: 'static, B: 'static>(
+        &mut self,
+    ) -> impl Iterator<Item = (Entity, &mut A, &B)> {
+        self.storage_pair_mut::<A, B>()
+            .into_iter()
+            .flat_map(|(a, b)| {
+                let b: &Storage<B> = b;
+                a.iter_mut()
+                    .filter_map(move |(entity, a)| Some((entity, a, b.get(entity)?)))
+            })
+    }
+
+    /// paint paints the components of type C, like Space::paint
+    pub fn paint<C: Paint + 'static>(&self, viewport: &Viewport) -> Scene {
+        paint_sprites(
+            viewport,
+            self.query::<C>()
+                .filter_map(|(_, component)| component.paint()),
+        )
+    }
+
+    fn storage<C: 'static>(&self) -> Option<&Storage<C>> {
+        let index = *self.storage_by_type.get(&TypeId::of::<C>())?;
+        self.storages[index].as_any().downcast_ref()
+    }
+
+    fn storage_mut<C: 'static>(&mut self) -> Option<&mut Storage<C>> {
+        let index = *self.storage_by_type.get(&TypeId::of::<C>())?;
+        downcast_mut(&mut self.storages[index])
+    }
+
+    // Borrow two storages at once, like Space::get_pair_mut
+    fn storage_pair_mut<A: 'static, B: 'static>(
+        &mut self,
+    ) -> Option<(&mut Storage<A>, &mut Storage<B>)> {
+        let index_a = *self.storage_by_type.get(&TypeId::of::<A>())?;
+        let index_b = *self.storage_by_type.get(&TypeId::of::<B>())?;
+        assert_ne!(index_a, index_b, "query of the same component type twice");
+        let (lo, hi) = self.storages.split_at_mut(index_a.max(index_b));
+        let (low, high) = (&mut lo[index_a.min(index_b)], &mut hi[0]);
+        let (a, b) = match index_a < index_b {
+            true => (low, high),
+            false => (high, low),
+        };
+        Some((downcast_mut(a)?, downcast_mut(b)?))
+    }
+}
+
+fn downcast_mut<C: 'static>(storage: &mut Box<dyn AnyStorage>) -> Option<&mut Storage<C>> {
+    storage.as_any_mut().downcast_mut()
+}
+
+/// System updates the components of a World on each tick,
+/// e.g. physics::PhysicsSystem; closures taking the World and
+/// delta_t are systems too.
+pub trait System {
+    fn run(&mut self, world: &mut World, delta_t: f32);
+}
+
+impl<F> System for F
+where
+    F: FnMut(&mut World, f32),
+{
+    fn run(&mut self, world: &mut World, delta_t: f32) {
+        self(world, delta_t)
+    }
+}
+
+/// Schedule runs systems in the order they were added.
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self { systems: vec![] }
+    }
+
+    pub fn add(&mut self, system: impl System + 'static) -> &mut Self {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    pub fn run(&mut self, world: &mut World, delta_t: f32) {
+        for system in self.systems.iter_mut() {
+            system.run(world, delta_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Vel(i32);
+
+    #[test]
+    fn test_components() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        world.insert(a, Pos(1));
+        assert_eq!(world.insert(a, Pos(2)), Some(Pos(1)));
+        world.insert(b, Vel(3));
+        assert_eq!(world.get::<Pos>(a), Some(&Pos(2)));
+        assert!(world.get::<Pos>(b).is_none());
+        assert!(world.has::<Vel>(b));
+
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        assert_eq!(world.len(), 1);
+        // The index of a is reused, but a doesn't find the new entity
+        let c = world.spawn();
+        world.insert(c, Pos(4));
+        assert!(world.get::<Pos>(a).is_none());
+        assert_eq!(world.remove::<Pos>(c), Some(Pos(4)));
+        assert_eq!(world.entities().collect::<Vec<_>>(), vec![c, b]);
+    }
+
+    #[test]
+    fn test_queries_and_systems() {
+        let mut world = World::new();
+        let still = world.spawn();
+        world.insert(still, Pos(0));
+        let moving: Vec<Entity> = (0..3)
+            .map(|i| {
+                let entity = world.spawn();
+                world.insert(entity, Vel(i));
+                world.insert(entity, Pos(10 * i));
+                entity
+            })
+            .collect();
+        let only_vel = world.spawn();
+        world.insert(only_vel, Vel(100));
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add(|world: &mut World, _| {
+                for (_, pos, vel) in world.query2_mut::<Pos, Vel>() {
+                    pos.0 += vel.0;
+                }
+            })
+            .add(|world: &mut World, _| {
+                for (_, vel) in world.query_mut::<Vel>() {
+                    vel.0 *= 2;
+                }
+            });
+        schedule.run(&mut world, 1.0);
+        schedule.run(&mut world, 1.0);
+
+        let positions: Vec<(Entity, i32)> = world
+            .query2::<Pos, Vel>()
+            .map(|(entity, pos, _)| (entity, pos.0))
+            .collect();
+        let expected: Vec<(Entity, i32)> = moving
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (*entity, 13 * i as i32))
+            .collect();
+        assert_eq!(positions, expected);
+        assert_eq!(world.get::<Pos>(still), Some(&Pos(0)));
+        assert_eq!(world.query::<Pos>().count(), 4);
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Entity, System, World};
+use crate::graphics::{Layer, Scene, Sprite, SpriteImageRef, Viewport};
+use crate::num::Float;
+use crate::physics::box2d::{Box2DPhysics, HitBox, Object, ObjectId};
+use crate::physics::identity::{Identity, ObjectKey};
+use crate::physics::universe::{paint_sprites, Universe};
+
+/// Body is the object of the Universe of a PhysicsSystem which
+/// stands for an entity with a HitBox component.
+pub struct Body<T> {
+    id: ObjectId,
+    entity: Entity,
+    hitbox: HitBox<T>,
+}
+
+impl<T> Body<T> {
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T> Identity for Body<T> {
+    fn id(&self) -> &ObjectId {
+        &self.id
+    }
+}
+
+impl<T> Object<T> for Body<T>
+where
+    T: Float,
+{
+    fn hitbox(&self) -> &HitBox<T> {
+        &self.hitbox
+    }
+
+    fn hitbox_mut(&mut self) -> &mut HitBox<T> {
+        &mut self.hitbox
+    }
+}
+
+/// Look is how PhysicsSystem::paint paints an entity with a HitBox:
+/// an image at the position of the hitbox.
+#[derive(Clone)]
+pub struct Look {
+    pub image: SpriteImageRef,
+    pub layer: Layer,
+}
+
+/// PhysicsSystem moves the entities with a HitBox<T> component with
+/// Box2DPhysics, through a Universe of Bodies kept in sync with them.
+///
+/// The physics is configured through universe_mut, e.g. to set the
+/// gravity or a fixed step; its events, constraints and controllers
+/// refer to the bodies by ObjectKey, see key and entity.
+///
+/// The hitboxes of the entities are moved into the bodies for each
+/// run, rather than copied. Between runs, the bodies only follow the
+/// movement and tile contacts of the entities. Their other fields stay
+/// those the hitboxes had when the bodies were added.
+pub struct PhysicsSystem<T> {
+    universe: Universe<Box2DPhysics<T>, Body<T>>,
+    keys: BTreeMap<Entity, ObjectKey>,
+    entities: HashMap<ObjectKey, Entity>,
+}
+
+impl<T> PhysicsSystem<T>
+where
+    T: Float,
+{
+    pub fn new(physics: Box2DPhysics<T>) -> Self {
+        Self {
+            universe: Universe::new(physics),
+            keys: BTreeMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    pub fn universe(&self) -> &Universe<Box2DPhysics<T>, Body<T>> {
+        &self.universe
+    }
+
+    pub fn universe_mut(&mut self) -> &mut Universe<Box2DPhysics<T>, Body<T>> {
+        &mut self.universe
+    }
+
+    /// key returns the ObjectKey of the body of an entity,
+    /// once the system ran since its HitBox was inserted
+    pub fn key(&self, entity: Entity) -> Option<ObjectKey> {
+        self.keys.get(&entity).copied()
+    }
+
+    pub fn entity(&self, key: ObjectKey) -> Option<Entity> {
+        self.entities.get(&key).copied()
+    }
+
+    /// paint paints the entities with both a HitBox and a Look,
+    /// interpolated between the last two fixed steps like Universe::paint
+    pub fn paint(&self, world: &World, viewport: &Viewport) -> Scene {
+        let alpha = self.universe.alpha();
+        paint_sprites(
+            viewport,
+            world.query2::<HitBox<T>, Look>().map(|(_, hitbox, look)| {
+                Sprite::new(
+                    hitbox.mov.lerp_pos(alpha).round(),
+                    look.layer,
+                    look.image.clone(),
+                )
+            }),
+        )
+    }
+
+    // Move the hitboxes of the world into the bodies, adding bodies for
+    // new ones and destroying those of the entities which lost theirs
+    fn sync_in(&mut self, world: &mut World) {
+        let gone: Vec<Entity> = self
+            .keys
+            .keys()
+            .filter(|entity| !world.has::<HitBox<T>>(**entity))
+            .copied()
+            .collect();
+        for entity in gone {
+            let key = self.keys.remove(&entity).unwrap();
+            self.entities.remove(&key);
+            self.universe.space_mut().destroy(key);
+        }
+        let space = self.universe.space_mut();
+        for (entity, hitbox) in world.query_mut::<HitBox<T>>() {
+            let key = match self.keys.get(&entity).copied() {
+                Some(key) if space.contains(key) => key,
+                stale => {
+                    // The body may have been destroyed through universe_mut
+                    if let Some(key) = stale {
+                        self.entities.remove(&key);
+                    }
+                    let id = space.new_id();
+                    let key = space.add(Body {
+                        id,
+                        entity,
+                        hitbox: hitbox.clone(),
+                    });
+                    self.keys.insert(entity, key);
+                    self.entities.insert(key, entity);
+                    key
+                },
+            };
+            let body = space.get_mut(key).unwrap();
+            std::mem::swap(&mut body.hitbox, hitbox);
+        }
+    }
+
+    // Move the hitboxes of the bodies back into the world, and copy
+    // their movement, so that the bodies stay where they are for
+    // spatial queries too
+    fn sync_out(&mut self, world: &mut World) {
+        let space = self.universe.space_mut();
+        for (entity, hitbox) in world.query_mut::<HitBox<T>>() {
+            if let Some(body) = self.keys.get(&entity).and_then(|key| space.get_mut(*key)) {
+                std::mem::swap(&mut body.hitbox, hitbox);
+                body.hitbox.mov.clone_from(&hitbox.mov);
+                body.hitbox.tile_contacts = hitbox.tile_contacts;
+            }
+        }
+        space.reindex();
+    }
+}
+
+impl<T> System for PhysicsSystem<T>
+where
+    T: Float,
+{
+    fn run(&mut self, world: &mut World, delta_t: f32) {
+        self.sync_in(world);
+        self.universe.tick(delta_t);
+        self.sync_out(world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::{Events, Pump};
+    use crate::graphics::color::Rgba32;
+    use crate::graphics::SpriteImage;
+    use crate::physics::box2d::{Mass, Mov};
+    use crate::vector::v2::V2 as V;
+
+    fn hitbox(x: f32, y: f32, size: usize, mass: Mass<f32>) -> HitBox<f32> {
+        HitBox::new(
+            size,
+            size,
+            Mov::new(V::new(x, y), V::zero(), V::zero()),
+            mass,
+        )
+    }
+
+    #[test]
+    fn test_physics_system() {
+        let mut world = World::new();
+        let mut physics = PhysicsSystem::new(Box2DPhysics::new(0.0));
+        physics
+            .universe_mut()
+            .physics_mut()
+            .set_gravity(V::new(0.0, 0.001));
+        let (mut events, _) = Events::new();
+        physics
+            .universe_mut()
+            .physics_mut()
+            .attach_events(&mut events);
+        let collisions = events.collision_events();
+
+        let floor = world.spawn();
+        world.insert(floor, hitbox(-50.0, 10.0, 100, Mass::Infinite));
+        let ball = world.spawn();
+        world.insert(ball, hitbox(0.0, -20.0, 10, Mass::Fixed(1.0)));
+        // Entities without a HitBox are left alone
+        let ghost = world.spawn();
+        world.insert(
+            ghost,
+            Look {
+                image: SpriteImage::monochrome(1, 1, Rgba32::rgb(0, 0, 0)),
+                layer: Layer::L0,
+            },
+        );
+
+        for _ in 0..100 {
+            physics.run(&mut world, 16.0);
+        }
+        let pos = world.get::<HitBox<f32>>(ball).unwrap().mov.pos;
+        assert!((pos.y - 0.0).abs() < 1.0, "{pos:?}");
+        let key = physics.key(ball).unwrap();
+        assert_eq!(
+            physics.universe().space().get(key).unwrap().hitbox.mov.pos,
+            pos
+        );
+        // Found where it is, with its size, between runs
+        let center = pos + V::new(5.0, 5.0);
+        assert_eq!(physics.universe().space().at_point(center), vec![key]);
+        events.pump();
+        let collision = collisions.recv().unwrap();
+        let mut collided = [physics.entity(collision.a), physics.entity(collision.b)];
+        collided.sort();
+        assert_eq!(collided, [Some(floor), Some(ball)]);
+
+        // Removing the HitBox removes the body
+        world.remove::<HitBox<f32>>(ball);
+        physics.run(&mut world, 16.0);
+        assert!(physics.key(ball).is_none());
+        assert!(!physics.universe().space().contains(key));
+        assert_eq!(physics.universe().space().len(), 1);
+    }
+
+    #[test]
+    fn test_body_destroyed_through_universe() {
+        let mut world = World::new();
+        let mut physics = PhysicsSystem::new(Box2DPhysics::<f32>::new(0.0));
+        let ball = world.spawn();
+        world.insert(ball, hitbox(0.0, 0.0, 10, Mass::Fixed(1.0)));
+        physics.run(&mut world, 16.0);
+        let key = physics.key(ball).unwrap();
+
+        physics.universe_mut().space_mut().destroy(key);
+        physics.run(&mut world, 16.0);
+        let new_key = physics.key(ball).unwrap();
+        assert_ne!(new_key, key);
+        assert!(physics.entity(key).is_none());
+        assert_eq!(physics.entity(new_key), Some(ball));
+        assert_eq!(physics.universe().space().len(), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use std::any::Any;
+
+use super::Entity;
+
+// AnyStorage lets the World keep storages of all component types together
+pub(super) trait AnyStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// Storage keeps the components of one type in slots indexed by entity,
+// along with the generation of the entity they belong to
+pub(super) struct Storage<C> {
+    slots: Vec<Option<(u32, C)>>,
+}
+
+impl<C> Storage<C> {
+    pub(super) fn new() -> Self {
+        Self { slots: vec![] }
+    }
+
+    pub(super) fn get(&self, entity: Entity) -> Option<&C> {
+        match self.slots.get(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub(super) fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        match self.slots.get_mut(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub(super) fn insert(&mut self, entity: Entity, component: C) -> Option<C> {
+        let index = entity.index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        match self.slots[index].replace((entity.generation, component)) {
+            Some((generation, old)) if generation == entity.generation => Some(old),
+            _ => None,
+        }
+    }
+
+    pub(super) fn remove(&mut self, entity: Entity) -> Option<C> {
+        self.get(entity)?;
+        self.slots[entity.index as usize]
+            .take()
+            .map(|(_, component)| component)
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = (Entity, &C)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let (generation, component) = slot.as_ref()?;
+            Some((Entity::new(index, *generation), component))
+        })
+    }
+
+    pub(super) fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut C)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (generation, component) = slot.as_mut()?;
+                Some((Entity::new(index, *generation), component))
+            })
+    }
+}
+
+impl<C> AnyStorage for Storage<C>
+where
+    C: 'static,
+{
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -1,6 +1,11 @@
 use std::rc::Rc;
 
+use bincode::{Decode, Encode};
+
+use crate::input::gamepad::{GamepadAxis, GamepadButton};
 use crate::input::keyboard::KeyCode;
+use crate::physics::constraint::ConstraintKey;
+use crate::physics::identity::ObjectKey;
 use crate::vector::v2::V2;
 
 #[derive(Clone, Copy, Debug)]
@@ -16,12 +21,14 @@ pub struct KeyEvent {
     pub ts: f32,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
 #[repr(u8)]
 pub enum MouseButton {
     Left = 0,
     Middle = 1,
     Right = 2,
+    Back = 3,
+    Forward = 4,
 }
 
 impl From<MouseButton> for u8 {
@@ -30,6 +37,8 @@ impl From<MouseButton> for u8 {
             MouseButton::Left => 0,
             MouseButton::Middle => 1,
             MouseButton::Right => 2,
+            MouseButton::Back => 3,
+            MouseButton::Forward => 4,
         }
     }
 }
@@ -39,15 +48,137 @@ pub enum MouseEventKind {
     Up(MouseButton),
     Down(MouseButton),
     Move,
+    /// Wheel scroll delta, in the units reported by the browser
+    Wheel(V2<f32>),
+    /// Motion delta in scene pixels, sent instead of Move
+    /// while the pointer is locked
+    RelativeMove(V2<f32>),
 }
 
 #[derive(Clone, Debug)]
 pub struct MouseEvent {
     pub kind: MouseEventKind,
+    /// Position relative to the scene size, from (0, 0) to (1, 1)
+    pub pos: V2<f32>,
+    /// Position in scene pixels
+    pub scene_pos: V2<f32>,
+    pub ts: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchEventKind {
+    Start,
+    Move,
+    End,
+    Cancel,
+}
+
+/// A single touch point changing; id is the identifier of
+/// the touch point, stable from Start until End or Cancel.
+#[derive(Clone, Debug)]
+pub struct TouchEvent {
+    pub kind: TouchEventKind,
+    pub id: i32,
+    /// Position relative to the scene size, from (0, 0) to (1, 1)
     pub pos: V2<f32>,
+    /// Position in scene pixels
+    pub scene_pos: V2<f32>,
+    pub ts: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEventKind {
+    Connect,
+    Disconnect,
+    ButtonDown(GamepadButton),
+    ButtonUp(GamepadButton),
+    Axis(GamepadAxis, f32),
+}
+
+/// An event from the gamepad at index gamepad, as numbered
+/// by the browser Gamepad API.
+#[derive(Clone, Debug)]
+pub struct GamepadEvent {
+    pub kind: GamepadEventKind,
+    pub gamepad: u32,
+    pub ts: f32,
+}
+
+/// Editing keys for text input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEdit {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    SelectAll,
+    Enter,
+}
+
+impl TextEdit {
+    /// Parse the name of a key, as in JS KeyboardEvent.key
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "Backspace" => Some(Self::Backspace),
+            "Delete" => Some(Self::Delete),
+            "ArrowLeft" => Some(Self::Left),
+            "ArrowRight" => Some(Self::Right),
+            "Home" => Some(Self::Home),
+            "End" => Some(Self::End),
+            "SelectAll" => Some(Self::SelectAll),
+            "Enter" => Some(Self::Enter),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextEventKind {
+    /// Composed characters typed by the user
+    Input(String),
+    Paste(String),
+    /// An editing key; with select, movement extends the selection
+    Edit {
+        edit: TextEdit,
+        select: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct TextEvent {
+    pub kind: TextEventKind,
     pub ts: f32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionEventKind {
+    Begin,
+    Stay,
+    End,
+}
+
+/// Sent by the physics engine when two objects touch;
+/// the contact normal points from a to b.
+#[derive(Clone, Debug)]
+pub struct CollisionEvent {
+    pub a: ObjectKey,
+    pub b: ObjectKey,
+    pub kind: CollisionEventKind,
+    pub normal: V2<f32>,
+}
+
+/// Sent by the physics engine when a constraint breaks, having
+/// pulled or pushed with more than its max_force.
+#[derive(Clone, Debug)]
+pub struct ConstraintBreakEvent {
+    pub constraint: ConstraintKey,
+    pub a: ObjectKey,
+    pub b: Option<ObjectKey>,
+    pub force: f32,
+}
+
 #[derive(Clone, Debug)]
 pub struct WindowResizeEvent {
     pub width: usize,
@@ -61,18 +192,40 @@ pub struct FileReadEvent {
     pub filename: String,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum EventType {
     Key,
     Mouse,
+    Touch,
+    Gamepad,
+    Text,
+    Collision,
+    ConstraintBreak,
     WindowResize,
     FileRead,
 }
 
+pub static EVENT_TYPES: [EventType; 9] = [
+    EventType::Key,
+    EventType::Mouse,
+    EventType::Touch,
+    EventType::Gamepad,
+    EventType::Text,
+    EventType::Collision,
+    EventType::ConstraintBreak,
+    EventType::WindowResize,
+    EventType::FileRead,
+];
+
 #[derive(Clone, Debug)]
 pub enum Event {
     Key(KeyEvent),
     Mouse(MouseEvent),
+    Touch(TouchEvent),
+    Gamepad(GamepadEvent),
+    Text(TextEvent),
+    Collision(CollisionEvent),
+    ConstraintBreak(ConstraintBreakEvent),
     WindowResize(WindowResizeEvent),
     FileRead(FileReadEvent),
 }
@@ -82,6 +235,11 @@ impl Event {
         match self {
             Self::Key(_) => EventType::Key,
             Self::Mouse(_) => EventType::Mouse,
+            Self::Touch(_) => EventType::Touch,
+            Self::Gamepad(_) => EventType::Gamepad,
+            Self::Text(_) => EventType::Text,
+            Self::Collision(_) => EventType::Collision,
+            Self::ConstraintBreak(_) => EventType::ConstraintBreak,
             Self::WindowResize(_) => EventType::WindowResize,
             Self::FileRead(_) => EventType::FileRead,
         }
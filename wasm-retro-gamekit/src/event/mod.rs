@@ -1,16 +1,24 @@
 mod events;
 mod plumbing;
 mod queue;
+#[cfg(feature = "console-events")]
+mod trace;
 
 pub use self::events::*;
 pub use self::plumbing::*;
 // TODO: use mpsc::channel instead if not feature = "js"
 pub use self::queue::*;
+#[cfg(feature = "console-events")]
+pub use self::trace::console_log;
+#[cfg(feature = "console-events")]
+use self::trace::EventTracer;
 use crate::input::keyboard::{KeyMap, MappedKeyEvent};
 
 pub struct Events {
     mains: Joint<Event>,
     plumbing: Plumbing,
+    #[cfg(feature = "console-events")]
+    tracer: EventTracer,
 }
 
 impl Events {
@@ -19,13 +27,47 @@ impl Events {
         let (sink, source) = plumbing.pipe::<Event>();
         let mut mains = Joint::new();
         mains.add_source(source);
-        (Self { plumbing, mains }, sink)
+        #[cfg(feature = "console-events")]
+        let tracer = {
+            let (t_sink, t_source) = plumbing.pipe::<Event>();
+            mains.add_sink(t_sink);
+            EventTracer::new(t_source)
+        };
+        (
+            Self {
+                plumbing,
+                mains,
+                #[cfg(feature = "console-events")]
+                tracer,
+            },
+            sink,
+        )
     }
 
     pub fn plumbing(&mut self) -> &mut Plumbing {
         &mut self.plumbing
     }
 
+    /// sink returns a new sink for events sent from within the game,
+    /// e.g. by the physics engine; they are delivered with the next pump.
+    pub fn sink(&mut self) -> Sink<Event> {
+        let (sink, source) = self.plumbing.pipe::<Event>();
+        self.mains.add_source(source);
+        sink
+    }
+
+    /// Only log events of the given types; by default all events are logged.
+    #[cfg(feature = "console-events")]
+    pub fn trace(&mut self, types: &[EventType]) {
+        self.tracer.set_types(types);
+    }
+
+    /// Log the pipes, pumps and queue depths of the event plumbing.
+    #[cfg(feature = "console-events")]
+    pub fn trace_plumbing(&self) {
+        console_log(&self.plumbing.to_string());
+    }
+
     pub fn mouse_events(&mut self) -> Source<MouseEvent> {
         let (e_sink, e_source) = self.plumbing.pipe::<Event>();
         let (m_sink, m_source) = self.plumbing.pipe::<MouseEvent>();
@@ -35,6 +77,51 @@ impl Events {
         m_source
     }
 
+    pub fn touch_events(&mut self) -> Source<TouchEvent> {
+        let (e_sink, e_source) = self.plumbing.pipe::<Event>();
+        let (t_sink, t_source) = self.plumbing.pipe::<TouchEvent>();
+        self.mains.add_sink(e_sink);
+        self.plumbing
+            .filter(e_source, t_sink, FnFilter(filter_touch_events));
+        t_source
+    }
+
+    pub fn gamepad_events(&mut self) -> Source<GamepadEvent> {
+        let (e_sink, e_source) = self.plumbing.pipe::<Event>();
+        let (g_sink, g_source) = self.plumbing.pipe::<GamepadEvent>();
+        self.mains.add_sink(e_sink);
+        self.plumbing
+            .filter(e_source, g_sink, FnFilter(filter_gamepad_events));
+        g_source
+    }
+
+    pub fn collision_events(&mut self) -> Source<CollisionEvent> {
+        let (e_sink, e_source) = self.plumbing.pipe::<Event>();
+        let (c_sink, c_source) = self.plumbing.pipe::<CollisionEvent>();
+        self.mains.add_sink(e_sink);
+        self.plumbing
+            .filter(e_source, c_sink, FnFilter(filter_collision_events));
+        c_source
+    }
+
+    pub fn constraint_break_events(&mut self) -> Source<ConstraintBreakEvent> {
+        let (e_sink, e_source) = self.plumbing.pipe::<Event>();
+        let (c_sink, c_source) = self.plumbing.pipe::<ConstraintBreakEvent>();
+        self.mains.add_sink(e_sink);
+        self.plumbing
+            .filter(e_source, c_sink, FnFilter(filter_constraint_break_events));
+        c_source
+    }
+
+    pub fn text_events(&mut self) -> Source<TextEvent> {
+        let (e_sink, e_source) = self.plumbing.pipe::<Event>();
+        let (t_sink, t_source) = self.plumbing.pipe::<TextEvent>();
+        self.mains.add_sink(e_sink);
+        self.plumbing
+            .filter(e_source, t_sink, FnFilter(filter_text_events));
+        t_source
+    }
+
     pub fn key_events(&mut self) -> Source<KeyEvent> {
         let (e_sink, e_source) = self.plumbing.pipe::<Event>();
         let (k_sink, k_source) = self.plumbing.pipe::<KeyEvent>();
@@ -76,6 +163,8 @@ impl Events {
 impl Pump for Events {
     fn pump(&mut self) {
         self.mains.pump();
+        #[cfg(feature = "console-events")]
+        self.tracer.pump();
         self.plumbing.pump();
     }
 }
@@ -87,6 +176,41 @@ fn filter_mouse_events(e: Event) -> Option<MouseEvent> {
     }
 }
 
+fn filter_touch_events(e: Event) -> Option<TouchEvent> {
+    match e {
+        Event::Touch(tevent) => Some(tevent),
+        _ => None,
+    }
+}
+
+fn filter_gamepad_events(e: Event) -> Option<GamepadEvent> {
+    match e {
+        Event::Gamepad(gevent) => Some(gevent),
+        _ => None,
+    }
+}
+
+fn filter_collision_events(e: Event) -> Option<CollisionEvent> {
+    match e {
+        Event::Collision(cevent) => Some(cevent),
+        _ => None,
+    }
+}
+
+fn filter_constraint_break_events(e: Event) -> Option<ConstraintBreakEvent> {
+    match e {
+        Event::ConstraintBreak(cevent) => Some(cevent),
+        _ => None,
+    }
+}
+
+fn filter_text_events(e: Event) -> Option<TextEvent> {
+    match e {
+        Event::Text(tevent) => Some(tevent),
+        _ => None,
+    }
+}
+
 fn filter_key_events(e: Event) -> Option<KeyEvent> {
     match e {
         Event::Key(kevent) => Some(kevent),
@@ -1,7 +1,9 @@
-use std::cell::RefCell;
+use std::any::type_name;
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
+use std::fmt;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 use super::{Queue, Sink, Source};
 
@@ -45,6 +47,27 @@ where
     }
 }
 
+struct FlatFilterPump<F, T, U> {
+    source: Source<T>,
+    sink: Sink<U>,
+    filter: F,
+    t: PhantomData<T>,
+    u: PhantomData<U>,
+}
+
+impl<F, T, U> Pump for FlatFilterPump<F, T, U>
+where
+    F: Filter<T, Vec<U>>,
+{
+    fn pump(&mut self) {
+        while let Some(t_event) = self.source.recv() {
+            for u_event in self.filter.filter(t_event).into_iter().flatten() {
+                self.sink.send(u_event);
+            }
+        }
+    }
+}
+
 pub(super) struct Joint<T> {
     sources: Vec<Source<T>>,
     sinks: Vec<Sink<T>>,
@@ -81,20 +104,88 @@ where
     }
 }
 
+/// Description of a pipe created by Plumbing, for debugging;
+/// pipes whose ends were both dropped aren't described.
+#[derive(Clone, Debug)]
+pub struct PipeInfo {
+    pub id: usize,
+    pub item_type: &'static str,
+    /// Number of items waiting in the pipe
+    pub depth: usize,
+}
+
+/// Description of a pump registered with Plumbing, for debugging.
+/// Source and sink are pipe ids, if the pipes were created by
+/// the same Plumbing.
+#[derive(Clone, Debug)]
+pub struct PumpInfo {
+    pub id: usize,
+    pub filter: &'static str,
+    pub source: Option<usize>,
+    pub sink: Option<usize>,
+}
+
+// Lets PipeEntry keep queues of any item type
+trait Depth {
+    fn depth(&self) -> usize;
+}
+
+impl<T> Depth for RefCell<VecDeque<T>> {
+    fn depth(&self) -> usize {
+        self.borrow().len()
+    }
+}
+
+struct PipeEntry {
+    id: usize,
+    item_type: &'static str,
+    queue: Weak<dyn Depth>,
+}
+
+impl PipeEntry {
+    fn is_alive(&self) -> bool {
+        self.queue.strong_count() > 0
+    }
+}
+
+struct PumpEntry {
+    filter: &'static str,
+    source: Option<usize>,
+    sink: Option<usize>,
+    pump: Box<dyn Pump>,
+}
+
 pub struct Plumbing {
-    pumps: Vec<Box<dyn Pump>>,
+    pipes: RefCell<Vec<PipeEntry>>,
+    next_pipe_id: Cell<usize>,
+    pumps: Vec<PumpEntry>,
 }
 
 impl Plumbing {
     pub fn new() -> Self {
-        Self { pumps: vec![] }
+        Self {
+            pipes: RefCell::new(vec![]),
+            next_pipe_id: Cell::new(0),
+            pumps: vec![],
+        }
     }
 
-    pub fn pipe<T>(&self) -> (Sink<T>, Source<T>) {
-        {
-            let q: Queue<T> = Queue(Rc::new(RefCell::new(VecDeque::new())));
-            (Sink(q.clone()), Source(q))
-        }
+    pub fn pipe<T>(&self) -> (Sink<T>, Source<T>)
+    where
+        T: 'static,
+    {
+        let q: Queue<T> = Queue(Rc::new(RefCell::new(VecDeque::new())));
+        let queue: Weak<RefCell<VecDeque<T>>> = Rc::downgrade(&q.0);
+        let id = self.next_pipe_id.get();
+        self.next_pipe_id.set(id + 1);
+        let mut pipes = self.pipes.borrow_mut();
+        pipes.retain(PipeEntry::is_alive);
+        pipes.push(PipeEntry {
+            id,
+            item_type: type_name::<T>(),
+            queue,
+        });
+        (Sink(q.clone()), Source(q))
     }
 
     pub fn filter<F, T, U>(&mut self, source: Source<T>, sink: Sink<U>, filter: F)
@@ -103,6 +194,8 @@ impl Plumbing {
         T: 'static,
         U: 'static,
     {
+        let source_id = self.pipe_id(source.0.addr());
+        let sink_id = self.pipe_id(sink.0.addr());
         let p = FilterPump {
             filter,
             source,
@@ -110,14 +203,171 @@ impl Plumbing {
             t: PhantomData,
             u: PhantomData,
         };
-        self.pumps.push(Box::new(p));
+        self.pumps.push(PumpEntry {
+            filter: type_name::<F>(),
+            source: source_id,
+            sink: sink_id,
+            pump: Box::new(p),
+        });
+    }
+
+    /// Like filter, for filters which can output many events for each input event.
+    pub fn flat_filter<F, T, U>(&mut self, source: Source<T>, sink: Sink<U>, filter: F)
+    where
+        F: Filter<T, Vec<U>> + 'static,
+        T: 'static,
+        U: 'static,
+    {
+        let source_id = self.pipe_id(source.0.addr());
+        let sink_id = self.pipe_id(sink.0.addr());
+        let p = FlatFilterPump {
+            filter,
+            source,
+            sink,
+            t: PhantomData,
+            u: PhantomData,
+        };
+        self.pumps.push(PumpEntry {
+            filter: type_name::<F>(),
+            source: source_id,
+            sink: sink_id,
+            pump: Box::new(p),
+        });
+    }
+
+    /// Register a pump which does not fit the one-in, at-most-one-out
+    /// shape of a Filter.
+    pub fn add_pump<P>(&mut self, pump: P)
+    where
+        P: Pump + 'static,
+    {
+        self.pumps.push(PumpEntry {
+            filter: type_name::<P>(),
+            source: None,
+            sink: None,
+            pump: Box::new(pump),
+        });
+    }
+
+    // Dead entries are pruned first, so that a queue allocated where
+    // a dropped one was doesn't take its id
+    fn pipe_id(&self, addr: *const ()) -> Option<usize> {
+        let mut pipes = self.pipes.borrow_mut();
+        pipes.retain(PipeEntry::is_alive);
+        pipes
+            .iter()
+            .find(|p| p.queue.as_ptr() as *const () == addr)
+            .map(|p| p.id)
+    }
+
+    pub fn pipes(&self) -> Vec<PipeInfo> {
+        let mut pipes = self.pipes.borrow_mut();
+        pipes.retain(PipeEntry::is_alive);
+        pipes
+            .iter()
+            .filter_map(|p| {
+                Some(PipeInfo {
+                    id: p.id,
+                    item_type: p.item_type,
+                    depth: p.queue.upgrade()?.depth(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn pumps(&self) -> Vec<PumpInfo> {
+        self.pumps
+            .iter()
+            .enumerate()
+            .map(|(id, p)| PumpInfo {
+                id,
+                filter: p.filter,
+                source: p.source,
+                sink: p.sink,
+            })
+            .collect()
     }
 }
 
 impl Pump for Plumbing {
     fn pump(&mut self) {
         for p in self.pumps.iter_mut() {
-            p.pump();
+            p.pump.pump();
+        }
+    }
+}
+
+fn fmt_pipe_id(id: Option<usize>) -> String {
+    match id {
+        Some(id) => format!("#{}", id),
+        None => "?".to_string(),
+    }
+}
+
+impl fmt::Display for Plumbing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pipes:")?;
+        for pipe in self.pipes() {
+            writeln!(
+                f,
+                "  #{} {} ({} queued)",
+                pipe.id, pipe.item_type, pipe.depth
+            )?;
         }
+        writeln!(f, "pumps:")?;
+        for pump in self.pumps() {
+            writeln!(
+                f,
+                "  #{} {}: {} -> {}",
+                pump.id,
+                pump.filter,
+                fmt_pipe_id(pump.source),
+                fmt_pipe_id(pump.sink)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plumbing_introspection() {
+        let mut plumbing = Plumbing::new();
+        let (a_sink, a_source) = plumbing.pipe::<u32>();
+        let (b_sink, b_source) = plumbing.pipe::<u64>();
+        plumbing.filter(a_source, b_sink, FnFilter(|x: u32| Some(x as u64)));
+        a_sink.send(1);
+        a_sink.send(2);
+
+        let pipes = plumbing.pipes();
+        assert_eq!(pipes.len(), 2);
+        assert_eq!(pipes[0].item_type, "u32");
+        assert_eq!(pipes[0].depth, 2);
+        assert_eq!(pipes[1].depth, 0);
+
+        let pumps = plumbing.pumps();
+        assert_eq!(pumps.len(), 1);
+        assert_eq!(pumps[0].source, Some(0));
+        assert_eq!(pumps[0].sink, Some(1));
+
+        plumbing.pump();
+        let pipes = plumbing.pipes();
+        assert_eq!(pipes[0].depth, 0);
+        assert_eq!(pipes[1].depth, 2);
+
+        // Closed pipes are forgotten, and their ids aren't reused
+        drop(a_sink);
+        drop(plumbing.pumps.pop());
+        let pipes = plumbing.pipes();
+        assert_eq!(pipes.len(), 1);
+        assert_eq!(pipes[0].id, 1);
+        drop(b_source);
+        let (c_sink, c_source) = plumbing.pipe::<u32>();
+        plumbing.filter(c_source, c_sink, FnFilter(Some));
+        assert!(plumbing.pipes().iter().all(|p| p.id == 2));
+        assert_eq!(plumbing.pumps()[0].source, Some(2));
     }
 }
@@ -4,6 +4,12 @@ use std::rc::Rc;
 
 pub(super) struct Queue<T>(pub(super) Rc<RefCell<VecDeque<T>>>);
 
+impl<T> Queue<T> {
+    pub(super) fn addr(&self) -> *const () {
+        Rc::as_ptr(&self.0) as *const ()
+    }
+}
+
 impl<T> Clone for Queue<T> {
     fn clone(&self) -> Self {
         Self(self.0.clone())
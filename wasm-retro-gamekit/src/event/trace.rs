@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+
+use super::{Event, EventType, Pump, Source, EVENT_TYPES};
+
+/// Log a message to the browser console, or to stderr
+/// when not running in the browser.
+pub fn console_log(msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(msg));
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", msg);
+}
+
+/// EventTracer logs every event it receives whose type
+/// is selected; all types are selected by default.
+pub(super) struct EventTracer {
+    source: Source<Event>,
+    types: HashSet<EventType>,
+}
+
+impl EventTracer {
+    pub fn new(source: Source<Event>) -> Self {
+        Self {
+            source,
+            types: EVENT_TYPES.iter().copied().collect(),
+        }
+    }
+
+    pub fn set_types(&mut self, types: &[EventType]) {
+        self.types = types.iter().copied().collect();
+    }
+}
+
+impl Pump for EventTracer {
+    fn pump(&mut self) {
+        while let Some(event) = self.source.recv() {
+            if self.types.contains(&event.event_type()) {
+                console_log(&format!("{:?}", event));
+            }
+        }
+    }
+}
@@ -28,6 +28,20 @@ impl Viewport {
         self.pos + V2::new(relx, rely)
     }
 
+    /// scene_to_world converts a position in scene pixels,
+    /// e.g. MouseEvent::scene_pos, into a world position
+    pub fn scene_to_world<T>(&self, scene_pos: V2<T>) -> V2<i64>
+    where
+        T: Float,
+    {
+        self.pos + scene_pos.round()
+    }
+
+    /// world_to_scene converts a world position into a position in scene pixels
+    pub fn world_to_scene(&self, world_pos: V2<i64>) -> V2<i64> {
+        world_pos - self.pos
+    }
+
     pub fn overlaps(&self, sprite: &Sprite) -> bool {
         let image = sprite.image();
         let stl = sprite.pos() - self.pos;
@@ -74,7 +74,7 @@ where
     T: Default + Clone,
 {
     pub fn new(width: usize, height: usize) -> Self {
-        assert!(height % 2 == 0, "must have even height");
+        assert!(height.is_multiple_of(2), "must have even height");
         Self {
             width,
             height,
@@ -0,0 +1,549 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+
+use super::gamepad::GamepadButton;
+use super::keyboard::KeyCode;
+use crate::event::{
+    Events,
+    Filter,
+    GamepadEvent,
+    GamepadEventKind,
+    KeyEvent,
+    KeyEventKind,
+    MouseButton,
+    MouseEvent,
+    MouseEventKind,
+    Plumbing,
+    Source,
+};
+
+/// A physical button which can be bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// A Binding presses an action when its input is pressed while
+/// all of its modifiers are held, e.g. Shift+Z. When several
+/// bindings match, only the ones with the most modifiers are used,
+/// so binding Shift+Z does not also trigger a binding for Z.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Binding {
+    pub modifiers: Vec<Input>,
+    pub input: Input,
+}
+
+impl Binding {
+    pub fn new(input: Input) -> Self {
+        Self {
+            modifiers: vec![],
+            input,
+        }
+    }
+
+    pub fn chord(modifiers: &[Input], input: Input) -> Self {
+        Self {
+            modifiers: modifiers.to_vec(),
+            input,
+        }
+    }
+}
+
+/// A Combo presses an action when its inputs are pressed in order,
+/// each one at most window milliseconds after the previous one.
+/// Combos are pressed for a single tick, they are never held.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Combo {
+    pub sequence: Vec<Input>,
+    pub window: f32,
+}
+
+impl Combo {
+    pub fn new(sequence: &[Input], window: f32) -> Self {
+        Self {
+            sequence: sequence.to_vec(),
+            window,
+        }
+    }
+}
+
+/// ActionMap holds the bindings and combos of actions of type T.
+/// It can be changed at runtime and encoded with bincode, to let
+/// players customize controls.
+#[derive(Clone, Debug, Encode, Decode)]
+#[bincode(
+    encode_bounds = "T: Encode + 'static",
+    decode_bounds = "T: Decode + 'static",
+    borrow_decode_bounds = "T: bincode::BorrowDecode<'__de> + 'static"
+)]
+pub struct ActionMap<T> {
+    bindings: Vec<(T, Binding)>,
+    combos: Vec<(T, Combo)>,
+}
+
+impl<T> ActionMap<T>
+where
+    T: Clone + PartialEq,
+{
+    pub fn new() -> Self {
+        Self {
+            bindings: vec![],
+            combos: vec![],
+        }
+    }
+    pub fn bind(&mut self, action: &T, binding: Binding) {
+        self.bindings.push((action.clone(), binding));
+    }
+    pub fn bind_combo(&mut self, action: &T, combo: Combo) {
+        self.combos.push((action.clone(), combo));
+    }
+    /// Remove all bindings and combos of an action.
+    pub fn unbind(&mut self, action: &T) {
+        self.bindings.retain(|(a, _)| a != action);
+        self.combos.retain(|(a, _)| a != action);
+    }
+    /// Replace all bindings of an action, keeping its combos.
+    pub fn rebind(&mut self, action: &T, bindings: &[Binding]) {
+        self.bindings.retain(|(a, _)| a != action);
+        for binding in bindings {
+            self.bind(action, binding.clone());
+        }
+    }
+    pub fn bindings(&self, action: &T) -> Vec<&Binding> {
+        self.bindings
+            .iter()
+            .filter(|(a, _)| a == action)
+            .map(|(_, b)| b)
+            .collect()
+    }
+    pub fn combos(&self, action: &T) -> Vec<&Combo> {
+        self.combos
+            .iter()
+            .filter(|(a, _)| a == action)
+            .map(|(_, c)| c)
+            .collect()
+    }
+}
+
+impl<T> ActionMap<T>
+where
+    T: Encode + 'static,
+{
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_to_vec(self, config::standard())
+    }
+}
+
+impl<T> ActionMap<T>
+where
+    T: Decode + 'static,
+{
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        decode_from_slice(bytes, config::standard()).map(|(map, _)| map)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InputEvent {
+    pub input: Input,
+    pub kind: KeyEventKind,
+    pub ts: f32,
+}
+
+/// InputFilter turns key, mouse button and gamepad button events
+/// into InputEvents.
+pub struct InputFilter;
+
+impl Filter<KeyEvent, InputEvent> for InputFilter {
+    fn filter(&mut self, t: KeyEvent) -> Option<InputEvent> {
+        Some(InputEvent {
+            input: Input::Key(t.code),
+            kind: t.kind,
+            ts: t.ts,
+        })
+    }
+}
+
+impl Filter<MouseEvent, InputEvent> for InputFilter {
+    fn filter(&mut self, t: MouseEvent) -> Option<InputEvent> {
+        let (input, kind) = match t.kind {
+            MouseEventKind::Down(button) => (Input::Mouse(button), KeyEventKind::Down),
+            MouseEventKind::Up(button) => (Input::Mouse(button), KeyEventKind::Up),
+            _ => return None,
+        };
+        Some(InputEvent {
+            input,
+            kind,
+            ts: t.ts,
+        })
+    }
+}
+
+impl Filter<GamepadEvent, InputEvent> for InputFilter {
+    fn filter(&mut self, t: GamepadEvent) -> Option<InputEvent> {
+        let (input, kind) = match t.kind {
+            GamepadEventKind::ButtonDown(button) => (Input::Gamepad(button), KeyEventKind::Down),
+            GamepadEventKind::ButtonUp(button) => (Input::Gamepad(button), KeyEventKind::Up),
+            _ => return None,
+        };
+        Some(InputEvent {
+            input,
+            kind,
+            ts: t.ts,
+        })
+    }
+}
+
+/// Actions tracks the state of actions of type T, according to an ActionMap.
+///
+/// Actions::update must be called once per tick; "pressed" and
+/// "released" queries refer to what happened since the previous update.
+pub struct Actions<T> {
+    map: ActionMap<T>,
+    events: Source<InputEvent>,
+    inputs_down: HashSet<Input>,
+    // Actions pressed by each held input, so that releasing works
+    // even if bindings changed in between
+    held_by_input: HashMap<Input, Vec<T>>,
+    holders: HashMap<T, u32>,
+    held_since: HashMap<T, f32>,
+    pressed: HashMap<T, u32>,
+    released: HashSet<T>,
+    combo_progress: Vec<(usize, f32)>,
+    pressed_inputs: Vec<Input>,
+}
+
+impl<T> Actions<T>
+where
+    T: Clone + PartialEq + Eq + Hash,
+{
+    pub fn new(map: ActionMap<T>, events: Source<InputEvent>) -> Self {
+        Self {
+            map,
+            events,
+            inputs_down: HashSet::new(),
+            held_by_input: HashMap::new(),
+            holders: HashMap::new(),
+            held_since: HashMap::new(),
+            pressed: HashMap::new(),
+            released: HashSet::new(),
+            combo_progress: vec![],
+            pressed_inputs: vec![],
+        }
+    }
+    pub fn map(&self) -> &ActionMap<T> {
+        &self.map
+    }
+    /// map_mut lets the map be changed; combos in progress start over
+    pub fn map_mut(&mut self) -> &mut ActionMap<T> {
+        self.combo_progress.clear();
+        &mut self.map
+    }
+    pub fn update(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.pressed_inputs.clear();
+        self.combo_progress
+            .resize(self.map.combos.len(), (0, f32::NEG_INFINITY));
+        for event in drain_sorted(&self.events) {
+            match event.kind {
+                KeyEventKind::Down => self.input_down(event.input, event.ts),
+                KeyEventKind::Up => self.input_up(event.input),
+            }
+        }
+    }
+    fn input_down(&mut self, input: Input, ts: f32) {
+        // Ignore repeated down events
+        if !self.inputs_down.insert(input) {
+            return;
+        }
+        self.pressed_inputs.push(input);
+
+        let matching: Vec<&(T, Binding)> = self
+            .map
+            .bindings
+            .iter()
+            .filter(|(_, b)| {
+                b.input == input && b.modifiers.iter().all(|m| self.inputs_down.contains(m))
+            })
+            .collect();
+        let max_modifiers = matching.iter().map(|(_, b)| b.modifiers.len()).max();
+        let actions: Vec<T> = matching
+            .iter()
+            .filter(|(_, b)| Some(b.modifiers.len()) == max_modifiers)
+            .map(|(a, _)| a.clone())
+            .collect();
+        for action in actions {
+            *self.pressed.entry(action.clone()).or_insert(0) += 1;
+            let holders = self.holders.entry(action.clone()).or_insert(0);
+            if *holders == 0 {
+                self.held_since.insert(action.clone(), ts);
+            }
+            *holders += 1;
+            self.held_by_input.entry(input).or_default().push(action);
+        }
+
+        for action in self.advance_combos(input, ts) {
+            *self.pressed.entry(action).or_insert(0) += 1;
+        }
+    }
+    fn advance_combos(&mut self, input: Input, ts: f32) -> Vec<T> {
+        let mut completed = vec![];
+        for (i, (action, combo)) in self.map.combos.iter().enumerate() {
+            if combo.sequence.is_empty() {
+                continue;
+            }
+            let (progress, last_ts) = self.combo_progress[i];
+            let in_window = ts - last_ts <= combo.window;
+            let progress = match in_window && progress < combo.sequence.len() {
+                true => advance_combo(&combo.sequence, progress, input),
+                false => advance_combo(&combo.sequence, 0, input),
+            };
+            if progress == combo.sequence.len() {
+                completed.push(action.clone());
+                self.combo_progress[i] = (0, ts);
+            } else {
+                self.combo_progress[i] = (progress, ts);
+            }
+        }
+        completed
+    }
+    fn input_up(&mut self, input: Input) {
+        self.inputs_down.remove(&input);
+        for action in self.held_by_input.remove(&input).unwrap_or_default() {
+            if let Some(holders) = self.holders.get_mut(&action) {
+                *holders -= 1;
+                if *holders == 0 {
+                    self.holders.remove(&action);
+                    self.held_since.remove(&action);
+                    self.released.insert(action);
+                }
+            }
+        }
+    }
+    /// is_pressed returns whether the action was pressed since the last update
+    pub fn is_pressed(&self, action: &T) -> bool {
+        self.pressed.contains_key(action)
+    }
+    /// press_count returns how many times the action was pressed since the last update
+    pub fn press_count(&self, action: &T) -> u32 {
+        self.pressed.get(action).copied().unwrap_or(0)
+    }
+    /// is_released returns whether the action was released since the last update
+    pub fn is_released(&self, action: &T) -> bool {
+        self.released.contains(action)
+    }
+    /// is_held returns whether the action is currently held
+    pub fn is_held(&self, action: &T) -> bool {
+        self.held_since.contains_key(action)
+    }
+    /// held_for returns for how long the action has been held, or None if it's not held
+    pub fn held_for(&self, action: &T, now: f32) -> Option<f32> {
+        self.held_since.get(action).map(|since| now - since)
+    }
+    /// is_held_for returns whether the action has been held for at least duration
+    pub fn is_held_for(&self, action: &T, duration: f32, now: f32) -> bool {
+        self.held_for(action, now)
+            .map(|held| held >= duration)
+            .unwrap_or(false)
+    }
+    /// pressed_inputs returns the physical inputs pressed since the last update,
+    /// bound or not; useful to let players pick new bindings.
+    pub fn pressed_inputs(&self) -> &[Input] {
+        &self.pressed_inputs
+    }
+}
+
+// Like KMP matching: after progress inputs of the sequence matched,
+// returns how many match once input is pressed, falling back to the
+// longest prefix of the sequence ending with input, so that e.g.
+// [Down, Down, Right] matches after Down, Down, Down, Right
+fn advance_combo(sequence: &[Input], progress: usize, input: Input) -> usize {
+    (0..=progress)
+        .rev()
+        .find(|k| sequence[progress - k..progress] == sequence[..*k] && sequence[*k] == input)
+        .map_or(0, |k| k + 1)
+}
+
+/// input_events merges key, mouse button and gamepad button events
+/// into a single source of InputEvents
+pub fn input_events(events: &mut Events) -> Source<InputEvent> {
+    let k_source = events.key_events();
+    let m_source = events.mouse_events();
+    let g_source = events.gamepad_events();
+    let (i_sink, i_source) = events.plumbing().pipe::<InputEvent>();
+    let plumbing: &mut Plumbing = events.plumbing();
+    plumbing.filter(k_source, i_sink.clone(), InputFilter);
+    plumbing.filter(m_source, i_sink.clone(), InputFilter);
+    plumbing.filter(g_source, i_sink, InputFilter);
+    i_source
+}
+
+// Receive all the events of a source from input_events,
+// in the order of their timestamps
+pub(super) fn drain_sorted(events: &Source<InputEvent>) -> Vec<InputEvent> {
+    let mut drained = vec![];
+    while let Some(event) = events.recv() {
+        drained.push(event);
+    }
+    // input_events pumps the events of each device in turn into the
+    // same pipe, so events of different devices are out of order
+    drained.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+    drained
+}
+
+pub fn attach_actions<T>(events: &mut Events, map: ActionMap<T>) -> Actions<T>
+where
+    T: Clone + PartialEq + Eq + Hash,
+{
+    Actions::new(map, input_events(events))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::{Event, Pump, Sink};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
+    enum Action {
+        Attack,
+        Special,
+        Hadoken,
+    }
+
+    fn key(sink: &Sink<Event>, code: KeyCode, kind: KeyEventKind, ts: f32) {
+        sink.send(Event::Key(KeyEvent { kind, code, ts }));
+    }
+
+    fn action_map() -> ActionMap<Action> {
+        let mut map = ActionMap::new();
+        map.bind(&Action::Attack, Binding::new(Input::Key(KeyCode::Z)));
+        map.bind(
+            &Action::Special,
+            Binding::chord(&[Input::Key(KeyCode::Shift)], Input::Key(KeyCode::Z)),
+        );
+        map.bind_combo(
+            &Action::Hadoken,
+            Combo::new(
+                &[
+                    Input::Key(KeyCode::ArrowDown),
+                    Input::Key(KeyCode::ArrowRight),
+                    Input::Key(KeyCode::Z),
+                ],
+                100.0,
+            ),
+        );
+        map
+    }
+
+    #[test]
+    fn test_chord_and_held() {
+        let (mut events, sink) = Events::new();
+        let mut actions = attach_actions(&mut events, action_map());
+
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 0.0);
+        events.pump();
+        actions.update();
+        assert!(actions.is_pressed(&Action::Attack));
+        assert!(!actions.is_pressed(&Action::Special));
+        assert!(actions.is_held_for(&Action::Attack, 100.0, 150.0));
+
+        key(&sink, KeyCode::Z, KeyEventKind::Up, 200.0);
+        key(&sink, KeyCode::Shift, KeyEventKind::Down, 210.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 220.0);
+        events.pump();
+        actions.update();
+        assert!(actions.is_released(&Action::Attack));
+        assert!(!actions.is_pressed(&Action::Attack));
+        assert!(actions.is_pressed(&Action::Special));
+        assert!(actions.is_held(&Action::Special));
+    }
+
+    #[test]
+    fn test_combo() {
+        let (mut events, sink) = Events::new();
+        let mut actions = attach_actions(&mut events, action_map());
+
+        key(&sink, KeyCode::ArrowDown, KeyEventKind::Down, 0.0);
+        key(&sink, KeyCode::ArrowRight, KeyEventKind::Down, 50.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 100.0);
+        events.pump();
+        actions.update();
+        assert!(actions.is_pressed(&Action::Hadoken));
+        assert!(!actions.is_held(&Action::Hadoken));
+
+        key(&sink, KeyCode::ArrowDown, KeyEventKind::Up, 200.0);
+        key(&sink, KeyCode::ArrowRight, KeyEventKind::Up, 200.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Up, 200.0);
+        key(&sink, KeyCode::ArrowDown, KeyEventKind::Down, 300.0);
+        key(&sink, KeyCode::ArrowRight, KeyEventKind::Down, 500.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 550.0);
+        events.pump();
+        actions.update();
+        assert!(!actions.is_pressed(&Action::Hadoken));
+    }
+
+    #[test]
+    fn test_combo_repeated_start() {
+        let (mut events, sink) = Events::new();
+        let mut map = ActionMap::new();
+        let (down, right) = (
+            Input::Key(KeyCode::ArrowDown),
+            Input::Key(KeyCode::ArrowRight),
+        );
+        map.bind_combo(&Action::Hadoken, Combo::new(&[down, down, right], 100.0));
+        let mut actions = attach_actions(&mut events, map);
+
+        for (i, code) in [KeyCode::ArrowDown, KeyCode::ArrowDown, KeyCode::ArrowDown]
+            .iter()
+            .enumerate()
+        {
+            key(&sink, *code, KeyEventKind::Down, 20.0 * i as f32);
+            key(&sink, *code, KeyEventKind::Up, 20.0 * i as f32 + 10.0);
+        }
+        key(&sink, KeyCode::ArrowRight, KeyEventKind::Down, 60.0);
+        events.pump();
+        actions.update();
+        assert!(actions.is_pressed(&Action::Hadoken));
+    }
+
+    #[test]
+    fn test_combo_map_changed() {
+        let (mut events, sink) = Events::new();
+        let mut actions = attach_actions(&mut events, action_map());
+        key(&sink, KeyCode::ArrowDown, KeyEventKind::Down, 0.0);
+        key(&sink, KeyCode::ArrowRight, KeyEventKind::Down, 10.0);
+        events.pump();
+        actions.update();
+
+        // A shorter combo takes the place of the one in progress
+        let map = actions.map_mut();
+        map.unbind(&Action::Hadoken);
+        map.bind_combo(
+            &Action::Hadoken,
+            Combo::new(&[Input::Key(KeyCode::Z)], 100.0),
+        );
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 20.0);
+        events.pump();
+        actions.update();
+        assert!(actions.is_pressed(&Action::Hadoken));
+    }
+
+    #[test]
+    fn test_rebind_and_serialize() {
+        let mut map = action_map();
+        map.rebind(&Action::Attack, &[Binding::new(Input::Key(KeyCode::X))]);
+        let bytes = map.to_bytes().unwrap();
+        let map: ActionMap<Action> = ActionMap::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            map.bindings(&Action::Attack),
+            vec![&Binding::new(Input::Key(KeyCode::X))]
+        );
+        assert_eq!(map.combos(&Action::Hadoken).len(), 1);
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use bincode::{Decode, Encode};
+
+use super::keyboard::{KeyCode, KeyMap, Keyboard, MappedKeyEvent};
+use crate::event::{
+    Events,
+    Filter,
+    GamepadEvent,
+    GamepadEventKind,
+    KeyEvent,
+    KeyEventKind,
+    Plumbing,
+    Pump,
+    Sink,
+    Source,
+};
+use crate::num::Float;
+use crate::vector::v2::V2;
+
+/// Buttons of the W3C standard gamepad layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
+#[repr(u8)]
+pub enum GamepadButton {
+    FaceDown = 0,
+    FaceRight = 1,
+    FaceLeft = 2,
+    FaceUp = 3,
+    LeftBumper = 4,
+    RightBumper = 5,
+    LeftTrigger = 6,
+    RightTrigger = 7,
+    Select = 8,
+    Start = 9,
+    LeftStick = 10,
+    RightStick = 11,
+    DpadUp = 12,
+    DpadDown = 13,
+    DpadLeft = 14,
+    DpadRight = 15,
+    Home = 16,
+}
+
+#[derive(Clone, Debug)]
+pub struct InvalidGamepadButton(pub u8);
+
+impl TryFrom<u8> for GamepadButton {
+    type Error = InvalidGamepadButton;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(GamepadButton::FaceDown),
+            1 => Ok(GamepadButton::FaceRight),
+            2 => Ok(GamepadButton::FaceLeft),
+            3 => Ok(GamepadButton::FaceUp),
+            4 => Ok(GamepadButton::LeftBumper),
+            5 => Ok(GamepadButton::RightBumper),
+            6 => Ok(GamepadButton::LeftTrigger),
+            7 => Ok(GamepadButton::RightTrigger),
+            8 => Ok(GamepadButton::Select),
+            9 => Ok(GamepadButton::Start),
+            10 => Ok(GamepadButton::LeftStick),
+            11 => Ok(GamepadButton::RightStick),
+            12 => Ok(GamepadButton::DpadUp),
+            13 => Ok(GamepadButton::DpadDown),
+            14 => Ok(GamepadButton::DpadLeft),
+            15 => Ok(GamepadButton::DpadRight),
+            16 => Ok(GamepadButton::Home),
+            _ => Err(InvalidGamepadButton(value)),
+        }
+    }
+}
+
+/// Axes of the W3C standard gamepad layout; values range
+/// from -1.0 to 1.0, with negative Y pointing up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum GamepadAxis {
+    LeftX = 0,
+    LeftY = 1,
+    RightX = 2,
+    RightY = 3,
+}
+
+#[derive(Clone, Debug)]
+pub struct InvalidGamepadAxis(pub u8);
+
+impl TryFrom<u8> for GamepadAxis {
+    type Error = InvalidGamepadAxis;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(GamepadAxis::LeftX),
+            1 => Ok(GamepadAxis::LeftY),
+            2 => Ok(GamepadAxis::RightX),
+            3 => Ok(GamepadAxis::RightY),
+            _ => Err(InvalidGamepadAxis(value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+/// GamepadMap maps gamepad buttons and stick directions to the same
+/// kind of keys as a KeyMap, so that a Keyboard (and a Dpad) can be
+/// driven by a gamepad. A stick direction counts as pressed when the
+/// axis value goes beyond the threshold.
+pub struct GamepadMap<T> {
+    buttons: HashMap<GamepadButton, T>,
+    axes: HashMap<(GamepadAxis, AxisDirection), T>,
+    threshold: f32,
+}
+
+impl<T> GamepadMap<T>
+where
+    T: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+            threshold: 0.5,
+        }
+    }
+    pub fn set_button(&mut self, button: GamepadButton, mapped: &T) {
+        self.buttons.insert(button, mapped.clone());
+    }
+    pub fn set_axis(&mut self, axis: GamepadAxis, direction: AxisDirection, mapped: &T) {
+        self.axes.insert((axis, direction), mapped.clone());
+    }
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+    pub fn get_button(&self, button: &GamepadButton) -> Option<T> {
+        self.buttons.get(button).cloned()
+    }
+    pub fn get_axis(&self, axis: GamepadAxis, direction: AxisDirection) -> Option<T> {
+        self.axes.get(&(axis, direction)).cloned()
+    }
+    fn direction(&self, value: f32) -> Option<AxisDirection> {
+        if value >= self.threshold {
+            Some(AxisDirection::Positive)
+        } else if value <= -self.threshold {
+            Some(AxisDirection::Negative)
+        } else {
+            None
+        }
+    }
+}
+
+// What holds a mapped key down
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Holder {
+    Key(KeyCode),
+    Button(u32, GamepadButton),
+    Axis(u32, GamepadAxis),
+}
+
+// GamepadMapper maps both the keyboard and the gamepads, so that a key
+// held by several of them is only released once all of them let go
+struct GamepadMapper<T> {
+    keymap: KeyMap<T>,
+    key_source: Source<KeyEvent>,
+    map: GamepadMap<T>,
+    source: Source<GamepadEvent>,
+    sink: Sink<MappedKeyEvent<T>>,
+    buttons_down: HashSet<(u32, GamepadButton)>,
+    axes_down: HashMap<(u32, GamepadAxis), AxisDirection>,
+    holders: HashMap<T, HashSet<Holder>>,
+}
+
+impl<T> GamepadMapper<T>
+where
+    T: Eq + Hash + Clone,
+{
+    fn new(
+        keymap: KeyMap<T>,
+        key_source: Source<KeyEvent>,
+        map: GamepadMap<T>,
+        source: Source<GamepadEvent>,
+        sink: Sink<MappedKeyEvent<T>>,
+    ) -> Self {
+        Self {
+            keymap,
+            key_source,
+            map,
+            source,
+            sink,
+            buttons_down: HashSet::new(),
+            axes_down: HashMap::new(),
+            holders: HashMap::new(),
+        }
+    }
+
+    // Down is only sent for the first holder of a key,
+    // and Up once its last holder lets go
+    fn send(&mut self, kind: KeyEventKind, key: Option<T>, holder: Holder, ts: f32) {
+        let key = match key {
+            Some(key) => key,
+            None => return,
+        };
+        let send = match kind {
+            KeyEventKind::Down => {
+                let holders = self.holders.entry(key.clone()).or_default();
+                holders.insert(holder) && holders.len() == 1
+            },
+            KeyEventKind::Up => {
+                let holders = match self.holders.get_mut(&key) {
+                    Some(holders) => holders,
+                    None => return,
+                };
+                let last = holders.remove(&holder) && holders.is_empty();
+                if last {
+                    self.holders.remove(&key);
+                }
+                last
+            },
+        };
+        if send {
+            self.sink.send(MappedKeyEvent { kind, key, ts });
+        }
+    }
+
+    fn map_axis(&mut self, gamepad: u32, axis: GamepadAxis, value: f32, ts: f32) {
+        let new_dir = self.map.direction(value);
+        let old_dir = self.axes_down.get(&(gamepad, axis)).copied();
+        if new_dir == old_dir {
+            return;
+        }
+        let holder = Holder::Axis(gamepad, axis);
+        if let Some(old_dir) = old_dir {
+            self.axes_down.remove(&(gamepad, axis));
+            let key = self.map.get_axis(axis, old_dir);
+            self.send(KeyEventKind::Up, key, holder, ts);
+        }
+        if let Some(new_dir) = new_dir {
+            self.axes_down.insert((gamepad, axis), new_dir);
+            let key = self.map.get_axis(axis, new_dir);
+            self.send(KeyEventKind::Down, key, holder, ts);
+        }
+    }
+
+    fn release_all(&mut self, gamepad: u32, ts: f32) {
+        let buttons: Vec<GamepadButton> = self
+            .buttons_down
+            .iter()
+            .filter(|(g, _)| *g == gamepad)
+            .map(|(_, b)| *b)
+            .collect();
+        for button in buttons {
+            self.buttons_down.remove(&(gamepad, button));
+            let key = self.map.get_button(&button);
+            self.send(KeyEventKind::Up, key, Holder::Button(gamepad, button), ts);
+        }
+        let axes: Vec<GamepadAxis> = self
+            .axes_down
+            .keys()
+            .filter(|(g, _)| *g == gamepad)
+            .map(|(_, a)| *a)
+            .collect();
+        for axis in axes {
+            self.map_axis(gamepad, axis, 0.0, ts);
+        }
+    }
+}
+
+impl<T> Pump for GamepadMapper<T>
+where
+    T: Eq + Hash + Clone,
+{
+    fn pump(&mut self) {
+        while let Some(event) = self.key_source.recv() {
+            let holder = Holder::Key(event.code);
+            if let Some(mapped) = self.keymap.filter(event) {
+                self.send(mapped.kind, Some(mapped.key), holder, mapped.ts);
+            }
+        }
+        while let Some(event) = self.source.recv() {
+            let gamepad = event.gamepad;
+            let ts = event.ts;
+            match event.kind {
+                GamepadEventKind::ButtonDown(button) => {
+                    if self.buttons_down.insert((gamepad, button)) {
+                        let key = self.map.get_button(&button);
+                        self.send(KeyEventKind::Down, key, Holder::Button(gamepad, button), ts);
+                    }
+                },
+                GamepadEventKind::ButtonUp(button) => {
+                    if self.buttons_down.remove(&(gamepad, button)) {
+                        let key = self.map.get_button(&button);
+                        self.send(KeyEventKind::Up, key, Holder::Button(gamepad, button), ts);
+                    }
+                },
+                GamepadEventKind::Axis(axis, value) => {
+                    self.map_axis(gamepad, axis, value, ts);
+                },
+                GamepadEventKind::Disconnect => {
+                    self.release_all(gamepad, ts);
+                },
+                GamepadEventKind::Connect => (),
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct GamepadState {
+    buttons: HashSet<GamepadButton>,
+    axes: [f32; 4],
+}
+
+/// Gamepad keeps track of the raw state of all connected gamepads,
+/// for analog reads of the sticks.
+///
+/// Gamepad::update must be called periodically to process GamepadEvents.
+pub struct Gamepad {
+    dead_zone: f32,
+    gamepads: HashMap<u32, GamepadState>,
+    events: Source<GamepadEvent>,
+}
+
+impl Gamepad {
+    pub fn new(events: Source<GamepadEvent>, dead_zone: f32) -> Self {
+        Self {
+            dead_zone,
+            gamepads: HashMap::new(),
+            events,
+        }
+    }
+    pub fn update(&mut self) {
+        while let Some(event) = self.events.recv() {
+            if let GamepadEventKind::Disconnect = event.kind {
+                self.gamepads.remove(&event.gamepad);
+                continue;
+            }
+            let state = self.gamepads.entry(event.gamepad).or_default();
+            match event.kind {
+                GamepadEventKind::ButtonDown(button) => {
+                    state.buttons.insert(button);
+                },
+                GamepadEventKind::ButtonUp(button) => {
+                    state.buttons.remove(&button);
+                },
+                GamepadEventKind::Axis(axis, value) => {
+                    state.axes[axis as usize] = value;
+                },
+                _ => (),
+            }
+        }
+    }
+    /// connected returns the indices of connected gamepads, in order
+    pub fn connected(&self) -> Vec<u32> {
+        let mut gamepads: Vec<u32> = self.gamepads.keys().copied().collect();
+        gamepads.sort();
+        gamepads
+    }
+    pub fn is_down(&self, gamepad: u32, button: GamepadButton) -> bool {
+        self.gamepads
+            .get(&gamepad)
+            .map(|s| s.buttons.contains(&button))
+            .unwrap_or(false)
+    }
+    /// axis returns the raw value of an axis, without dead zone
+    pub fn axis(&self, gamepad: u32, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .get(&gamepad)
+            .map(|s| s.axes[axis as usize])
+            .unwrap_or(0.0)
+    }
+    /// stick returns the position of a stick, with a radial dead zone:
+    /// positions inside the dead zone read as zero, and positions
+    /// outside are rescaled so that magnitude still goes from 0 to 1.
+    pub fn stick<F>(&self, gamepad: u32, stick: Stick) -> V2<F>
+    where
+        F: Float,
+    {
+        let (ax, ay) = match stick {
+            Stick::Left => (GamepadAxis::LeftX, GamepadAxis::LeftY),
+            Stick::Right => (GamepadAxis::RightX, GamepadAxis::RightY),
+        };
+        let v: V2<f32> = V2::new(self.axis(gamepad, ax), self.axis(gamepad, ay));
+        let v = apply_dead_zone(v, self.dead_zone);
+        V2::new(F::from_f32(v.x).unwrap(), F::from_f32(v.y).unwrap())
+    }
+}
+
+fn apply_dead_zone(v: V2<f32>, dead_zone: f32) -> V2<f32> {
+    let mag = v.mag();
+    if mag <= dead_zone {
+        V2::zero()
+    } else {
+        v.norm() * ((mag - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+pub fn attach_gamepad(events: &mut Events, dead_zone: f32) -> Gamepad {
+    Gamepad::new(events.gamepad_events(), dead_zone)
+}
+
+/// Attach a Keyboard whose keys can be pressed both from the keyboard
+/// and from any connected gamepad.
+pub fn attach_keyboard_and_gamepad<T>(
+    events: &mut Events,
+    keymap: KeyMap<T>,
+    gamepad_map: GamepadMap<T>,
+) -> Keyboard<T>
+where
+    T: PartialEq + Eq + Hash + Clone + 'static,
+{
+    let k_source = events.key_events();
+    let g_source = events.gamepad_events();
+    let (m_sink, m_source) = events.plumbing().pipe::<MappedKeyEvent<T>>();
+    let plumbing: &mut Plumbing = events.plumbing();
+    plumbing.add_pump(GamepadMapper::new(
+        keymap,
+        k_source,
+        gamepad_map,
+        g_source,
+        m_sink,
+    ));
+    Keyboard::new(m_source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::Event;
+    use crate::input::Dpad;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum Keys {
+        Up,
+        Down,
+        Left,
+        Right,
+    }
+
+    fn send(sink: &Sink<Event>, kind: GamepadEventKind) {
+        send_from(sink, 0, kind);
+    }
+
+    fn send_from(sink: &Sink<Event>, gamepad: u32, kind: GamepadEventKind) {
+        sink.send(Event::Gamepad(GamepadEvent {
+            kind,
+            gamepad,
+            ts: 0.0,
+        }));
+    }
+
+    #[test]
+    fn test_gamepad_map_stick_to_dpad() {
+        let (mut events, sink) = Events::new();
+        let mut gm = GamepadMap::new();
+        gm.set_axis(GamepadAxis::LeftX, AxisDirection::Negative, &Keys::Left);
+        gm.set_axis(GamepadAxis::LeftX, AxisDirection::Positive, &Keys::Right);
+        gm.set_button(GamepadButton::DpadUp, &Keys::Up);
+        gm.set_button(GamepadButton::DpadDown, &Keys::Down);
+        let mut keyboard = attach_keyboard_and_gamepad(&mut events, KeyMap::new(), gm);
+        let dpad = Dpad::new([Keys::Up, Keys::Down, Keys::Left, Keys::Right]);
+
+        send(&sink, GamepadEventKind::Axis(GamepadAxis::LeftX, -0.9));
+        events.pump();
+        keyboard.update();
+        assert_eq!(dpad.read::<f32>(&keyboard), V2::new(-1.0, 0.0));
+
+        send(&sink, GamepadEventKind::Axis(GamepadAxis::LeftX, 0.9));
+        send(&sink, GamepadEventKind::ButtonDown(GamepadButton::DpadDown));
+        events.pump();
+        keyboard.update();
+        assert!(!keyboard.is_down(&Keys::Left));
+        assert!(keyboard.is_down(&Keys::Right));
+        assert!(keyboard.is_down(&Keys::Down));
+
+        send(&sink, GamepadEventKind::Disconnect);
+        events.pump();
+        keyboard.update();
+        assert!(!keyboard.is_down(&Keys::Right));
+        assert!(!keyboard.is_down(&Keys::Down));
+    }
+
+    #[test]
+    fn test_keys_held_by_many() {
+        let (mut events, sink) = Events::new();
+        let mut km = KeyMap::new();
+        km.set(KeyCode::ArrowLeft, &Keys::Left);
+        let mut gm = GamepadMap::new();
+        gm.set_axis(GamepadAxis::LeftX, AxisDirection::Negative, &Keys::Left);
+        gm.set_button(GamepadButton::DpadLeft, &Keys::Left);
+        let mut keyboard = attach_keyboard_and_gamepad(&mut events, km, gm);
+        let key = |kind| {
+            sink.send(Event::Key(KeyEvent {
+                kind,
+                code: KeyCode::ArrowLeft,
+                ts: 0.0,
+            }))
+        };
+        let mut is_left_down = |events: &mut Events| {
+            events.pump();
+            keyboard.update();
+            keyboard.is_down(&Keys::Left)
+        };
+
+        key(KeyEventKind::Down);
+        send(&sink, GamepadEventKind::ButtonDown(GamepadButton::DpadLeft));
+        send_from(&sink, 1, GamepadEventKind::Axis(GamepadAxis::LeftX, -1.0));
+        assert!(is_left_down(&mut events));
+        key(KeyEventKind::Up);
+        assert!(is_left_down(&mut events));
+        send(&sink, GamepadEventKind::ButtonUp(GamepadButton::DpadLeft));
+        assert!(is_left_down(&mut events));
+        send_from(&sink, 1, GamepadEventKind::Disconnect);
+        assert!(!is_left_down(&mut events));
+    }
+
+    #[test]
+    fn test_dead_zone() {
+        assert_eq!(apply_dead_zone(V2::new(0.25, 0.25), 0.5), V2::zero());
+        assert_eq!(apply_dead_zone(V2::new(0.75, 0.0), 0.5), V2::new(0.5, 0.0));
+        assert_eq!(apply_dead_zone(V2::new(0.0, -1.0), 0.5), V2::new(0.0, -1.0));
+    }
+}
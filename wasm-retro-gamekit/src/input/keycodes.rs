@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+use bincode::{Decode, Encode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode)]
 #[repr(u8)]
 pub enum KeyCode {
     Backspace = 8,
@@ -109,7 +111,7 @@ impl From<KeyCode> for u8 {
 }
 
 #[derive(Clone, Debug)]
-pub struct InvalidKeyCode(u8);
+pub struct InvalidKeyCode(pub u8);
 
 impl TryFrom<u8> for KeyCode {
     type Error = InvalidKeyCode;
@@ -1,6 +1,11 @@
+pub mod action;
+pub mod gamepad;
 pub mod keyboard;
 pub mod keycodes;
 pub mod mouse;
+pub mod snapshot;
+pub mod text;
+pub mod touch;
 
 use std::hash::Hash;
 
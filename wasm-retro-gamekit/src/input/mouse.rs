@@ -1,24 +1,117 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use crate::event::{Events, Filter, MouseButton, MouseEvent, MouseEventKind, Plumbing, Source};
 use crate::vector::v2::V2;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MouseInteractionKind {
-    Click,
-    Drag,
-    Drop,
+    Click(MouseButton),
+    /// Sent instead of Click for the second click of a double click
+    DoubleClick(MouseButton),
+    Drag(MouseButton),
+    /// Sent on every move after Drag, until Drop
+    DragMove(MouseButton),
+    Drop(MouseButton),
+    Wheel(V2<f32>),
+    HoverEnter(HoverId),
+    HoverLeave(HoverId),
 }
 
+#[derive(Clone, Debug)]
 pub struct MouseInteraction {
     pub kind: MouseInteractionKind,
-    pub button: MouseButton,
     pub pos: V2<f32>,
+    pub scene_pos: V2<f32>,
     pub ts: f32,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HoverId(u32);
+
+/// A rectangle in the same coordinates as mouse positions,
+/// used to track when the mouse enters and leaves it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HoverRect {
+    pub pos: V2<f32>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl HoverRect {
+    pub fn new(pos: V2<f32>, width: f32, height: f32) -> Self {
+        Self { pos, width, height }
+    }
+
+    pub fn contains(&self, p: V2<f32>) -> bool {
+        p.x >= self.pos.x
+            && p.x < self.pos.x + self.width
+            && p.y >= self.pos.y
+            && p.y < self.pos.y + self.height
+    }
+}
+
+struct HoverRegion {
+    id: HoverId,
+    rect: HoverRect,
+    inside: bool,
+}
+
+// State shared between a Mouse and its MouseFilter
+struct MouseState {
+    pos: V2<f32>,
+    scene_pos: V2<f32>,
+    // Relative motion accumulated since the last take_motion
+    motion: V2<f32>,
+    hover_regions: Vec<HoverRegion>,
+    next_hover_id: u32,
+}
+
+impl MouseState {
+    fn new() -> Self {
+        Self {
+            pos: V2::zero(),
+            scene_pos: V2::zero(),
+            motion: V2::zero(),
+            hover_regions: vec![],
+            next_hover_id: 0,
+        }
+    }
+
+    fn update_hover(&mut self, event: &MouseEvent) -> Vec<MouseInteraction> {
+        let mut leaves = vec![];
+        let mut enters = vec![];
+        for region in self.hover_regions.iter_mut() {
+            let inside = region.rect.contains(event.pos);
+            if inside != region.inside {
+                region.inside = inside;
+                let interaction = MouseInteraction {
+                    kind: match inside {
+                        true => MouseInteractionKind::HoverEnter(region.id),
+                        false => MouseInteractionKind::HoverLeave(region.id),
+                    },
+                    pos: event.pos,
+                    scene_pos: event.scene_pos,
+                    ts: event.ts,
+                };
+                match inside {
+                    true => enters.push(interaction),
+                    false => leaves.push(interaction),
+                }
+            }
+        }
+        leaves.extend(enters);
+        leaves
+    }
+}
+
 #[derive(Clone)]
 struct ButtonState {
     button: MouseButton,
     last_down_ts: f32,
     last_down_pos: V2<f32>,
+    last_click_ts: Option<f32>,
+    last_click_pos: V2<f32>,
     is_down: bool,
     is_drag: bool,
     sent_drag: bool,
@@ -30,39 +123,64 @@ impl ButtonState {
             button,
             last_down_ts: 0.0,
             last_down_pos: V2::zero(),
+            last_click_ts: None,
+            last_click_pos: V2::zero(),
             is_down: false,
             is_drag: false,
             sent_drag: false,
         }
     }
 
+    fn click_kind(
+        &mut self,
+        pos: V2<f32>,
+        ts: f32,
+        max_double_click_delay: f32,
+        max_click_distance: f32,
+    ) -> MouseInteractionKind {
+        let is_double = match self.last_click_ts {
+            Some(last_ts) => {
+                ts - last_ts <= max_double_click_delay
+                    && (pos - self.last_click_pos).mag() <= max_click_distance
+            },
+            None => false,
+        };
+        if is_double {
+            // A third click starts a new double click
+            self.last_click_ts = None;
+            MouseInteractionKind::DoubleClick(self.button)
+        } else {
+            self.last_click_ts = Some(ts);
+            self.last_click_pos = pos;
+            MouseInteractionKind::Click(self.button)
+        }
+    }
+
     fn update(
         &mut self,
         event: &MouseEvent,
         max_click_delay: f32,
         max_click_distance: f32,
+        max_double_click_delay: f32,
     ) -> Option<MouseInteraction> {
         let ts = event.ts;
         let pos = event.pos;
         let delay = ts - self.last_down_ts;
         let distance = (pos - self.last_down_pos).mag().abs();
         self.is_drag = delay > max_click_delay || distance > max_click_distance;
-        match event.kind {
+        let kind = match event.kind {
             MouseEventKind::Up(_) => {
-                let kind: Option<MouseInteractionKind> = match (self.is_drag, self.sent_drag) {
-                    (true, true) => Some(MouseInteractionKind::Drop),
-                    (false, false) => Some(MouseInteractionKind::Click),
+                let kind = match (self.is_drag, self.sent_drag) {
+                    (true, true) => Some(MouseInteractionKind::Drop(self.button)),
+                    (false, false) => {
+                        Some(self.click_kind(pos, ts, max_double_click_delay, max_click_distance))
+                    },
                     _ => None,
                 };
                 self.is_down = false;
                 self.is_drag = false;
                 self.sent_drag = false;
-                kind.map(|k| MouseInteraction {
-                    kind: k,
-                    button: self.button,
-                    pos,
-                    ts,
-                })
+                kind
             },
             MouseEventKind::Down(_) => {
                 self.last_down_pos = pos;
@@ -72,29 +190,33 @@ impl ButtonState {
             },
             MouseEventKind::Move => {
                 if !self.is_down {
-                    return None;
-                }
-                if self.is_drag && !self.sent_drag {
+                    None
+                } else if self.sent_drag {
+                    Some(MouseInteractionKind::DragMove(self.button))
+                } else if self.is_drag {
                     self.sent_drag = true;
-                    Some(MouseInteraction {
-                        kind: MouseInteractionKind::Drag,
-                        button: self.button,
-                        pos,
-                        ts,
-                    })
+                    Some(MouseInteractionKind::Drag(self.button))
                 } else {
                     None
                 }
             },
-        }
+            MouseEventKind::Wheel(_) | MouseEventKind::RelativeMove(_) => None,
+        };
+        kind.map(|kind| MouseInteraction {
+            kind,
+            pos,
+            scene_pos: event.scene_pos,
+            ts,
+        })
     }
 }
 
 pub struct MouseFilter {
     max_click_delay: f32,
     max_click_distance: f32,
-    last_move_update: u8,
-    buttons: [ButtonState; 3],
+    max_double_click_delay: f32,
+    buttons: [ButtonState; 5],
+    state: Rc<RefCell<MouseState>>,
 }
 
 impl MouseFilter {
@@ -102,56 +224,244 @@ impl MouseFilter {
         Self {
             max_click_delay,
             max_click_distance,
-            last_move_update: 0,
+            max_double_click_delay: DEFAULT_DOUBLE_CLICK_DELAY,
             buttons: [
                 ButtonState::new(MouseButton::Left),
                 ButtonState::new(MouseButton::Middle),
                 ButtonState::new(MouseButton::Right),
+                ButtonState::new(MouseButton::Back),
+                ButtonState::new(MouseButton::Forward),
             ],
+            state: Rc::new(RefCell::new(MouseState::new())),
         }
     }
+
+    /// Set the delay between clicks under which they make a double click.
+    pub fn with_double_click_delay(mut self, max_double_click_delay: f32) -> Self {
+        self.max_double_click_delay = max_double_click_delay;
+        self
+    }
+
+    fn update_button(&mut self, idx: usize, me: &MouseEvent) -> Option<MouseInteraction> {
+        self.buttons[idx].update(
+            me,
+            self.max_click_delay,
+            self.max_click_distance,
+            self.max_double_click_delay,
+        )
+    }
 }
 
-impl Filter<MouseEvent, MouseInteraction> for MouseFilter {
-    fn filter(&mut self, me: MouseEvent) -> Option<MouseInteraction> {
-        let update_idx: u8 = match me.kind {
-            MouseEventKind::Down(button) => button.into(),
-            MouseEventKind::Up(button) => button.into(),
+impl Filter<MouseEvent, Vec<MouseInteraction>> for MouseFilter {
+    fn filter(&mut self, me: MouseEvent) -> Option<Vec<MouseInteraction>> {
+        let mut interactions = vec![];
+        match me.kind {
+            MouseEventKind::Down(button) | MouseEventKind::Up(button) => {
+                let idx: u8 = button.into();
+                interactions.extend(self.update_button(idx as usize, &me));
+            },
             MouseEventKind::Move => {
-                self.last_move_update = (self.last_move_update + 1) % 3;
-                self.last_move_update
+                for idx in 0..self.buttons.len() {
+                    interactions.extend(self.update_button(idx, &me));
+                }
             },
-        };
-        let button = &mut self.buttons[update_idx as usize];
-        button.update(&me, self.max_click_delay, self.max_click_distance)
+            MouseEventKind::Wheel(delta) => {
+                interactions.push(MouseInteraction {
+                    kind: MouseInteractionKind::Wheel(delta),
+                    pos: me.pos,
+                    scene_pos: me.scene_pos,
+                    ts: me.ts,
+                });
+            },
+            MouseEventKind::RelativeMove(delta) => {
+                let mut state = self.state.borrow_mut();
+                state.motion = state.motion + delta;
+            },
+        }
+        let mut state = self.state.borrow_mut();
+        state.pos = me.pos;
+        state.scene_pos = me.scene_pos;
+        interactions.extend(state.update_hover(&me));
+        Some(interactions)
     }
 }
 
 pub struct Mouse {
-    pos: V2<f32>,
+    state: Rc<RefCell<MouseState>>,
     interactions: Source<MouseInteraction>,
 }
 
 impl Mouse {
-    pub fn relpos(&self) -> &V2<f32> {
-        &self.pos
+    /// relpos returns the last known position of the mouse
+    pub fn relpos(&self) -> V2<f32> {
+        self.state.borrow().pos
+    }
+    /// scene_pos returns the last known position of the mouse, in scene pixels
+    pub fn scene_pos(&self) -> V2<f32> {
+        self.state.borrow().scene_pos
+    }
+    /// take_motion returns the relative motion accumulated while the
+    /// pointer was locked, and resets it; call it once per tick
+    pub fn take_motion(&self) -> V2<f32> {
+        std::mem::replace(&mut self.state.borrow_mut().motion, V2::zero())
     }
     pub fn interactions(&self) -> &Source<MouseInteraction> {
         &self.interactions
     }
+    /// Start tracking when the mouse enters and leaves rect;
+    /// HoverEnter and HoverLeave interactions are sent with the returned id.
+    pub fn add_hover_rect(&self, rect: HoverRect) -> HoverId {
+        let mut state = self.state.borrow_mut();
+        let id = HoverId(state.next_hover_id);
+        state.next_hover_id += 1;
+        state.hover_regions.push(HoverRegion {
+            id,
+            rect,
+            inside: false,
+        });
+        id
+    }
+    pub fn remove_hover_rect(&self, id: HoverId) {
+        self.state
+            .borrow_mut()
+            .hover_regions
+            .retain(|region| region.id != id);
+    }
 }
 
+/// Delay between clicks under which MouseFilter sees a double click,
+/// in milliseconds, like the default of most desktops
+pub const DEFAULT_DOUBLE_CLICK_DELAY: f32 = 500.0;
+
 pub fn attach_mouse(events: &mut Events, max_click_delay: f32, max_click_distance: f32) -> Mouse {
+    attach_mouse_with_double_click(
+        events,
+        max_click_delay,
+        max_click_distance,
+        DEFAULT_DOUBLE_CLICK_DELAY,
+    )
+}
+
+/// Like attach_mouse, with the delay between clicks
+/// under which they make a double click.
+pub fn attach_mouse_with_double_click(
+    events: &mut Events,
+    max_click_delay: f32,
+    max_click_distance: f32,
+    max_double_click_delay: f32,
+) -> Mouse {
     let m_source = events.mouse_events();
     let (mint_sink, mint_source) = events.plumbing().pipe::<MouseInteraction>();
+    let filter = MouseFilter::new(max_click_delay, max_click_distance)
+        .with_double_click_delay(max_double_click_delay);
+    let state = filter.state.clone();
     let plumbing: &mut Plumbing = events.plumbing();
-    plumbing.filter(
-        m_source,
-        mint_sink,
-        MouseFilter::new(max_click_delay, max_click_distance),
-    );
+    plumbing.flat_filter(m_source, mint_sink, filter);
     Mouse {
-        pos: V2::zero(),
+        state,
         interactions: mint_source,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::{Event, Pump, Sink};
+
+    fn send(sink: &Sink<Event>, kind: MouseEventKind, x: f32, ts: f32) {
+        sink.send(Event::Mouse(MouseEvent {
+            kind,
+            pos: V2::new(x, 0.5),
+            scene_pos: V2::new(x * 64.0, 32.0),
+            ts,
+        }));
+    }
+
+    fn recv_all(mouse: &Mouse) -> Vec<MouseInteractionKind> {
+        let mut kinds = vec![];
+        while let Some(i) = mouse.interactions().recv() {
+            kinds.push(i.kind);
+        }
+        kinds
+    }
+
+    #[test]
+    fn test_double_click() {
+        let (mut events, sink) = Events::new();
+        let mouse = attach_mouse_with_double_click(&mut events, 200.0, 0.02, 400.0);
+        send(&sink, MouseEventKind::Down(MouseButton::Left), 0.5, 0.0);
+        send(&sink, MouseEventKind::Up(MouseButton::Left), 0.5, 50.0);
+        send(&sink, MouseEventKind::Down(MouseButton::Left), 0.5, 150.0);
+        send(&sink, MouseEventKind::Up(MouseButton::Left), 0.5, 200.0);
+        events.pump();
+        assert_eq!(
+            recv_all(&mouse),
+            vec![
+                MouseInteractionKind::Click(MouseButton::Left),
+                MouseInteractionKind::DoubleClick(MouseButton::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drag_moves_and_relpos() {
+        let (mut events, sink) = Events::new();
+        let mouse = attach_mouse(&mut events, 200.0, 0.02);
+        send(&sink, MouseEventKind::Down(MouseButton::Right), 0.25, 0.0);
+        send(&sink, MouseEventKind::Move, 0.5, 10.0);
+        send(&sink, MouseEventKind::Move, 0.75, 20.0);
+        send(&sink, MouseEventKind::Up(MouseButton::Right), 0.75, 30.0);
+        events.pump();
+        assert_eq!(
+            recv_all(&mouse),
+            vec![
+                MouseInteractionKind::Drag(MouseButton::Right),
+                MouseInteractionKind::DragMove(MouseButton::Right),
+                MouseInteractionKind::Drop(MouseButton::Right),
+            ]
+        );
+        assert_eq!(mouse.relpos(), V2::new(0.75, 0.5));
+        assert_eq!(mouse.scene_pos(), V2::new(48.0, 32.0));
+    }
+
+    #[test]
+    fn test_relative_motion() {
+        let (mut events, sink) = Events::new();
+        let mouse = attach_mouse(&mut events, 200.0, 0.02);
+        send(
+            &sink,
+            MouseEventKind::RelativeMove(V2::new(2.0, -1.0)),
+            0.5,
+            0.0,
+        );
+        send(
+            &sink,
+            MouseEventKind::RelativeMove(V2::new(3.0, 0.5)),
+            0.5,
+            10.0,
+        );
+        events.pump();
+        assert_eq!(mouse.take_motion(), V2::new(5.0, -0.5));
+        assert_eq!(mouse.take_motion(), V2::zero());
+        assert!(recv_all(&mouse).is_empty());
+    }
+
+    #[test]
+    fn test_hover() {
+        let (mut events, sink) = Events::new();
+        let mouse = attach_mouse(&mut events, 200.0, 0.02);
+        let a = mouse.add_hover_rect(HoverRect::new(V2::new(0.0, 0.0), 0.5, 1.0));
+        let b = mouse.add_hover_rect(HoverRect::new(V2::new(0.5, 0.0), 0.5, 1.0));
+        send(&sink, MouseEventKind::Move, 0.25, 0.0);
+        send(&sink, MouseEventKind::Move, 0.75, 10.0);
+        events.pump();
+        assert_eq!(
+            recv_all(&mouse),
+            vec![
+                MouseInteractionKind::HoverEnter(a),
+                MouseInteractionKind::HoverLeave(a),
+                MouseInteractionKind::HoverEnter(b),
+            ]
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+
+use super::action::{drain_sorted, input_events, Input, InputEvent};
+use crate::event::{Events, KeyEventKind, Source};
+
+/// InputSnapshot is the state of keys, mouse buttons and gamepad
+/// buttons at one tick, and what happened to them since the previous one.
+///
+/// Snapshots can be encoded with bincode, e.g. to send them over
+/// the network or to record a replay.
+#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+pub struct InputSnapshot {
+    pub tick: u64,
+    pub ts: f32,
+    /// Inputs pressed since the previous snapshot, in order; an input
+    /// tapped twice between two ticks appears twice
+    pub pressed: Vec<Input>,
+    /// Inputs released since the previous snapshot, in order
+    pub released: Vec<Input>,
+    /// Inputs down when the snapshot was taken
+    pub held: Vec<Input>,
+    /// Presses within the input buffer which weren't consumed yet,
+    /// with their timestamps
+    pub buffered: Vec<(Input, f32)>,
+}
+
+impl InputSnapshot {
+    pub fn is_pressed(&self, input: &Input) -> bool {
+        self.pressed.contains(input)
+    }
+    pub fn press_count(&self, input: &Input) -> usize {
+        self.pressed.iter().filter(|i| *i == input).count()
+    }
+    pub fn is_released(&self, input: &Input) -> bool {
+        self.released.contains(input)
+    }
+    pub fn is_held(&self, input: &Input) -> bool {
+        self.held.contains(input)
+    }
+    /// is_buffered returns whether the input was pressed within the
+    /// input buffer, even if that was several ticks ago
+    pub fn is_buffered(&self, input: &Input) -> bool {
+        self.buffered.iter().any(|(i, _)| i == input)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_to_vec(self, config::standard())
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        decode_from_slice(bytes, config::standard()).map(|(snapshot, _)| snapshot)
+    }
+}
+
+/// InputRecorder turns input events into one InputSnapshot per tick.
+///
+/// InputRecorder::snapshot should be called once at the beginning
+/// of Game::tick.
+pub struct InputRecorder {
+    events: Source<InputEvent>,
+    buffer: f32,
+    tick: u64,
+    held: Vec<Input>,
+    buffered: VecDeque<(Input, f32)>,
+}
+
+impl InputRecorder {
+    pub fn new(events: Source<InputEvent>, buffer: f32) -> Self {
+        Self {
+            events,
+            buffer,
+            tick: 0,
+            held: vec![],
+            buffered: VecDeque::new(),
+        }
+    }
+    /// set_buffer sets for how many milliseconds presses stay buffered
+    pub fn set_buffer(&mut self, buffer: f32) {
+        self.buffer = buffer;
+    }
+    pub fn snapshot(&mut self, now: f32) -> InputSnapshot {
+        let mut pressed = vec![];
+        let mut released = vec![];
+        for event in drain_sorted(&self.events) {
+            match event.kind {
+                KeyEventKind::Down => {
+                    // Ignore repeated down events
+                    if !self.held.contains(&event.input) {
+                        self.held.push(event.input);
+                        pressed.push(event.input);
+                        self.buffered.push_back((event.input, event.ts));
+                    }
+                },
+                KeyEventKind::Up => {
+                    if let Some(idx) = self.held.iter().position(|i| *i == event.input) {
+                        self.held.remove(idx);
+                        released.push(event.input);
+                    }
+                },
+            }
+        }
+        while let Some((_, ts)) = self.buffered.front() {
+            if now - ts > self.buffer {
+                self.buffered.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let snapshot = InputSnapshot {
+            tick: self.tick,
+            ts: now,
+            pressed,
+            released,
+            held: self.held.clone(),
+            buffered: self.buffered.iter().copied().collect(),
+        };
+        self.tick += 1;
+        snapshot
+    }
+    /// consume drops the buffered presses of an input, so that a
+    /// buffered press only triggers once, e.g. a single jump on landing
+    pub fn consume(&mut self, input: &Input) {
+        self.buffered.retain(|(i, _)| i != input);
+    }
+}
+
+pub fn attach_input_recorder(events: &mut Events, buffer: f32) -> InputRecorder {
+    InputRecorder::new(input_events(events), buffer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::{Event, KeyEvent, Pump, Sink};
+    use crate::input::keyboard::KeyCode;
+
+    fn key(sink: &Sink<Event>, code: KeyCode, kind: KeyEventKind, ts: f32) {
+        sink.send(Event::Key(KeyEvent { kind, code, ts }));
+    }
+
+    #[test]
+    fn test_fast_taps() {
+        let (mut events, sink) = Events::new();
+        let mut recorder = attach_input_recorder(&mut events, 100.0);
+        let z = Input::Key(KeyCode::Z);
+        let x = Input::Key(KeyCode::X);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 1.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Up, 2.0);
+        key(&sink, KeyCode::X, KeyEventKind::Down, 3.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 4.0);
+        events.pump();
+        let snapshot = recorder.snapshot(10.0);
+        assert_eq!(snapshot.pressed, vec![z, x, z]);
+        assert_eq!(snapshot.press_count(&z), 2);
+        assert_eq!(snapshot.released, vec![z]);
+        assert_eq!(snapshot.held, vec![x, z]);
+
+        let snapshot = recorder.snapshot(20.0);
+        assert_eq!(snapshot.tick, 1);
+        assert!(snapshot.pressed.is_empty());
+        assert!(snapshot.is_held(&z));
+    }
+
+    #[test]
+    fn test_buffer() {
+        let (mut events, sink) = Events::new();
+        let mut recorder = attach_input_recorder(&mut events, 100.0);
+        let z = Input::Key(KeyCode::Z);
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 0.0);
+        key(&sink, KeyCode::Z, KeyEventKind::Up, 10.0);
+        events.pump();
+        assert!(recorder.snapshot(16.0).is_buffered(&z));
+        assert!(recorder.snapshot(100.0).is_buffered(&z));
+        assert!(!recorder.snapshot(116.0).is_buffered(&z));
+
+        key(&sink, KeyCode::Z, KeyEventKind::Down, 120.0);
+        events.pump();
+        assert!(recorder.snapshot(132.0).is_buffered(&z));
+        recorder.consume(&z);
+        assert!(!recorder.snapshot(148.0).is_buffered(&z));
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let snapshot = InputSnapshot {
+            tick: 3,
+            ts: 48.0,
+            pressed: vec![Input::Key(KeyCode::Z)],
+            released: vec![],
+            held: vec![Input::Key(KeyCode::Z)],
+            buffered: vec![(Input::Key(KeyCode::Z), 40.0)],
+        };
+        let bytes = snapshot.to_bytes().unwrap();
+        assert_eq!(InputSnapshot::from_bytes(&bytes).unwrap(), snapshot);
+    }
+}
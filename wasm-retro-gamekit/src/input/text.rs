@@ -0,0 +1,263 @@
+use crate::event::{Events, Source, TextEdit, TextEvent, TextEventKind};
+
+/// TextField is a single line text editor with a cursor and
+/// an optional selection.
+///
+/// Positions are counted in chars, not bytes, so they can be
+/// used directly to lay out the text with a bitmap font.
+#[derive(Clone, Debug, Default)]
+pub struct TextField {
+    chars: Vec<char>,
+    cursor: usize,
+    // The other end of the selection, when there is one
+    anchor: Option<usize>,
+    max_len: Option<usize>,
+}
+
+impl TextField {
+    pub fn new(max_len: Option<usize>) -> Self {
+        Self {
+            max_len,
+            ..Default::default()
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    /// selection returns the selected range as (start, end),
+    /// with end exclusive, or None if nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.cursor => {
+                Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+            },
+            _ => None,
+        }
+    }
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection()
+            .map(|(start, end)| self.chars[start..end].iter().collect())
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.chars.clear();
+        self.cursor = 0;
+        self.anchor = None;
+        self.insert(text);
+    }
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    /// insert replaces the selection, if any, with text at the cursor.
+    /// Control characters are dropped, and text beyond max_len is cut.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let room = match self.max_len {
+            Some(max_len) => max_len.saturating_sub(self.chars.len()),
+            None => usize::MAX,
+        };
+        let new: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(room)
+            .collect();
+        let n = new.len();
+        self.chars.splice(self.cursor..self.cursor, new);
+        self.cursor += n;
+    }
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                self.chars.drain(start..end);
+                self.cursor = start;
+                self.anchor = None;
+                true
+            },
+            None => {
+                self.anchor = None;
+                false
+            },
+        }
+    }
+
+    /// move_to moves the cursor to pos; with select, the selection
+    /// is extended to pos instead of being dropped
+    pub fn move_to(&mut self, pos: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = pos.min(self.chars.len());
+    }
+    pub fn move_left(&mut self, select: bool) {
+        match (self.selection(), select) {
+            // Without shift, left collapses the selection to its start
+            (Some((start, _)), false) => self.move_to(start, false),
+            _ => self.move_to(self.cursor.saturating_sub(1), select),
+        }
+    }
+    pub fn move_right(&mut self, select: bool) {
+        match (self.selection(), select) {
+            (Some((_, end)), false) => self.move_to(end, false),
+            _ => self.move_to(self.cursor + 1, select),
+        }
+    }
+    pub fn home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+    pub fn end(&mut self, select: bool) {
+        self.move_to(self.chars.len(), select);
+    }
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.chars.len();
+    }
+
+    /// apply edits the field according to a text event;
+    /// it returns true when the event was Enter
+    pub fn apply(&mut self, event: &TextEvent) -> bool {
+        match &event.kind {
+            TextEventKind::Input(text) | TextEventKind::Paste(text) => self.insert(text),
+            TextEventKind::Edit { edit, select } => {
+                let select = *select;
+                match edit {
+                    TextEdit::Backspace => self.backspace(),
+                    TextEdit::Delete => self.delete(),
+                    TextEdit::Left => self.move_left(select),
+                    TextEdit::Right => self.move_right(select),
+                    TextEdit::Home => self.home(select),
+                    TextEdit::End => self.end(select),
+                    TextEdit::SelectAll => self.select_all(),
+                    TextEdit::Enter => return true,
+                }
+            },
+        }
+        false
+    }
+}
+
+/// TextInput feeds text events into a TextField.
+///
+/// TextInput::update must be called periodically to process
+/// the pending events.
+pub struct TextInput {
+    field: TextField,
+    events: Source<TextEvent>,
+    submitted: Option<String>,
+}
+
+impl TextInput {
+    pub fn update(&mut self) {
+        while let Some(event) = self.events.recv() {
+            if self.field.apply(&event) {
+                self.submitted = Some(self.field.text());
+            }
+        }
+    }
+    pub fn field(&self) -> &TextField {
+        &self.field
+    }
+    pub fn field_mut(&mut self) -> &mut TextField {
+        &mut self.field
+    }
+    /// take_submitted returns the text of the field when Enter
+    /// was last pressed, if it wasn't taken yet
+    pub fn take_submitted(&mut self) -> Option<String> {
+        self.submitted.take()
+    }
+}
+
+pub fn attach_text_input(events: &mut Events, max_len: Option<usize>) -> TextInput {
+    TextInput {
+        field: TextField::new(max_len),
+        events: events.text_events(),
+        submitted: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_delete() {
+        let mut field = TextField::new(Some(5));
+        field.insert("abc");
+        field.move_left(false);
+        field.backspace();
+        assert_eq!(field.text(), "ac");
+        assert_eq!(field.cursor(), 1);
+        field.insert("x\nyz1234");
+        assert_eq!(field.text(), "axyzc");
+        field.home(false);
+        field.delete();
+        assert_eq!(field.text(), "xyzc");
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut field = TextField::new(None);
+        field.set_text("héllo");
+        field.move_left(true);
+        field.move_left(true);
+        assert_eq!(field.selection(), Some((3, 5)));
+        assert_eq!(field.selected_text().unwrap(), "lo");
+        field.insert("p!");
+        assert_eq!(field.text(), "hélp!");
+        field.select_all();
+        field.move_left(false);
+        assert_eq!(field.cursor(), 0);
+        assert_eq!(field.selection(), None);
+        field.select_all();
+        field.backspace();
+        assert!(field.is_empty());
+    }
+
+    #[test]
+    fn test_text_input() {
+        use crate::event::{Event, Pump};
+
+        let (mut events, sink) = Events::new();
+        let mut input = attach_text_input(&mut events, None);
+        for kind in [
+            TextEventKind::Input("h".to_string()),
+            TextEventKind::Paste("ey".to_string()),
+            TextEventKind::Edit {
+                edit: TextEdit::Enter,
+                select: false,
+            },
+        ] {
+            sink.send(Event::Text(TextEvent { kind, ts: 0.0 }));
+        }
+        events.pump();
+        input.update();
+        assert_eq!(input.take_submitted().unwrap(), "hey");
+        assert_eq!(input.take_submitted(), None);
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use crate::event::{Events, Filter, Plumbing, Source, TouchEvent, TouchEventKind};
+use crate::vector::v2::V2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TouchGestureKind {
+    Tap,
+    LongPress,
+    /// Displacement from the start to the end of the swipe
+    Swipe(V2<f32>),
+    /// Ratio of the current distance between the two touch points
+    /// to their distance when the pinch started
+    Pinch(f32),
+}
+
+#[derive(Clone, Debug)]
+pub struct TouchGesture {
+    pub kind: TouchGestureKind,
+    pub pos: V2<f32>,
+    pub scene_pos: V2<f32>,
+    pub ts: f32,
+}
+
+#[derive(Clone)]
+struct TouchPoint {
+    // Order in which the touch points started, since browsers don't
+    // guarantee that identifiers grow with the age of touch points
+    seq: u64,
+    start_ts: f32,
+    start_pos: V2<f32>,
+    pos: V2<f32>,
+    scene_pos: V2<f32>,
+    // Touch points which were ever part of a multi-touch
+    // are only used for pinches
+    is_multi: bool,
+    sent_long_press: bool,
+}
+
+/// TouchFilter turns raw touch events into gestures.
+///
+/// Since filters only run when events arrive, a long press is
+/// detected on the first move after long_press_delay, or on release.
+pub struct TouchFilter {
+    max_tap_delay: f32,
+    max_tap_distance: f32,
+    long_press_delay: f32,
+    min_swipe_distance: f32,
+    touches: HashMap<i32, TouchPoint>,
+    next_seq: u64,
+    pinch: Option<Pinch>,
+}
+
+// The two touch points of a pinch, by id, and their distance
+// when they became the pinching pair
+struct Pinch {
+    a: i32,
+    b: i32,
+    start_distance: f32,
+}
+
+impl TouchFilter {
+    pub fn new(
+        max_tap_delay: f32,
+        max_tap_distance: f32,
+        long_press_delay: f32,
+        min_swipe_distance: f32,
+    ) -> Self {
+        Self {
+            max_tap_delay,
+            max_tap_distance,
+            long_press_delay,
+            min_swipe_distance,
+            touches: HashMap::new(),
+            next_seq: 0,
+            pinch: None,
+        }
+    }
+
+    // The pinch is tracked between the two oldest touch points
+    fn pinch_points(&self) -> Option<(i32, i32)> {
+        let mut points: Vec<(&i32, &TouchPoint)> = self.touches.iter().collect();
+        if points.len() < 2 {
+            return None;
+        }
+        points.sort_by_key(|(_, point)| point.seq);
+        Some((*points[0].0, *points[1].0))
+    }
+
+    // Start a new pinch when the pinching pair changes, e.g. when
+    // a second touch starts or one of the pair ends, but not when
+    // a third one comes and goes
+    fn update_pinch(&mut self) {
+        let (a, b) = match self.pinch_points() {
+            Some(pair) => pair,
+            None => {
+                self.pinch = None;
+                return;
+            },
+        };
+        if let Some(pinch) = &self.pinch {
+            if (pinch.a, pinch.b) == (a, b) {
+                return;
+            }
+        }
+        let start_distance = (self.touches[&a].pos - self.touches[&b].pos).mag();
+        self.pinch = Some(Pinch {
+            a,
+            b,
+            start_distance,
+        });
+    }
+
+    fn start(&mut self, event: &TouchEvent) -> Option<TouchGesture> {
+        self.touches.insert(
+            event.id,
+            TouchPoint {
+                seq: self.next_seq,
+                start_ts: event.ts,
+                start_pos: event.pos,
+                pos: event.pos,
+                scene_pos: event.scene_pos,
+                is_multi: false,
+                sent_long_press: false,
+            },
+        );
+        self.next_seq += 1;
+        if self.touches.len() >= 2 {
+            for point in self.touches.values_mut() {
+                point.is_multi = true;
+            }
+        }
+        self.update_pinch();
+        None
+    }
+
+    fn moved(&mut self, event: &TouchEvent) -> Option<TouchGesture> {
+        let point = self.touches.get_mut(&event.id)?;
+        point.pos = event.pos;
+        point.scene_pos = event.scene_pos;
+        if point.is_multi {
+            let pinch = self.pinch.as_ref()?;
+            if event.id != pinch.a && event.id != pinch.b {
+                return None;
+            }
+            let start_distance = pinch.start_distance;
+            if start_distance == 0.0 {
+                return None;
+            }
+            let (a, b) = (&self.touches[&pinch.a], &self.touches[&pinch.b]);
+            return Some(TouchGesture {
+                kind: TouchGestureKind::Pinch((a.pos - b.pos).mag() / start_distance),
+                pos: (a.pos + b.pos) / 2.0,
+                scene_pos: (a.scene_pos + b.scene_pos) / 2.0,
+                ts: event.ts,
+            });
+        }
+        let delay = event.ts - point.start_ts;
+        let distance = (event.pos - point.start_pos).mag();
+        if !point.sent_long_press
+            && delay >= self.long_press_delay
+            && distance <= self.max_tap_distance
+        {
+            point.sent_long_press = true;
+            Some(TouchGesture {
+                kind: TouchGestureKind::LongPress,
+                pos: event.pos,
+                scene_pos: event.scene_pos,
+                ts: event.ts,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn end(&mut self, event: &TouchEvent) -> Option<TouchGesture> {
+        let point = self.touches.remove(&event.id)?;
+        self.update_pinch();
+        if point.is_multi || point.sent_long_press {
+            return None;
+        }
+        let delay = event.ts - point.start_ts;
+        let delta = event.pos - point.start_pos;
+        let distance = delta.mag();
+        let kind = if distance <= self.max_tap_distance {
+            if delay <= self.max_tap_delay {
+                TouchGestureKind::Tap
+            } else if delay >= self.long_press_delay {
+                TouchGestureKind::LongPress
+            } else {
+                return None;
+            }
+        } else if distance >= self.min_swipe_distance {
+            TouchGestureKind::Swipe(delta)
+        } else {
+            return None;
+        };
+        Some(TouchGesture {
+            kind,
+            pos: event.pos,
+            scene_pos: event.scene_pos,
+            ts: event.ts,
+        })
+    }
+
+    fn cancel(&mut self, event: &TouchEvent) -> Option<TouchGesture> {
+        self.touches.remove(&event.id);
+        self.update_pinch();
+        None
+    }
+}
+
+impl Filter<TouchEvent, TouchGesture> for TouchFilter {
+    fn filter(&mut self, te: TouchEvent) -> Option<TouchGesture> {
+        match te.kind {
+            TouchEventKind::Start => self.start(&te),
+            TouchEventKind::Move => self.moved(&te),
+            TouchEventKind::End => self.end(&te),
+            TouchEventKind::Cancel => self.cancel(&te),
+        }
+    }
+}
+
+pub struct Touch {
+    gestures: Source<TouchGesture>,
+}
+
+impl Touch {
+    pub fn gestures(&self) -> &Source<TouchGesture> {
+        &self.gestures
+    }
+}
+
+pub fn attach_touch(
+    events: &mut Events,
+    max_tap_delay: f32,
+    max_tap_distance: f32,
+    long_press_delay: f32,
+    min_swipe_distance: f32,
+) -> Touch {
+    let t_source = events.touch_events();
+    let (g_sink, g_source) = events.plumbing().pipe::<TouchGesture>();
+    let plumbing: &mut Plumbing = events.plumbing();
+    plumbing.filter(
+        t_source,
+        g_sink,
+        TouchFilter::new(
+            max_tap_delay,
+            max_tap_distance,
+            long_press_delay,
+            min_swipe_distance,
+        ),
+    );
+    Touch { gestures: g_source }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(kind: TouchEventKind, id: i32, x: f32, y: f32, ts: f32) -> TouchEvent {
+        TouchEvent {
+            kind,
+            id,
+            pos: V2::new(x, y),
+            scene_pos: V2::new(x * 64.0, y * 64.0),
+            ts,
+        }
+    }
+
+    fn filter() -> TouchFilter {
+        TouchFilter::new(200.0, 0.02, 500.0, 0.1)
+    }
+
+    #[test]
+    fn test_tap_and_long_press() {
+        let mut f = filter();
+        assert!(f
+            .filter(touch(TouchEventKind::Start, 0, 0.5, 0.5, 0.0))
+            .is_none());
+        let g = f.filter(touch(TouchEventKind::End, 0, 0.5, 0.5, 100.0));
+        assert_eq!(g.unwrap().kind, TouchGestureKind::Tap);
+
+        f.filter(touch(TouchEventKind::Start, 1, 0.5, 0.5, 1000.0));
+        let g = f.filter(touch(TouchEventKind::Move, 1, 0.51, 0.5, 1600.0));
+        assert_eq!(g.unwrap().kind, TouchGestureKind::LongPress);
+        assert!(f
+            .filter(touch(TouchEventKind::End, 1, 0.5, 0.5, 1700.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_swipe() {
+        let mut f = filter();
+        f.filter(touch(TouchEventKind::Start, 0, 0.25, 0.5, 0.0));
+        let g = f.filter(touch(TouchEventKind::End, 0, 0.75, 0.5, 150.0));
+        assert_eq!(g.unwrap().kind, TouchGestureKind::Swipe(V2::new(0.5, 0.0)));
+    }
+
+    #[test]
+    fn test_pinch() {
+        let mut f = filter();
+        f.filter(touch(TouchEventKind::Start, 0, 0.25, 0.5, 0.0));
+        f.filter(touch(TouchEventKind::Start, 1, 0.5, 0.5, 10.0));
+        let g = f
+            .filter(touch(TouchEventKind::Move, 1, 0.75, 0.5, 20.0))
+            .unwrap();
+        assert_eq!(g.kind, TouchGestureKind::Pinch(2.0));
+        assert_eq!(g.pos, V2::new(0.5, 0.5));
+        assert_eq!(g.scene_pos, V2::new(32.0, 32.0));
+        assert!(f
+            .filter(touch(TouchEventKind::End, 1, 0.75, 0.5, 30.0))
+            .is_none());
+        assert!(f
+            .filter(touch(TouchEventKind::End, 0, 0.25, 0.5, 40.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_pinch_oldest_points() {
+        let mut f = filter();
+        // Identifiers don't follow the age of touch points
+        f.filter(touch(TouchEventKind::Start, 5, 0.25, 0.5, 0.0));
+        f.filter(touch(TouchEventKind::Start, 3, 0.5, 0.5, 10.0));
+        f.filter(touch(TouchEventKind::Start, 1, 0.9, 0.5, 20.0));
+        let g = f
+            .filter(touch(TouchEventKind::Move, 3, 0.75, 0.5, 30.0))
+            .unwrap();
+        assert_eq!(g.kind, TouchGestureKind::Pinch(2.0));
+        // A third touch point moving isn't part of the pinch
+        assert!(f
+            .filter(touch(TouchEventKind::Move, 1, 0.95, 0.5, 40.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_pinch_third_finger() {
+        let mut f = filter();
+        f.filter(touch(TouchEventKind::Start, 0, 0.25, 0.5, 0.0));
+        f.filter(touch(TouchEventKind::Start, 1, 0.5, 0.5, 10.0));
+        f.filter(touch(TouchEventKind::Move, 1, 0.75, 0.5, 20.0));
+        // A third finger landing mid-pinch keeps its start distance
+        f.filter(touch(TouchEventKind::Start, 2, 0.5, 0.25, 30.0));
+        let g = f
+            .filter(touch(TouchEventKind::Move, 1, 0.5, 0.5, 40.0))
+            .unwrap();
+        assert_eq!(g.kind, TouchGestureKind::Pinch(1.0));
+
+        // Lifting one of the pinching fingers pinches between the
+        // others, from their distance at that time
+        f.filter(touch(TouchEventKind::End, 0, 0.25, 0.5, 50.0));
+        let g = f
+            .filter(touch(TouchEventKind::Move, 2, 0.5, 0.0, 60.0))
+            .unwrap();
+        assert_eq!(g.kind, TouchGestureKind::Pinch(2.0));
+        assert_eq!(g.pos, V2::new(0.5, 0.25));
+    }
+}
@@ -8,15 +8,23 @@ use super::runner::JSGameRunner;
 use crate::event::{
     Event,
     FileReadEvent,
+    GamepadEvent,
+    GamepadEventKind,
     KeyEvent,
     KeyEventKind,
     MouseButton,
     MouseEvent,
     MouseEventKind,
     Sink,
+    TextEdit,
+    TextEvent,
+    TextEventKind,
+    TouchEvent,
+    TouchEventKind,
     WindowResizeEvent,
 };
 use crate::game::Response;
+use crate::input::gamepad::{GamepadAxis, GamepadButton};
 use crate::input::keyboard::{InvalidKeyCode, KeyCode};
 use crate::js::display::JSCanvasWindow;
 use crate::vector::v2::V2;
@@ -50,11 +58,31 @@ impl WindowHandle {
     pub fn image_data_size(&self) -> usize {
         self.0.borrow().image_data_size()
     }
+    pub fn request_pointer_lock(&self) {
+        self.0.borrow_mut().set_pointer_lock(true)
+    }
+    pub fn release_pointer_lock(&self) {
+        self.0.borrow_mut().set_pointer_lock(false)
+    }
+    pub fn pointer_lock_requested(&self) -> bool {
+        self.0.borrow().pointer_lock()
+    }
 }
 
 #[wasm_bindgen]
-pub struct EventQueueHandle(Sink<Event>);
+pub struct EventQueueHandle {
+    sink: Sink<Event>,
+    window: Rc<RefCell<JSCanvasWindow>>,
+}
 
+impl EventQueueHandle {
+    fn scene_pos(&self, x: f32, y: f32) -> (V2<f32>, V2<f32>) {
+        self.window.borrow().canvas_to_scene(V2::new(x, y))
+    }
+}
+
+// Mouse and touch positions are sent in canvas pixels, and
+// converted into scene coordinates by the window.
 #[wasm_bindgen]
 impl EventQueueHandle {
     pub fn send_mouse_button(&mut self, ts: f32, x: f32, y: f32, button: u8, up: bool) {
@@ -62,6 +90,8 @@ impl EventQueueHandle {
             0 => MouseButton::Left,
             1 => MouseButton::Middle,
             2 => MouseButton::Right,
+            3 => MouseButton::Back,
+            4 => MouseButton::Forward,
             _ => {
                 return;
             },
@@ -70,23 +100,112 @@ impl EventQueueHandle {
             true => MouseEventKind::Up(button),
             false => MouseEventKind::Down(button),
         };
-        self.0.send(Event::Mouse(MouseEvent {
+        let (pos, scene_pos) = self.scene_pos(x, y);
+        self.sink.send(Event::Mouse(MouseEvent {
             kind,
-            pos: V2::new(x, y),
+            pos,
+            scene_pos,
             ts,
         }))
     }
     pub fn send_mouse_move(&mut self, ts: f32, x: f32, y: f32) {
-        self.0.send(Event::Mouse(MouseEvent {
+        let (pos, scene_pos) = self.scene_pos(x, y);
+        self.sink.send(Event::Mouse(MouseEvent {
             kind: MouseEventKind::Move,
-            pos: V2::new(x, y),
+            pos,
+            scene_pos,
+            ts,
+        }))
+    }
+    pub fn send_mouse_wheel(&mut self, ts: f32, x: f32, y: f32, dx: f32, dy: f32) {
+        let (pos, scene_pos) = self.scene_pos(x, y);
+        self.sink.send(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Wheel(V2::new(dx, dy)),
+            pos,
+            scene_pos,
+            ts,
+        }))
+    }
+    pub fn send_mouse_relative_move(&mut self, ts: f32, x: f32, y: f32, dx: f32, dy: f32) {
+        let (pos, scene_pos) = self.scene_pos(x, y);
+        let delta = self.window.borrow().canvas_to_scene_delta(V2::new(dx, dy));
+        self.sink.send(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::RelativeMove(delta),
+            pos,
+            scene_pos,
             ts,
         }))
     }
+    fn send_touch(&mut self, kind: TouchEventKind, ts: f32, id: i32, x: f32, y: f32) {
+        let (pos, scene_pos) = self.scene_pos(x, y);
+        self.sink.send(Event::Touch(TouchEvent {
+            kind,
+            id,
+            pos,
+            scene_pos,
+            ts,
+        }))
+    }
+    pub fn send_touch_start(&mut self, ts: f32, id: i32, x: f32, y: f32) {
+        self.send_touch(TouchEventKind::Start, ts, id, x, y)
+    }
+    pub fn send_touch_move(&mut self, ts: f32, id: i32, x: f32, y: f32) {
+        self.send_touch(TouchEventKind::Move, ts, id, x, y)
+    }
+    pub fn send_touch_end(&mut self, ts: f32, id: i32, x: f32, y: f32) {
+        self.send_touch(TouchEventKind::End, ts, id, x, y)
+    }
+    pub fn send_touch_cancel(&mut self, ts: f32, id: i32, x: f32, y: f32) {
+        self.send_touch(TouchEventKind::Cancel, ts, id, x, y)
+    }
+    fn send_gamepad(&mut self, kind: GamepadEventKind, ts: f32, gamepad: u32) {
+        self.sink
+            .send(Event::Gamepad(GamepadEvent { kind, gamepad, ts }))
+    }
+    pub fn send_gamepad_connect(&mut self, ts: f32, gamepad: u32) {
+        self.send_gamepad(GamepadEventKind::Connect, ts, gamepad)
+    }
+    pub fn send_gamepad_disconnect(&mut self, ts: f32, gamepad: u32) {
+        self.send_gamepad(GamepadEventKind::Disconnect, ts, gamepad)
+    }
+    pub fn send_gamepad_button(&mut self, ts: f32, gamepad: u32, button: u8, pressed: bool) {
+        if let Ok(button) = GamepadButton::try_from(button) {
+            let kind = match pressed {
+                true => GamepadEventKind::ButtonDown(button),
+                false => GamepadEventKind::ButtonUp(button),
+            };
+            self.send_gamepad(kind, ts, gamepad)
+        }
+    }
+    pub fn send_gamepad_axis(&mut self, ts: f32, gamepad: u32, axis: u8, value: f32) {
+        if let Ok(axis) = GamepadAxis::try_from(axis) {
+            self.send_gamepad(GamepadEventKind::Axis(axis, value), ts, gamepad)
+        }
+    }
+    pub fn send_text_input(&mut self, ts: f32, text: &str) {
+        self.sink.send(Event::Text(TextEvent {
+            kind: TextEventKind::Input(text.to_string()),
+            ts,
+        }))
+    }
+    pub fn send_text_paste(&mut self, ts: f32, text: &str) {
+        self.sink.send(Event::Text(TextEvent {
+            kind: TextEventKind::Paste(text.to_string()),
+            ts,
+        }))
+    }
+    pub fn send_text_edit(&mut self, ts: f32, key: &str, select: bool) {
+        if let Some(edit) = TextEdit::from_key(key) {
+            self.sink.send(Event::Text(TextEvent {
+                kind: TextEventKind::Edit { edit, select },
+                ts,
+            }))
+        }
+    }
     pub fn send_key_up(&mut self, code: u8, ts: f32) {
         let keycode_res: Result<KeyCode, InvalidKeyCode> = code.try_into();
         if let Ok(keycode) = keycode_res {
-            self.0.send(Event::Key(KeyEvent {
+            self.sink.send(Event::Key(KeyEvent {
                 kind: KeyEventKind::Up,
                 code: keycode,
                 ts,
@@ -96,7 +215,7 @@ impl EventQueueHandle {
     pub fn send_key_down(&mut self, code: u8, ts: f32) {
         let keycode_res: Result<KeyCode, InvalidKeyCode> = code.try_into();
         if let Ok(keycode) = keycode_res {
-            self.0.send(Event::Key(KeyEvent {
+            self.sink.send(Event::Key(KeyEvent {
                 kind: KeyEventKind::Down,
                 code: keycode,
                 ts,
@@ -104,13 +223,13 @@ impl EventQueueHandle {
         }
     }
     pub fn send_window_resize(&mut self, width: u32, height: u32) {
-        self.0.send(Event::WindowResize(WindowResizeEvent {
+        self.sink.send(Event::WindowResize(WindowResizeEvent {
             width: width as usize,
             height: height as usize,
         }))
     }
     pub fn send_file_read(&mut self, name: &str, filename: &str, data: &[u8]) -> String {
-        self.0.send(Event::FileRead(FileReadEvent {
+        self.sink.send(Event::FileRead(FileReadEvent {
             data: Rc::new(data.to_vec()),
             name: name.to_string(),
             filename: filename.to_string(),
@@ -142,8 +261,17 @@ impl GameHandle {
     pub fn window(&self) -> WindowHandle {
         WindowHandle(self.window.clone())
     }
+    pub fn request_pointer_lock(&self) {
+        self.window.borrow_mut().set_pointer_lock(true)
+    }
+    pub fn release_pointer_lock(&self) {
+        self.window.borrow_mut().set_pointer_lock(false)
+    }
     pub fn event_queue(&self) -> EventQueueHandle {
-        EventQueueHandle(self.game.event_sink())
+        EventQueueHandle {
+            sink: self.game.event_sink(),
+            window: self.window.clone(),
+        }
     }
     pub fn tick(&mut self, now: f32) -> String {
         self.window.borrow_mut().update();
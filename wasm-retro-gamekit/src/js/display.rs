@@ -38,6 +38,7 @@ pub struct JSCanvasWindow {
     max_width: usize,
     max_height: usize,
     scale: usize,
+    pointer_lock: bool,
     image_data: JSImageData,
     resize_events: Source<WindowResizeEvent>,
 }
@@ -48,6 +49,7 @@ impl JSCanvasWindow {
             max_width,
             max_height,
             scale: 1,
+            pointer_lock: false,
             image_data: JSImageData::new(0, 0),
             resize_events: events,
         }
@@ -82,6 +84,34 @@ impl JSCanvasWindow {
     fn frame_height(&self) -> usize {
         self.image_height() / self.scale
     }
+    /// canvas_to_scene converts a position in canvas pixels into a
+    /// position relative to the scene size, and a position in scene pixels
+    pub fn canvas_to_scene(&self, canvas_pos: V2<f32>) -> (V2<f32>, V2<f32>) {
+        let scene_pos = canvas_pos / self.scale as f32;
+        let width = self.frame_width() as f32;
+        let height = self.frame_height() as f32;
+        // Nothing was rendered yet
+        if width == 0.0 || height == 0.0 {
+            return (V2::zero(), scene_pos);
+        }
+        (
+            V2::new(scene_pos.x / width, scene_pos.y / height),
+            scene_pos,
+        )
+    }
+    /// canvas_to_scene_delta converts a motion in canvas pixels
+    /// into a motion in scene pixels
+    pub fn canvas_to_scene_delta(&self, canvas_delta: V2<f32>) -> V2<f32> {
+        canvas_delta / self.scale as f32
+    }
+    /// Pointer lock can only be acquired by JS during a user gesture,
+    /// so this only records whether the game wants it.
+    pub fn set_pointer_lock(&mut self, lock: bool) {
+        self.pointer_lock = lock;
+    }
+    pub fn pointer_lock(&self) -> bool {
+        self.pointer_lock
+    }
     pub fn image_width(&self) -> usize {
         self.image_data.width
     }
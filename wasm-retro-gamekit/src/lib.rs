@@ -2,6 +2,7 @@
 pub mod asset;
 pub mod compress;
 pub mod display;
+pub mod ecs;
 pub mod event;
 pub mod game;
 pub mod graphics;
@@ -11,5 +12,6 @@ pub mod input;
 pub mod js;
 pub mod num;
 pub mod pair;
+pub mod particles;
 pub mod physics;
 pub mod vector;
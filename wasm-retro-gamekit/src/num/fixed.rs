@@ -0,0 +1,898 @@
+use std::fmt;
+use std::ops::{
+    Add,
+    AddAssign,
+    Div,
+    DivAssign,
+    Mul,
+    MulAssign,
+    Neg,
+    Rem,
+    RemAssign,
+    Sub,
+    SubAssign,
+};
+
+use bincode::{Decode, Encode};
+use num_traits::{FromPrimitive, Num, NumCast, One, ToPrimitive, Zero};
+
+use super::Float;
+
+// Transcendental functions are computed in Q32.32 with i128 intermediates, so
+// every fixed-point type gets the same integer-only, deterministic results.
+mod q32 {
+    pub const FRAC: u32 = 32;
+    pub const ONE: i64 = 1 << FRAC;
+    pub const PI: i64 = 13_493_037_705;
+    pub const HALF_PI: i64 = 6_746_518_852;
+    pub const TAU: i64 = 26_986_075_409;
+    pub const LN_2: i64 = 2_977_044_472;
+    pub const LN_10: i64 = 9_889_527_671;
+    pub const DEGREES: i64 = 246_083_499_208;
+    pub const RADIANS: i64 = 74_961_321;
+
+    pub fn sat(x: i128) -> i64 {
+        x.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    pub fn mul(a: i64, b: i64) -> i64 {
+        sat((a as i128 * b as i128 + (1 << (FRAC - 1))) >> FRAC)
+    }
+
+    pub fn div(a: i64, b: i64) -> i64 {
+        match (a.signum(), b) {
+            (0, 0) => 0,
+            (1, 0) => i64::MAX,
+            (_, 0) => i64::MIN,
+            _ => sat(((a as i128) << FRAC) / b as i128),
+        }
+    }
+
+    pub fn sqrt(a: i64) -> i64 {
+        match a > 0 {
+            true => super::isqrt((a as u128) << FRAC) as i64,
+            false => 0,
+        }
+    }
+
+    // sqrt(a^2 + b^2) without overflowing the squares
+    pub fn hypot(a: i64, b: i64) -> i64 {
+        sat(super::hypot_wide(a as i128, b as i128))
+    }
+
+    // Reduce an angle to [-pi, pi]
+    fn reduce(a: i64) -> i64 {
+        let r = a % TAU;
+        if r > PI {
+            r - TAU
+        } else if r < -PI {
+            r + TAU
+        } else {
+            r
+        }
+    }
+
+    pub fn sin(a: i64) -> i64 {
+        let x = match reduce(a) {
+            r if r > HALF_PI => PI - r,
+            r if r < -HALF_PI => -PI - r,
+            r => r,
+        };
+        // Taylor series, which converges quickly for |x| <= pi / 2
+        let x2 = mul(x, x);
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 1;
+        while term != 0 {
+            term = -mul(term, x2) / ((2 * n) * (2 * n + 1));
+            sum += term;
+            n += 1;
+        }
+        sum
+    }
+
+    pub fn cos(a: i64) -> i64 {
+        sin(reduce(a) + HALF_PI)
+    }
+
+    pub fn atan(x: i64) -> i64 {
+        if x > ONE {
+            return HALF_PI - atan(div(ONE, x));
+        }
+        if x < -ONE {
+            return -HALF_PI - atan(div(ONE, x));
+        }
+        // Halve the angle twice, so |y| <= tan(pi / 16) and the series
+        // converges in a few terms
+        let mut y = x;
+        for _ in 0..2 {
+            y = div(y, ONE + sqrt(ONE + mul(y, y)));
+        }
+        let y2 = mul(y, y);
+        let mut power = y;
+        let mut sum = y;
+        let mut n = 1;
+        while power != 0 {
+            power = -mul(power, y2);
+            sum += power / (2 * n + 1);
+            n += 1;
+        }
+        sum * 4
+    }
+
+    pub fn atan2(y: i64, x: i64) -> i64 {
+        match (x.signum(), y.signum()) {
+            (1, _) => atan(div(y, x)),
+            (-1, -1) => atan(div(y, x)) - PI,
+            (-1, _) => atan(div(y, x)) + PI,
+            (_, 1) => HALF_PI,
+            (_, -1) => -HALF_PI,
+            _ => 0,
+        }
+    }
+
+    pub fn asin(x: i64) -> i64 {
+        let x = x.clamp(-ONE, ONE);
+        atan2(x, sqrt(ONE - mul(x, x)))
+    }
+
+    pub fn acos(x: i64) -> i64 {
+        let x = x.clamp(-ONE, ONE);
+        atan2(sqrt(ONE - mul(x, x)), x)
+    }
+
+    pub fn exp(x: i64) -> i64 {
+        // Results beyond 2^31 saturate, those below 2^-33 round to zero
+        if x > 31 * LN_2 {
+            return i64::MAX;
+        }
+        if x < -33 * LN_2 {
+            return 0;
+        }
+        // e^x = 2^k e^r with |r| <= ln(2) / 2
+        let k = (x + LN_2 / 2).div_euclid(LN_2);
+        let r = x - k * LN_2;
+        let mut term = ONE;
+        let mut sum = ONE;
+        let mut n = 1;
+        while term != 0 {
+            term = mul(term, r) / n;
+            sum += term;
+            n += 1;
+        }
+        match k >= 0 {
+            true => sat((sum as i128) << k),
+            false => sum >> -k,
+        }
+    }
+
+    pub fn ln(x: i64) -> i64 {
+        if x <= 0 {
+            return i64::MIN;
+        }
+        // x = 2^k m with 1 <= m < 2
+        let k = (63 - x.leading_zeros() as i64) - FRAC as i64;
+        let m = match k >= 0 {
+            true => x >> k,
+            false => x << -k,
+        };
+        // ln(m) = 2 atanh(s) with s = (m - 1) / (m + 1) < 1 / 3
+        let s = div(m - ONE, m + ONE);
+        let s2 = mul(s, s);
+        let mut power = s;
+        let mut sum = s;
+        let mut n = 1;
+        while power != 0 {
+            power = mul(power, s2);
+            sum += power / (2 * n + 1);
+            n += 1;
+        }
+        k * LN_2 + 2 * sum
+    }
+
+    pub fn powf(x: i64, y: i64) -> i64 {
+        if x == 0 {
+            return match y.signum() {
+                1 => 0,
+                0 => ONE,
+                _ => i64::MAX,
+            };
+        }
+        let magnitude = exp(mul(y, ln(x.saturating_abs())));
+        if x > 0 {
+            return magnitude;
+        }
+        // Negative bases only have real powers for integer exponents
+        match (y & (ONE - 1), (y >> FRAC) & 1) {
+            (0, 0) => magnitude,
+            (0, _) => -magnitude,
+            _ => 0,
+        }
+    }
+
+    pub fn sinh(x: i64) -> i64 {
+        sat((exp(x) as i128 - exp(x.saturating_neg()) as i128) >> 1)
+    }
+
+    pub fn cosh(x: i64) -> i64 {
+        sat((exp(x) as i128 + exp(x.saturating_neg()) as i128) >> 1)
+    }
+
+    pub fn tanh(x: i64) -> i64 {
+        // tanh(16) is 1 to well beyond our precision
+        match x.clamp(-16 * ONE, 16 * ONE) {
+            x if x.abs() == 16 * ONE => x.signum() * ONE,
+            x => {
+                let (e, ei) = (exp(x), exp(-x));
+                div(e - ei, e + ei)
+            },
+        }
+    }
+
+    pub fn asinh(x: i64) -> i64 {
+        let a = x.saturating_abs();
+        x.signum() * ln(a.saturating_add(hypot(a, ONE)))
+    }
+
+    pub fn acosh(x: i64) -> i64 {
+        if x <= ONE {
+            return 0;
+        }
+        let root = super::isqrt((x as i128 * x as i128 - (ONE as i128) * (ONE as i128)) as u128);
+        ln(x.saturating_add(root as i64))
+    }
+
+    pub fn atanh(x: i64) -> i64 {
+        match x {
+            x if x >= ONE => i64::MAX,
+            x if x <= -ONE => i64::MIN,
+            x => ln(div(ONE + x, ONE - x)) / 2,
+        }
+    }
+}
+
+// Largest r with r^2 <= n, digit by digit
+fn isqrt(n: u128) -> u128 {
+    let mut x = n;
+    let mut r = 0;
+    let mut bit = 1 << 126;
+    while bit > n {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if x >= r + bit {
+            x -= r + bit;
+            r = (r >> 1) + bit;
+        } else {
+            r >>= 1;
+        }
+        bit >>= 2;
+    }
+    r
+}
+
+// Largest r with r^3 <= n, by bisection
+fn icbrt(n: u128) -> u128 {
+    let (mut lo, mut hi) = (0u128, 1u128 << 43);
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        let cube = mid.checked_mul(mid).and_then(|sq| sq.checked_mul(mid));
+        match cube.is_some_and(|cube| cube <= n) {
+            true => lo = mid,
+            false => hi = mid - 1,
+        }
+    }
+    lo
+}
+
+// Square root of a^2 + b^2 for values of at most 64 bits
+fn hypot_wide(a: i128, b: i128) -> i128 {
+    isqrt((a * a) as u128 + (b * b) as u128) as i128
+}
+
+macro_rules! fixed {
+    ($(#[$attr:meta])* $name:ident, $raw:ty, $wide:ty, $frac:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+        pub struct $name($raw);
+
+        impl $name {
+            /// Number of fractional bits.
+            pub const FRAC_BITS: u32 = $frac;
+            pub const ZERO: Self = Self(0);
+            pub const ONE: Self = Self(1 << $frac);
+            pub const MAX: Self = Self(<$raw>::MAX);
+            pub const MIN: Self = Self(<$raw>::MIN);
+
+            const MASK: $raw = (1 << $frac) - 1;
+            const HALF: $raw = 1 << ($frac - 1);
+            const SCALE: f64 = (1u64 << $frac) as f64;
+
+            /// Wraps the raw representation, i.e. the value times 2^FRAC_BITS.
+            pub const fn from_bits(bits: $raw) -> Self {
+                Self(bits)
+            }
+
+            /// Returns the raw representation, i.e. the value times 2^FRAC_BITS.
+            pub const fn to_bits(self) -> $raw {
+                self.0
+            }
+
+            fn saturate(x: i128) -> Self {
+                Self(x.clamp(<$raw>::MIN as i128, <$raw>::MAX as i128) as $raw)
+            }
+
+            fn as_f64(self) -> f64 {
+                self.0 as f64 / Self::SCALE
+            }
+
+            fn to_q32(self) -> i64 {
+                (self.0 as i64) << (q32::FRAC - $frac)
+            }
+
+            fn from_q32(q: i64) -> Self {
+                let shift = q32::FRAC - $frac;
+                Self::saturate(((q as i128) + ((1i128 << shift) >> 1)) >> shift)
+            }
+
+            fn map_q32(self, f: fn(i64) -> i64) -> Self {
+                Self::from_q32(f(self.to_q32()))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&self.as_f64()).finish()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.as_f64(), f)
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0.saturating_add(rhs.0))
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+            fn mul(self, rhs: Self) -> Self {
+                let product = self.0 as $wide * rhs.0 as $wide + Self::HALF as $wide;
+                Self::saturate((product >> $frac) as i128)
+            }
+        }
+
+        impl Div for $name {
+            type Output = Self;
+            /// Division by zero saturates towards the sign of the dividend.
+            fn div(self, rhs: Self) -> Self {
+                match (self.0.signum(), rhs.0) {
+                    (0, 0) => Self::ZERO,
+                    (1, 0) => Self::MAX,
+                    (_, 0) => Self::MIN,
+                    _ => Self::saturate((((self.0 as $wide) << $frac) / rhs.0 as $wide) as i128),
+                }
+            }
+        }
+
+        impl Rem for $name {
+            type Output = Self;
+            fn rem(self, rhs: Self) -> Self {
+                match rhs.0 {
+                    0 => Self::ZERO,
+                    _ => Self(self.0.wrapping_rem(rhs.0)),
+                }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Self(self.0.saturating_neg())
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, rhs: Self) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign for $name {
+            fn div_assign(&mut self, rhs: Self) {
+                *self = *self / rhs;
+            }
+        }
+
+        impl RemAssign for $name {
+            fn rem_assign(&mut self, rhs: Self) {
+                *self = *self % rhs;
+            }
+        }
+
+        impl Zero for $name {
+            fn zero() -> Self {
+                Self::ZERO
+            }
+            fn is_zero(&self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl One for $name {
+            fn one() -> Self {
+                Self::ONE
+            }
+        }
+
+        impl Num for $name {
+            type FromStrRadixErr = num_traits::ParseFloatError;
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                <f64 as Num>::from_str_radix(s, radix).map(|x| Self::from_f64(x).unwrap_or_default())
+            }
+        }
+
+        impl FromPrimitive for $name {
+            fn from_i64(n: i64) -> Option<Self> {
+                Some(Self::saturate((n as i128) << $frac))
+            }
+            fn from_u64(n: u64) -> Option<Self> {
+                Some(Self::saturate((n as i128) << $frac))
+            }
+            /// Out of range values saturate, NaN has no fixed-point equivalent.
+            fn from_f64(n: f64) -> Option<Self> {
+                match n.is_nan() {
+                    true => None,
+                    false => Some(Self((n * Self::SCALE).round() as $raw)),
+                }
+            }
+        }
+
+        impl ToPrimitive for $name {
+            fn to_i64(&self) -> Option<i64> {
+                Some((self.0 / (1 << $frac)) as i64)
+            }
+            fn to_u64(&self) -> Option<u64> {
+                match self.0 >= 0 {
+                    true => Some((self.0 >> $frac) as u64),
+                    false => None,
+                }
+            }
+            fn to_f64(&self) -> Option<f64> {
+                Some(self.as_f64())
+            }
+        }
+
+        impl NumCast for $name {
+            fn from<N: ToPrimitive>(n: N) -> Option<Self> {
+                n.to_f64().and_then(Self::from_f64)
+            }
+        }
+
+        impl num_traits::Float for $name {
+            /// There is no NaN, so this is zero.
+            fn nan() -> Self {
+                Self::ZERO
+            }
+            /// The largest value, which results saturate to.
+            fn infinity() -> Self {
+                Self::MAX
+            }
+            fn neg_infinity() -> Self {
+                Self::MIN
+            }
+            fn neg_zero() -> Self {
+                Self::ZERO
+            }
+            fn min_value() -> Self {
+                Self::MIN
+            }
+            fn min_positive_value() -> Self {
+                Self(1)
+            }
+            fn epsilon() -> Self {
+                Self(1)
+            }
+            fn max_value() -> Self {
+                Self::MAX
+            }
+            fn is_nan(self) -> bool {
+                false
+            }
+            fn is_infinite(self) -> bool {
+                self == Self::MAX || self == Self::MIN
+            }
+            fn is_finite(self) -> bool {
+                !self.is_infinite()
+            }
+            fn is_normal(self) -> bool {
+                self.0 != 0 && self.is_finite()
+            }
+            fn classify(self) -> std::num::FpCategory {
+                match self.0 {
+                    0 => std::num::FpCategory::Zero,
+                    _ if self.is_infinite() => std::num::FpCategory::Infinite,
+                    _ => std::num::FpCategory::Normal,
+                }
+            }
+            fn floor(self) -> Self {
+                Self(self.0 & !Self::MASK)
+            }
+            fn ceil(self) -> Self {
+                Self(self.0.saturating_add(Self::MASK) & !Self::MASK)
+            }
+            /// Rounds half-way cases away from zero, like `f64::round`.
+            fn round(self) -> Self {
+                match self.0 >= 0 {
+                    true => Self(self.0.saturating_add(Self::HALF) & !Self::MASK),
+                    false => -(-self).round(),
+                }
+            }
+            fn trunc(self) -> Self {
+                match self.0 >= 0 {
+                    true => self.floor(),
+                    false => -(-self).floor(),
+                }
+            }
+            fn fract(self) -> Self {
+                self - self.trunc()
+            }
+            fn abs(self) -> Self {
+                Self(self.0.saturating_abs())
+            }
+            fn signum(self) -> Self {
+                match self.0 >= 0 {
+                    true => Self::ONE,
+                    false => -Self::ONE,
+                }
+            }
+            fn is_sign_positive(self) -> bool {
+                self.0 >= 0
+            }
+            fn is_sign_negative(self) -> bool {
+                self.0 < 0
+            }
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                let sum = self.0 as i128 * a.0 as i128 + ((b.0 as i128) << $frac);
+                Self::saturate((sum + Self::HALF as i128) >> $frac)
+            }
+            fn recip(self) -> Self {
+                Self::ONE / self
+            }
+            fn powi(self, n: i32) -> Self {
+                let mut base = match n < 0 {
+                    true => self.recip(),
+                    false => self,
+                };
+                let mut exp = n.unsigned_abs();
+                let mut acc = Self::ONE;
+                while exp > 0 {
+                    if exp & 1 == 1 {
+                        acc *= base;
+                    }
+                    exp >>= 1;
+                    if exp > 0 {
+                        base *= base;
+                    }
+                }
+                acc
+            }
+            /// Negative bases with fractional exponents give zero.
+            fn powf(self, n: Self) -> Self {
+                Self::from_q32(q32::powf(self.to_q32(), n.to_q32()))
+            }
+            /// Negative values give zero.
+            fn sqrt(self) -> Self {
+                match self.0 > 0 {
+                    true => Self(isqrt((self.0 as u128) << $frac) as $raw),
+                    false => Self::ZERO,
+                }
+            }
+            fn exp(self) -> Self {
+                self.map_q32(q32::exp)
+            }
+            fn exp2(self) -> Self {
+                Self::from_q32(q32::exp(q32::mul(self.to_q32(), q32::LN_2)))
+            }
+            /// Non-positive values give `neg_infinity()`.
+            fn ln(self) -> Self {
+                self.map_q32(q32::ln)
+            }
+            fn log(self, base: Self) -> Self {
+                Self::from_q32(q32::div(q32::ln(self.to_q32()), q32::ln(base.to_q32())))
+            }
+            fn log2(self) -> Self {
+                Self::from_q32(q32::div(q32::ln(self.to_q32()), q32::LN_2))
+            }
+            fn log10(self) -> Self {
+                Self::from_q32(q32::div(q32::ln(self.to_q32()), q32::LN_10))
+            }
+            fn to_degrees(self) -> Self {
+                Self::from_q32(q32::mul(self.to_q32(), q32::DEGREES))
+            }
+            fn to_radians(self) -> Self {
+                Self::from_q32(q32::mul(self.to_q32(), q32::RADIANS))
+            }
+            fn max(self, other: Self) -> Self {
+                Ord::max(self, other)
+            }
+            fn min(self, other: Self) -> Self {
+                Ord::min(self, other)
+            }
+            fn abs_sub(self, other: Self) -> Self {
+                Ord::max(self - other, Self::ZERO)
+            }
+            fn cbrt(self) -> Self {
+                let root = icbrt((self.0.unsigned_abs() as u128) << (2 * $frac)) as i128;
+                Self::saturate(self.0.signum() as i128 * root)
+            }
+            fn hypot(self, other: Self) -> Self {
+                Self::saturate(hypot_wide(self.0 as i128, other.0 as i128))
+            }
+            fn sin(self) -> Self {
+                self.map_q32(q32::sin)
+            }
+            fn cos(self) -> Self {
+                self.map_q32(q32::cos)
+            }
+            fn tan(self) -> Self {
+                let x = self.to_q32();
+                Self::from_q32(q32::div(q32::sin(x), q32::cos(x)))
+            }
+            fn asin(self) -> Self {
+                self.map_q32(q32::asin)
+            }
+            fn acos(self) -> Self {
+                self.map_q32(q32::acos)
+            }
+            fn atan(self) -> Self {
+                self.map_q32(q32::atan)
+            }
+            fn atan2(self, other: Self) -> Self {
+                Self::from_q32(q32::atan2(self.to_q32(), other.to_q32()))
+            }
+            fn sin_cos(self) -> (Self, Self) {
+                (self.sin(), self.cos())
+            }
+            fn exp_m1(self) -> Self {
+                Self::from_q32(q32::exp(self.to_q32()).saturating_sub(q32::ONE))
+            }
+            fn ln_1p(self) -> Self {
+                Self::from_q32(q32::ln(self.to_q32().saturating_add(q32::ONE)))
+            }
+            fn sinh(self) -> Self {
+                self.map_q32(q32::sinh)
+            }
+            fn cosh(self) -> Self {
+                self.map_q32(q32::cosh)
+            }
+            fn tanh(self) -> Self {
+                self.map_q32(q32::tanh)
+            }
+            fn asinh(self) -> Self {
+                self.map_q32(q32::asinh)
+            }
+            fn acosh(self) -> Self {
+                self.map_q32(q32::acosh)
+            }
+            fn atanh(self) -> Self {
+                self.map_q32(q32::atanh)
+            }
+            fn integer_decode(self) -> (u64, i16, i8) {
+                let sign = match self.0 < 0 {
+                    true => -1,
+                    false => 1,
+                };
+                (self.0.unsigned_abs() as u64, -($frac as i16), sign)
+            }
+        }
+
+        // Fixed-point numbers have no NaNs, so their order is total
+        impl num_traits::float::TotalOrder for $name {
+            fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.cmp(other)
+            }
+        }
+
+        impl Float for $name {}
+    };
+}
+
+fixed!(
+    /// Q16.16 fixed-point number, for deterministic simulations in a small
+    /// world. Values range from -32768 to 32768 in steps of 2^-16.
+    ///
+    /// Arithmetic saturates instead of overflowing and there is no NaN.
+    Fixed32,
+    i32,
+    i64,
+    16
+);
+
+fixed!(
+    /// Q32.32 fixed-point number, for deterministic simulations. Values range
+    /// from -2^31 to 2^31 in steps of 2^-32.
+    ///
+    /// Arithmetic saturates instead of overflowing and there is no NaN.
+    Fixed64,
+    i64,
+    i128,
+    32
+);
+
+#[cfg(test)]
+mod test {
+    use num_traits::Float as _;
+
+    use super::*;
+    use crate::physics::box2d::{box2d_universe, HitBox, Mass, Mov};
+    use crate::physics::testing::Body;
+    use crate::vector::v2::V2;
+
+    fn f64s(x: f64) -> Fixed64 {
+        Fixed64::from_f64(x).unwrap()
+    }
+
+    fn f32s(x: f64) -> Fixed32 {
+        Fixed32::from_f64(x).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(f64s(1.5) * f64s(2.25), f64s(3.375));
+        assert_eq!(f64s(-7.5) / f64s(2.5), f64s(-3.0));
+        assert_eq!(f32s(3.5) % f32s(2.0), f32s(1.5));
+        assert_eq!(f32s(0.75) + f32s(-1.0), f32s(-0.25));
+        assert_eq!(Fixed32::MAX + Fixed32::ONE, Fixed32::MAX);
+        assert_eq!(Fixed32::MIN - Fixed32::ONE, Fixed32::MIN);
+        assert_eq!(f32s(300.0) * f32s(300.0), Fixed32::MAX);
+        assert_eq!(f32s(1.0) / Fixed32::ZERO, Fixed32::infinity());
+        assert_eq!(f32s(-1.0) / Fixed32::ZERO, Fixed32::neg_infinity());
+        assert_eq!(Fixed32::from_usize(40_000), Some(Fixed32::MAX));
+        assert_eq!(Fixed64::from_f32(0.5), Some(f64s(0.5)));
+        assert_eq!(Fixed64::from_f64(f64::NAN), None);
+        assert_eq!(f64s(-2.75).to_i64(), Some(-2));
+        assert_eq!(f64s(-2.75).to_f32(), Some(-2.75));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(f32s(-2.5).floor(), f32s(-3.0));
+        assert_eq!(f32s(-2.5).ceil(), f32s(-2.0));
+        assert_eq!(f32s(-2.5).round(), f32s(-3.0));
+        assert_eq!(f32s(2.5).round(), f32s(3.0));
+        assert_eq!(f32s(-2.5).trunc(), f32s(-2.0));
+        assert_eq!(f32s(-2.25).fract(), f32s(-0.25));
+        assert_eq!(f64s(-3.0).abs(), f64s(3.0));
+        assert_eq!(f64s(-3.0).signum(), f64s(-1.0));
+    }
+
+    #[test]
+    fn test_powers_and_roots() {
+        assert_eq!(f64s(16.0).sqrt(), f64s(4.0));
+        assert_eq!(f32s(2.0).sqrt().to_bits(), (2f64.sqrt() * 65536.0) as i32);
+        assert_eq!(f64s(-4.0).sqrt(), Fixed64::ZERO);
+        assert_eq!(f64s(-27.0).cbrt(), f64s(-3.0));
+        assert_eq!(f64s(3.0).hypot(f64s(4.0)), f64s(5.0));
+        assert_eq!(f64s(1.5).powi(3), f64s(3.375));
+        assert_eq!(f64s(2.0).powi(-2), f64s(0.25));
+        assert_eq!(f32s(3000.0).hypot(f32s(4000.0)), f32s(5000.0));
+        assert_eq!(f32s(30_000.0).hypot(f32s(40_000.0)), Fixed32::MAX);
+    }
+
+    fn assert_close(name: &str, x: f64, actual: Fixed64, expected: f64, tolerance: f64) {
+        let error = (actual.to_f64().unwrap() - expected).abs();
+        let allowed = tolerance * expected.abs().max(1.0);
+        assert!(
+            error <= allowed,
+            "{name}({x}) = {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn test_transcendental_accuracy() {
+        let tolerance = 1e-8;
+        for i in -200..=200 {
+            let x = i as f64 * 0.0731;
+            let fx = f64s(x);
+            assert_close("sin", x, fx.sin(), x.sin(), tolerance);
+            assert_close("cos", x, fx.cos(), x.cos(), tolerance);
+            assert_close("atan", x, fx.atan(), x.atan(), tolerance);
+            assert_close("atan2", x, fx.atan2(f64s(-1.3)), x.atan2(-1.3), tolerance);
+            assert_close("exp", x, fx.exp(), x.exp(), 1e-7);
+            assert_close("tanh", x, fx.tanh(), x.tanh(), tolerance);
+            if x > 0.0 {
+                assert_close("ln", x, fx.ln(), x.ln(), tolerance);
+                assert_close("powf", x, fx.powf(f64s(1.7)), x.powf(1.7), 1e-7);
+            }
+            if x.abs() <= 1.0 {
+                assert_close("asin", x, fx.asin(), x.asin(), tolerance);
+                assert_close("acos", x, fx.acos(), x.acos(), tolerance);
+            }
+        }
+        assert_eq!(Fixed64::ZERO.ln(), Fixed64::neg_infinity());
+        assert_eq!(f64s(100.0).exp(), Fixed64::infinity());
+        assert_close("powf", -2.0, f64s(-2.0).powf(f64s(3.0)), -8.0, 1e-7);
+        assert_eq!(f64s(-2.0).powf(f64s(0.5)), Fixed64::ZERO);
+    }
+
+    // A ball thrown at an angle bouncing around in a box, returning where it
+    // ends up
+    fn bounce<T: Float>() -> V2<T> {
+        let n = |x: f64| T::from_f64(x).unwrap();
+        let mut universe = box2d_universe(n(0.8));
+        universe.set_gravity(V2::new(n(0.0), n(0.001)));
+        let walls = [
+            (-60.0, -60.0, 120, 10),
+            (-60.0, 50.0, 120, 10),
+            (-60.0, -50.0, 10, 100),
+            (50.0, -50.0, 10, 100),
+        ];
+        for (x, y, w, h) in walls {
+            let mov = Mov::new(V2::new(n(x), n(y)), V2::zero(), V2::zero());
+            universe
+                .space_mut()
+                .add(Body::new(HitBox::new(w, h, mov, Mass::Infinite)));
+        }
+        let mov = Mov::new(
+            V2::new(n(-20.0), n(-20.0)),
+            V2::new(n(0.07), n(-0.03)),
+            V2::zero(),
+        );
+        let ball = universe
+            .space_mut()
+            .add(Body::new(HitBox::new(8, 8, mov, Mass::Fixed(n(1.0)))));
+        for _ in 0..300 {
+            universe.tick(16.0);
+        }
+        universe.space().get(ball).unwrap().hitbox.mov.pos
+    }
+
+    #[test]
+    fn test_box2d_physics() {
+        let float = bounce::<f64>();
+        let fixed = bounce::<Fixed64>();
+        assert_eq!(fixed, bounce::<Fixed64>());
+        assert!(
+            (fixed.x.to_f64().unwrap() - float.x).abs() < 0.01,
+            "{fixed:?} vs {float:?}"
+        );
+        assert!(
+            (fixed.y.to_f64().unwrap() - float.y).abs() < 0.01,
+            "{fixed:?} vs {float:?}"
+        );
+        let small = bounce::<Fixed32>();
+        assert!(
+            (small.x.to_f64().unwrap() - float.x).abs() < 0.5,
+            "{small:?} vs {float:?}"
+        );
+        assert!(
+            (small.y.to_f64().unwrap() - float.y).abs() < 0.5,
+            "{small:?} vs {float:?}"
+        );
+    }
+}
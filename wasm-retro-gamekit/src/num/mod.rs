@@ -1,9 +1,22 @@
+mod fixed;
+mod rng;
+
 use bincode::{Decode, Encode};
+pub use fixed::{Fixed32, Fixed64};
 pub use num_traits::bounds::Bounded;
 pub use num_traits::{FromPrimitive, One, ToPrimitive, Zero};
+pub use rng::Rng;
 
 pub trait Float:
-    num_traits::Float + FromPrimitive + ToPrimitive + Encode + Decode + Copy + Clone
+    num_traits::Float
+    + num_traits::float::TotalOrder
+    + FromPrimitive
+    + ToPrimitive
+    + Encode
+    + Decode
+    + Copy
+    + Clone
+    + 'static
 {
 }
 
@@ -0,0 +1,74 @@
+use bincode::{Decode, Encode};
+
+/// Rng is a small seedable pseudo-random number generator (SplitMix64),
+/// which gives the same numbers for the same seed on every platform,
+/// e.g. to replay effects or keep networked games in sync.
+///
+/// It's not suitable for cryptography.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// next_f32 returns a number from 0 inclusive to 1 exclusive
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// range returns a number from min inclusive to max exclusive,
+    /// or min if they're equal
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// below returns a number from 0 inclusive to n exclusive;
+    /// n must not be 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert_ne!(n, 0, "no number below 0");
+        // Multiply-shift instead of modulo, which would favor small numbers
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let xs: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let ys: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(Rng::new(43).next_u64(), xs[0]);
+        // Reference value of SplitMix64 seeded with 0
+        assert_eq!(Rng::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x));
+            assert!(rng.below(6) < 6);
+        }
+        let mean = (0..10_000).map(|_| rng.next_f32()).sum::<f32>() / 10_000.0;
+        assert!((mean - 0.5).abs() < 0.02, "{mean}");
+    }
+}
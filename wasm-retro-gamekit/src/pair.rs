@@ -1,7 +1,9 @@
 use std::cmp::Ordering;
 
+use bincode::{Decode, Encode};
+
 /// An unordered pair, e.g. Pair::new(a, b) == Pair::new(b, a);
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Encode, Decode)]
 pub struct Pair<T>(T, T);
 
 impl<T> Pair<T>
@@ -0,0 +1,303 @@
+use std::f32::consts::TAU;
+
+use crate::display::Frame;
+use crate::graphics::color::Rgba32;
+use crate::graphics::{Layer, Scene, Sprite, SpriteImage, SpriteImageRef, Viewport};
+use crate::num::Rng;
+use crate::vector::v2::V2;
+
+// How many images along the colors of a life are made for sprites
+const SPRITE_COLORS: usize = 16;
+
+/// Particle is a point of an effect, e.g. a spark, moving without colliding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub pos: V2<f32>,
+    pub vel: V2<f32>,
+    /// Time since the particle was emitted
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// life returns how far the particle is through its life, from 0 to 1
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Emitter emits particles and moves them, in a flat array, until
+/// their lifetime is over.
+///
+/// Like the physics, times are in milliseconds, and the y axis points
+/// down. Particles are picked with a seedable Rng, so an emitter with
+/// the same seed, settings and delta_ts gives the same particles.
+pub struct Emitter {
+    pub pos: V2<f32>,
+    /// Particles emitted per unit of time; 0 to only emit bursts
+    pub rate: f32,
+    /// Direction particles are emitted in, in radians from the x axis
+    pub angle: f32,
+    /// Angle of the cone of directions around angle; TAU for all of them
+    pub spread: f32,
+    /// Range of initial speeds, as (min, max)
+    pub speed: (f32, f32),
+    /// Range of lifetimes, as (min, max)
+    pub lifetime: (f32, f32),
+    pub gravity: V2<f32>,
+    /// Fraction of the velocity lost per unit of time, e.g. for smoke
+    pub damping: f32,
+    /// Colors through the life of particles, evenly spaced from
+    /// emission to expiry and blended in between, alpha included
+    pub colors: Vec<Rgba32>,
+    /// Width and height of the square drawn for each particle
+    pub size: usize,
+    /// Particles beyond this many aren't emitted
+    pub max_particles: usize,
+    particles: Vec<Particle>,
+    // Fraction of a particle left over by rate from the last update
+    owed: f32,
+    rng: Rng,
+}
+
+impl Emitter {
+    pub fn new(pos: V2<f32>, seed: u64) -> Self {
+        Self {
+            pos,
+            rate: 0.0,
+            angle: 0.0,
+            spread: TAU,
+            speed: (0.05, 0.1),
+            lifetime: (500.0, 1000.0),
+            gravity: V2::zero(),
+            damping: 0.0,
+            colors: vec![Rgba32::rgb(255, 255, 255)],
+            size: 1,
+            max_particles: 1024,
+            particles: vec![],
+            owed: 0.0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.owed = 0.0;
+    }
+
+    /// burst emits count particles at once, e.g. for an explosion
+    pub fn burst(&mut self, count: usize) {
+        let count = count.min(self.max_particles.saturating_sub(self.particles.len()));
+        for _ in 0..count {
+            let angle = self.angle + self.spread * (self.rng.next_f32() - 0.5);
+            let speed = self.rng.range(self.speed.0, self.speed.1);
+            let lifetime = self.rng.range(self.lifetime.0, self.lifetime.1);
+            self.particles.push(Particle {
+                pos: self.pos,
+                vel: V2::new(angle.cos(), angle.sin()) * speed,
+                age: 0.0,
+                lifetime,
+            });
+        }
+    }
+
+    /// update moves the particles, removes those which expired
+    /// and emits new ones at the rate of the emitter
+    pub fn update(&mut self, delta_t: f32) {
+        let damping = 1.0 / (1.0 + self.damping * delta_t);
+        for particle in self.particles.iter_mut() {
+            particle.age += delta_t;
+            particle.vel = (particle.vel + self.gravity * delta_t) * damping;
+            particle.pos = particle.pos + particle.vel * delta_t;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+        self.owed += self.rate * delta_t;
+        let count = self.owed.floor();
+        self.owed -= count;
+        self.burst(count as usize);
+    }
+
+    /// color returns the color of a particle at life, from 0 to 1
+    pub fn color(&self, life: f32) -> Rgba32 {
+        let last = match self.colors.len() {
+            0 => return Rgba32::rgba(0, 0, 0, 0),
+            n => n - 1,
+        };
+        let at = life.clamp(0.0, 1.0) * last as f32;
+        let i = (at.floor() as usize).min(last.saturating_sub(1));
+        let j = (i + 1).min(last);
+        let t = at - i as f32;
+        let (a, b) = (self.colors[i], self.colors[j]);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgba32::rgba(
+            mix(a.red, b.red),
+            mix(a.green, b.green),
+            mix(a.blue, b.blue),
+            mix(a.alpha, b.alpha),
+        )
+    }
+
+    /// render draws the particles into a frame showing the viewport,
+    /// blended into it by their alpha
+    pub fn render(&self, frame: &mut Frame, viewport: &Viewport) {
+        let half = (self.size / 2) as i64;
+        let size = self.size as i64;
+        for particle in self.particles.iter() {
+            let color = self.color(particle.life());
+            if color.alpha == 0 {
+                continue;
+            }
+            let tl = viewport.world_to_scene(particle.pos.round()) - V2::new(half, half);
+            for y in tl.y.max(0)..(tl.y + size).min(frame.h_i64()) {
+                for x in tl.x.max(0)..(tl.x + size).min(frame.w_i64()) {
+                    let pixel = frame.pixel_mut(x, y);
+                    *pixel = blend(*pixel, color);
+                }
+            }
+        }
+    }
+
+    /// add_sprites adds the particles to a scene showing the viewport,
+    /// as sprites on layer. Scenes don't blend sprites, so the alpha
+    /// of the colors only hides particles once it reaches 0.
+    pub fn add_sprites(&self, scene: &mut Scene, viewport: &Viewport, layer: Layer) {
+        let half = (self.size / 2) as i64;
+        let images: Vec<SpriteImageRef> = (0..SPRITE_COLORS)
+            .map(|i| {
+                let color = self.color(i as f32 / (SPRITE_COLORS - 1) as f32);
+                SpriteImage::monochrome(self.size, self.size, color)
+            })
+            .collect();
+        for particle in self.particles.iter() {
+            let step = (particle.life() * (SPRITE_COLORS - 1) as f32).round() as usize;
+            let pos = viewport.world_to_scene(particle.pos.round()) - V2::new(half, half);
+            scene.add_sprite(Sprite::new(pos, layer, images[step].clone()));
+        }
+    }
+}
+
+// Blend src over dst by the alpha of src
+fn blend(dst: Rgba32, src: Rgba32) -> Rgba32 {
+    let alpha = src.alpha as u32;
+    let mix = |s: u8, d: u8| ((s as u32 * alpha + d as u32 * (255 - alpha) + 127) / 255) as u8;
+    Rgba32::rgba(
+        mix(src.red, dst.red),
+        mix(src.green, dst.green),
+        mix(src.blue, dst.blue),
+        dst.alpha.max(src.alpha),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sparks(seed: u64) -> Emitter {
+        let mut emitter = Emitter::new(V2::new(10.0, 10.0), seed);
+        emitter.rate = 0.5;
+        emitter.speed = (0.01, 0.02);
+        emitter.lifetime = (100.0, 200.0);
+        emitter.gravity = V2::new(0.0, 0.0001);
+        emitter
+    }
+
+    #[test]
+    fn test_emission() {
+        let mut emitter = sparks(1);
+        emitter.update(3.0);
+        emitter.update(3.0);
+        assert_eq!(emitter.len(), 3);
+        emitter.burst(10);
+        assert_eq!(emitter.len(), 13);
+        emitter.max_particles = 15;
+        emitter.burst(10);
+        assert_eq!(emitter.len(), 15);
+
+        // Particles expire after their lifetime
+        emitter.rate = 0.0;
+        for _ in 0..20 {
+            emitter.update(10.0);
+        }
+        assert!(emitter.is_empty());
+    }
+
+    #[test]
+    fn test_determinism() {
+        let (mut a, mut b, mut c) = (sparks(7), sparks(7), sparks(8));
+        for _ in 0..50 {
+            a.update(4.0);
+            b.update(4.0);
+            c.update(4.0);
+        }
+        assert!(!a.is_empty());
+        assert_eq!(a.particles(), b.particles());
+        assert_ne!(a.particles(), c.particles());
+    }
+
+    #[test]
+    fn test_motion() {
+        let mut emitter = Emitter::new(V2::zero(), 0);
+        emitter.angle = std::f32::consts::FRAC_PI_2;
+        emitter.spread = 0.0;
+        emitter.speed = (0.1, 0.1);
+        emitter.lifetime = (1000.0, 1000.0);
+        emitter.gravity = V2::new(0.001, 0.0);
+        emitter.burst(1);
+        emitter.update(10.0);
+        let particle = emitter.particles()[0];
+        assert!((particle.vel - V2::new(0.01, 0.1)).mag() < 1e-6);
+        assert!((particle.pos - V2::new(0.1, 1.0)).mag() < 1e-6);
+        assert_eq!(particle.life(), 0.01);
+    }
+
+    #[test]
+    fn test_color_over_life() {
+        let mut emitter = Emitter::new(V2::zero(), 0);
+        emitter.colors = vec![
+            Rgba32::rgb(255, 255, 0),
+            Rgba32::rgb(255, 0, 0),
+            Rgba32::rgba(0, 0, 0, 0),
+        ];
+        assert_eq!(emitter.color(0.0), Rgba32::rgb(255, 255, 0));
+        assert_eq!(emitter.color(0.25), Rgba32::rgb(255, 128, 0));
+        assert_eq!(emitter.color(0.75), Rgba32::rgba(128, 0, 0, 128));
+        assert_eq!(emitter.color(1.0), Rgba32::rgba(0, 0, 0, 0));
+        emitter.colors = vec![Rgba32::rgb(1, 2, 3)];
+        assert_eq!(emitter.color(0.5), Rgba32::rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut emitter = Emitter::new(V2::new(5.0, 5.0), 0);
+        emitter.speed = (0.0, 0.0);
+        emitter.size = 2;
+        emitter.colors = vec![Rgba32::rgba(255, 0, 0, 255), Rgba32::rgba(255, 0, 0, 0)];
+        emitter.lifetime = (100.0, 100.0);
+        emitter.burst(1);
+        emitter.update(50.0);
+
+        let black = Rgba32::rgb(0, 0, 0);
+        let mut frame = Frame::new(8, 8, vec![black; 64]);
+        let viewport = Viewport::new(V2::new(2, 2), 8, 8);
+        emitter.render(&mut frame, &viewport);
+        // Half way through its life, the particle is half transparent
+        let blended = Rgba32::rgb(128, 0, 0);
+        assert_eq!(frame.pixel(2, 2), blended);
+        assert_eq!(frame.pixel(3, 3), blended);
+        assert_eq!(frame.pixel(4, 3), black);
+        assert_eq!(frame.pixel(1, 2), black);
+    }
+}
@@ -1,46 +1,180 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+
+pub use super::character::CharacterController;
+pub use super::constraint::{Constraint, ConstraintKey, Constraints};
+pub use super::field::ForceField;
 pub use super::identity::ObjectId;
 use super::identity::{Identity, IdentityKey, ObjectKey};
-use super::universe::{Physics, Space, Universe};
+pub use super::shape::Shape;
+use super::shape::{Contact, Solid};
+pub use super::tilemap::{Tile, TileContacts, TileMap};
+use super::universe::{Physics, Restore, Space, Universe};
+use crate::event::{CollisionEvent, CollisionEventKind, Event, Events, Sink};
 use crate::num::Float;
 use crate::pair::Pair;
 use crate::vector::v2::{Axis, V2 as V};
 
+#[derive(Clone, Encode, Decode)]
 pub struct Mov<T> {
     pub pos: V<T>,
     pub vel: V<T>,
     pub acc: V<T>,
+    /// Position before the last update, to interpolate when painting
+    pub prev_pos: V<T>,
 }
 
 impl<T> Mov<T>
 where
     T: Float,
 {
+    pub fn new(pos: V<T>, vel: V<T>, acc: V<T>) -> Self {
+        Self {
+            pos,
+            vel,
+            acc,
+            prev_pos: pos,
+        }
+    }
+
     pub fn update(&mut self, delta_t: f32) {
+        self.update_with(delta_t, V::zero(), T::zero());
+    }
+
+    /// update_with updates like update, with an external acceleration,
+    /// e.g. gravity, added to acc, and the velocity reduced by damping,
+    /// as a fraction per unit of time
+    pub fn update_with(&mut self, delta_t: f32, ext_acc: V<T>, damping: T) {
         let delta_t: T = T::from_f32(delta_t).unwrap();
-        self.vel = self.vel + self.acc * delta_t;
+        self.prev_pos = self.pos;
+        self.vel = self.vel + (self.acc + ext_acc) * delta_t;
+        if damping > T::zero() {
+            self.vel = self.vel * (T::one() / (T::one() + damping * delta_t));
+        }
         self.pos = self.pos + self.vel * delta_t;
     }
+
+    /// advance moves by the velocity alone, e.g. for kinematic bodies
+    pub fn advance(&mut self, delta_t: f32) {
+        let delta_t: T = T::from_f32(delta_t).unwrap();
+        self.prev_pos = self.pos;
+        self.pos = self.pos + self.vel * delta_t;
+    }
+
+    /// lerp_pos returns the position between the previous one, at alpha 0,
+    /// and the current one, at alpha 1; see Universe::alpha
+    pub fn lerp_pos(&self, alpha: f32) -> V<T> {
+        let alpha = T::from_f32(alpha).unwrap();
+        self.prev_pos + (self.pos - self.prev_pos) * alpha
+    }
 }
 
+/// BodyKind is how a body is moved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub enum BodyKind {
+    /// Never moves, e.g. level geometry
+    Static,
+    /// Moves only by its own velocity, set by the game, without
+    /// acceleration, gravity or damping, and pushes
+    /// dynamic bodies without being pushed back, e.g. moving platforms
+    Kinematic,
+    /// Moves by its velocity, acceleration and external forces,
+    /// and responds to collisions
+    #[default]
+    Dynamic,
+}
+
+#[derive(Clone, Encode, Decode)]
 pub enum Mass<T> {
     Infinite,
     Density(T),
     Fixed(T),
 }
 
+#[derive(Clone, Encode, Decode)]
+#[bincode(
+    encode_bounds = "T: Encode + 'static",
+    decode_bounds = "T: Decode + 'static",
+    borrow_decode_bounds = "T: bincode::BorrowDecode<'__de> + 'static"
+)]
 pub struct HitBox<T> {
     pub width: usize,
     pub height: usize,
+    pub shape: Shape<T>,
     pub mov: Mov<T>,
+    /// Mass of dynamic bodies; other kinds behave as if it were infinite
     pub mass: Mass<T>,
+    pub kind: BodyKind,
+    /// Sensors detect contacts, but don't collide
+    pub sensor: bool,
+    /// Bullets are swept from their previous position to the new one
+    /// on each tick, so that they don't pass through thin bodies
+    pub bullet: bool,
+    /// Bit flags of the collision layers this hitbox belongs to
+    pub category: u32,
+    /// Bit flags of the collision layers this hitbox collides with;
+    /// two hitboxes only interact if each one's mask matches
+    /// the other one's category
+    pub mask: u32,
+    /// Fraction of the velocity lost per unit of time, e.g. air drag
+    pub damping: T,
+    /// Coefficient of friction, overriding the Collider's;
+    /// between two bodies, the geometric mean of both is used
+    pub friction: Option<T>,
+    /// Coefficient of restitution, overriding the Collider's cor;
+    /// between two bodies, the bounciest one is used
+    pub restitution: Option<T>,
+    /// How the hitbox touched the TileMap of the physics, if any,
+    /// during the last tick
+    pub tile_contacts: TileContacts,
 }
 
 impl<T> HitBox<T>
 where
     T: Float,
 {
+    /// new returns a solid AABB hitbox, in the first collision
+    /// layer, and colliding with all layers
+    pub fn new(width: usize, height: usize, mov: Mov<T>, mass: Mass<T>) -> Self {
+        Self {
+            width,
+            height,
+            shape: Shape::Aabb,
+            mov,
+            mass,
+            kind: BodyKind::Dynamic,
+            sensor: false,
+            bullet: false,
+            category: 1,
+            mask: u32::MAX,
+            damping: T::zero(),
+            friction: None,
+            restitution: None,
+            tile_contacts: TileContacts::default(),
+        }
+    }
+
+    pub fn center(&self) -> V<T> {
+        let two = T::from_f32(2.0).unwrap();
+        self.mov.pos
+            + V::new(
+                T::from_usize(self.width).unwrap() / two,
+                T::from_usize(self.height).unwrap() / two,
+            )
+    }
+
+    pub fn interacts_with(&self, other: &Self) -> bool {
+        (self.category & other.mask) != 0 && (other.category & self.mask) != 0
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.kind == BodyKind::Dynamic
+    }
+
     pub fn bounds(&self) -> (T, T, T, T) {
         let x0 = self.mov.pos.x;
         let x1 = x0 + T::from_usize(self.width).unwrap();
@@ -49,6 +183,15 @@ where
         (x0, x1, y0, y1)
     }
 
+    pub fn solid(&self) -> Solid<T> {
+        Solid::new(
+            &self.shape,
+            self.mov.pos,
+            T::from_usize(self.width).unwrap(),
+            T::from_usize(self.height).unwrap(),
+        )
+    }
+
     pub fn dimension(&self, axis: Axis) -> usize {
         match axis {
             Axis::X => self.width,
@@ -56,12 +199,19 @@ where
         }
     }
 
+    /// mass returns None for infinite masses, and bodies which aren't dynamic
     pub fn mass(&self) -> Option<T> {
+        if self.kind != BodyKind::Dynamic {
+            return None;
+        }
         match self.mass {
             Mass::Infinite => None,
             Mass::Fixed(m) => Some(m),
             Mass::Density(m) => {
-                let area: T = T::from_usize(self.width * self.height).unwrap();
+                let area: T = self.shape.area(
+                    T::from_usize(self.width).unwrap(),
+                    T::from_usize(self.height).unwrap(),
+                );
                 Some(area * m)
             },
         }
@@ -114,20 +264,11 @@ where
     (v_a, v_b)
 }
 
-fn collide<O, T>(a: &mut O, b: &mut O, axis: Axis, cor: T)
+fn collide_1d<T>(m_a: Option<T>, u_a: T, m_b: Option<T>, u_b: T, cor: T) -> (T, T)
 where
-    O: Object<T>,
     T: Float,
 {
-    let hb_a = a.hitbox_mut();
-    let m_a = hb_a.mass();
-    let u_a = *hb_a.mov.vel.ax(axis);
-
-    let hb_b = b.hitbox_mut();
-    let m_b = hb_b.mass();
-    let u_b = *hb_b.mov.vel.ax(axis);
-
-    let (v_a, v_b) = match (m_a, m_b) {
+    match (m_a, m_b) {
         (Some(m_a), Some(m_b)) => collide_m_to_m(m_a, u_a, m_b, u_b, cor),
         (None, None) => collide_inf_to_inf(u_a, u_b, cor),
         (Some(_), None) => collide_m_to_inf(u_a, u_b, cor),
@@ -135,15 +276,112 @@ where
             let (v_b, v_a) = collide_m_to_inf(u_b, u_a, cor);
             (v_a, v_b)
         },
-    };
-    *a.hitbox_mut().mov.vel.ax_mut(axis) = v_a;
-    *b.hitbox_mut().mov.vel.ax_mut(axis) = v_b;
+    }
+}
+
+pub(super) fn inverse_mass<T>(m: Option<T>) -> T
+where
+    T: Float,
+{
+    match m {
+        Some(m) => T::one() / m,
+        None => T::zero(),
+    }
+}
+
+// Collision along the contact normal, which points from a to b;
+// the velocities along the normal collide like in one dimension.
+// Along the tangent, Coulomb friction opposes sliding with an impulse
+// of at most friction times the normal impulse.
+fn collide<O, T>(a: &mut O, b: &mut O, normal: V<T>, cor: T, friction: T)
+where
+    O: Object<T>,
+    T: Float,
+{
+    let m_a = a.hitbox().mass();
+    let u_a = a.hitbox().mov.vel.dot(&normal);
+    let m_b = b.hitbox().mass();
+    let u_b = b.hitbox().mov.vel.dot(&normal);
+    // Already moving apart
+    if u_a <= u_b {
+        return;
+    }
+    let (v_a, v_b) = collide_1d(m_a, u_a, m_b, u_b, cor);
+    let mut vel_a = a.hitbox().mov.vel + normal * (v_a - u_a);
+    let mut vel_b = b.hitbox().mov.vel + normal * (v_b - u_b);
+
+    let w_a = inverse_mass(m_a);
+    let w_b = inverse_mass(m_b);
+    if friction > T::zero() && w_a + w_b > T::zero() {
+        let j_n = match w_a > T::zero() {
+            true => (v_a - u_a).abs() / w_a,
+            false => (v_b - u_b).abs() / w_b,
+        };
+        let tangent = V::new(-normal.y, normal.x);
+        let u_t = (vel_a - vel_b).dot(&tangent);
+        // The impulse which would stop sliding, capped by friction
+        let max_j_t = friction * j_n;
+        let j_t = (u_t / (w_a + w_b)).max(-max_j_t).min(max_j_t);
+        vel_a = vel_a - tangent * (j_t * w_a);
+        vel_b = vel_b + tangent * (j_t * w_b);
+    }
+    a.hitbox_mut().mov.vel = vel_a;
+    b.hitbox_mut().mov.vel = vel_b;
 }
 
+// Static and kinematic bodies only push dynamic bodies of finite mass
+fn pushes<T>(a: &HitBox<T>, b: &HitBox<T>) -> bool
+where
+    T: Float,
+{
+    (a.is_dynamic() && b.is_dynamic()) || a.mass().is_some() || b.mass().is_some()
+}
+
+// Per-body coefficients override the global ones
+fn combine<T>(a: Option<T>, b: Option<T>, global: T, f: fn(T, T) -> T) -> T
+where
+    T: Float,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => f(a, b),
+        (Some(c), None) | (None, Some(c)) => c,
+        (None, None) => global,
+    }
+}
+
+// Split the movement needed to unclip a from b based on their mass
+fn split_unclip<T>(a_unclip: V<T>, m_a: Option<T>, m_b: Option<T>) -> (V<T>, V<T>)
+where
+    T: Float,
+{
+    match (m_a, m_b) {
+        (None, None) => {
+            let a_unclip = a_unclip * T::from_f32(0.5).unwrap();
+            let b_unclip = a_unclip * -T::one();
+            (a_unclip, b_unclip)
+        },
+        (Some(_), None) => (a_unclip, V::zero()),
+        (None, Some(_)) => (V::zero(), a_unclip * -T::one()),
+        (Some(ma), Some(mb)) => {
+            let ma_unclip = a_unclip * (mb / (ma + mb));
+            let mb_unclip = a_unclip * (ma / (ma + mb)) * -T::one();
+            (ma_unclip, mb_unclip)
+        },
+    }
+}
+
+// How many times a bullet can bounce during one tick
+const MAX_SWEEPS: usize = 4;
+
 pub struct Collider<T> {
     cor: T,
+    friction: T,
     last_colliding_x: HashSet<Pair<ObjectKey>>,
     last_colliding_y: HashSet<Pair<ObjectKey>>,
+    // Normals of the pairs in contact after the last collide,
+    // and the sweeps since
+    contacts: HashMap<Pair<ObjectKey>, V<T>>,
+    events: Option<Sink<Event>>,
 }
 
 impl<T> Collider<T>
@@ -153,162 +391,339 @@ where
     pub fn new(cor: T) -> Self {
         Self {
             cor,
+            friction: T::zero(),
             last_colliding_x: HashSet::new(),
             last_colliding_y: HashSet::new(),
+            contacts: HashMap::new(),
+            events: None,
         }
     }
 
-    fn unclip<O>(&self, a: &mut O, b: &mut O) -> Axis
+    /// set_friction sets the coefficient of friction of bodies
+    /// which don't have their own; it is 0 by default
+    pub fn set_friction(&mut self, friction: T) {
+        self.friction = friction;
+    }
+
+    /// Send CollisionEvents into events
+    pub fn attach_events(&mut self, events: &mut Events) {
+        self.events = Some(events.sink());
+    }
+
+    /// contacts returns the normals of the pairs of objects touching
+    /// after the last collide, or hit by bullets since, from the first
+    /// of the pair to the second
+    pub fn contacts(&self) -> &HashMap<Pair<ObjectKey>, V<T>> {
+        &self.contacts
+    }
+
+    fn send(&self, pair: &Pair<ObjectKey>, normal: &V<T>, kind: CollisionEventKind) {
+        let sink = match &self.events {
+            Some(sink) => sink,
+            None => return,
+        };
+        let (a, b) = pair.tuple();
+        sink.send(Event::Collision(CollisionEvent {
+            a: *a,
+            b: *b,
+            kind,
+            normal: V::new(normal.x.to_f32().unwrap(), normal.y.to_f32().unwrap()),
+        }));
+    }
+
+    // Events are sent in the order of their pairs, so that they are
+    // the same on each run
+    fn send_events(&self, contacts: &HashMap<Pair<ObjectKey>, V<T>>) {
+        if self.events.is_none() {
+            return;
+        }
+        for (pair, normal) in sorted_map(contacts) {
+            match self.contacts.contains_key(pair) {
+                true => self.send(pair, normal, CollisionEventKind::Stay),
+                false => self.send(pair, normal, CollisionEventKind::Begin),
+            }
+        }
+        for (pair, normal) in sorted_map(&self.contacts) {
+            if !contacts.contains_key(pair) {
+                self.send(pair, normal, CollisionEventKind::End);
+            }
+        }
+    }
+
+    // Find how a and b overlap. Boxes which already overlapped along
+    // one axis on the previous tick must have run into each other along
+    // the other axis, even if it isn't the one with the least overlap.
+    fn contact<O>(&self, a: &O, b: &O, pair: &Pair<ObjectKey>) -> Option<Contact<T>>
     where
         O: Object<T>,
     {
-        let (a_x0, a_x1, a_y0, a_y1) = a.hitbox().bounds();
-        let (b_x0, b_x1, b_y0, b_y1) = b.hitbox().bounds();
-
-        // Find the direction that requires the minimum amount of
-        // movement to unclip the objects
-        let potential_a_unclips = [
-            V::new(b_x1 - a_x0, T::zero()),
-            V::new(b_x0 - a_x1, T::zero()),
-            V::new(T::zero(), b_y1 - a_y0),
-            V::new(T::zero(), b_y0 - a_y1),
-        ];
-        let a_unclip = potential_a_unclips
-            .iter()
-            .min_by(|a, b| a.mag().partial_cmp(&b.mag()).unwrap())
-            .unwrap();
-
-        let unclip_axis = if a_unclip.x == T::zero() {
-            Axis::Y
-        } else {
-            Axis::X
-        };
+        let solid_a = a.hitbox().solid();
+        let solid_b = b.hitbox().solid();
+        let last_x_collided = self.last_colliding_x.contains(pair);
+        let last_y_collided = self.last_colliding_y.contains(pair);
+        match (last_x_collided, last_y_collided) {
+            (false, true) => solid_a.axis_contact(&solid_b, Axis::X),
+            (true, false) => solid_a.axis_contact(&solid_b, Axis::Y),
+            _ => None,
+        }
+        .or_else(|| solid_a.contact(&solid_b))
+    }
 
-        // Assign unclip movement to a and b based on their mass
-        let (a_unclip, b_unclip) = match (a.hitbox().mass(), b.hitbox().mass()) {
-            (None, None) => {
-                let a_unclip = *a_unclip * T::from_f32(0.5).unwrap();
-                let b_unclip = a_unclip * -T::one();
-                (a_unclip, b_unclip)
-            },
-            (Some(_), None) => (*a_unclip, V::zero()),
-            (None, Some(_)) => (V::zero(), *a_unclip * -T::one()),
-            (Some(ma), Some(mb)) => {
-                let ma_unclip = *a_unclip * (mb / (ma + mb));
-                let mb_unclip = *a_unclip * (ma / (ma + mb)) * -T::one();
-                (ma_unclip, mb_unclip)
-            },
-        };
+    // Move a and b apart along the minimum translation vector
+    fn unclip<O>(&self, a: &mut O, b: &mut O, contact: &Contact<T>)
+    where
+        O: Object<T>,
+    {
+        let (a_unclip, b_unclip) = split_unclip(
+            contact.normal * -contact.depth,
+            a.hitbox().mass(),
+            b.hitbox().mass(),
+        );
         let a_new_pos = a.hitbox().mov.pos + a_unclip;
         let b_new_pos = b.hitbox().mov.pos + b_unclip;
         a.hitbox_mut().mov.pos = a_new_pos;
         b.hitbox_mut().mov.pos = b_new_pos;
-
-        unclip_axis
     }
 
-    pub fn collide<O>(&mut self, objects: &mut [&mut O])
-    where
+    /// collide resolves the collisions between the candidate pairs;
+    /// sleeping bodies don't move, so those touching each other keep
+    /// their contacts without being candidates
+    pub fn collide<O>(
+        &mut self,
+        space: &mut Space<O>,
+        candidates: &HashSet<Pair<ObjectKey>>,
+        asleep: &HashSet<ObjectKey>,
+    ) where
         O: Object<T>,
         T: Float,
     {
-        let idx_by_id: HashMap<ObjectKey, usize> = objects
+        let resting = |pair: &&Pair<ObjectKey>| {
+            let (a, b) = pair.tuple();
+            asleep.contains(a) && asleep.contains(b)
+        };
+        let mut colliding_x: HashSet<_> = self
+            .last_colliding_x
             .iter()
-            .enumerate()
-            .map(|(idx, obj)| (obj.key(), idx))
+            .filter(resting)
+            .copied()
             .collect();
-
-        let scanlist_x = scan_axis(objects, Axis::X);
-        let colliding_x = find_axis_collisions(&scanlist_x);
-        if colliding_x.is_empty() {
-            return;
+        let mut colliding_y: HashSet<_> = self
+            .last_colliding_y
+            .iter()
+            .filter(resting)
+            .copied()
+            .collect();
+        let mut contacts: HashMap<_, _> = self
+            .contacts
+            .iter()
+            .filter(|(pair, _)| resting(pair))
+            .map(|(pair, normal)| (*pair, *normal))
+            .collect();
+        let mut colliding_xy = vec![];
+        // Resolved in a stable order, so that ticks are reproducible
+        let mut candidates: Vec<&Pair<ObjectKey>> = candidates.iter().collect();
+        candidates.sort();
+        for pair in candidates {
+            let (left, right) = pair.tuple();
+            let (left, right) = match (space.get(*left), space.get(*right)) {
+                (Some(left), Some(right)) => (left, right),
+                _ => continue,
+            };
+            let (ax0, ax1, ay0, ay1) = left.hitbox().bounds();
+            let (bx0, bx1, by0, by1) = right.hitbox().bounds();
+            let overlap_x = ax0 < bx1 && bx0 < ax1;
+            let overlap_y = ay0 < by1 && by0 < ay1;
+            if overlap_x {
+                colliding_x.insert(*pair);
+            }
+            if overlap_y {
+                colliding_y.insert(*pair);
+            }
+            if overlap_x && overlap_y {
+                colliding_xy.push(pair);
+            }
         }
-        let scanlist_y = scan_axis(objects, Axis::Y);
-        let colliding_y = find_axis_collisions(&scanlist_y);
-        let mut colliding_xy: HashSet<Pair<ObjectKey>> = HashSet::new();
-        for k in colliding_x.iter() {
-            if colliding_y.contains(k) {
-                colliding_xy.insert(*k);
+        for pair in colliding_xy {
+            let (left, right) = pair.tuple();
+            let (left, right) = space.get_pair_mut(*left, *right).expect("same object!?");
+            let hb_left = left.hitbox();
+            let hb_right = right.hitbox();
+            if !hb_left.interacts_with(hb_right) {
+                continue;
+            }
+            let is_pushing = pushes(hb_left, hb_right);
+            let is_sensor = hb_left.sensor || hb_right.sensor;
+            let cor = combine(hb_left.restitution, hb_right.restitution, self.cor, T::max);
+            let friction = combine(
+                hb_left.friction,
+                hb_right.friction,
+                self.friction,
+                |a, b| (a * b).sqrt(),
+            );
+            let contact = match self.contact(&*left, &*right, pair) {
+                Some(contact) => contact,
+                // Only the bounding boxes overlap
+                None => continue,
+            };
+            contacts.insert(*pair, contact.normal);
+            if is_sensor || !is_pushing {
+                continue;
             }
+            self.unclip(left, right, &contact);
+            collide(left, right, contact.normal, cor, friction);
         }
-        for pair in colliding_xy.iter() {
-            let pairt = pair.tuple();
-            let left_idx = idx_by_id.get(pairt.0).unwrap();
-            let right_idx = idx_by_id.get(pairt.1).unwrap();
-            assert_ne!(left_idx, right_idx, "same object!?");
-
-            let last_x_collided = self.last_colliding_x.contains(pair);
-            let last_y_collided = self.last_colliding_y.contains(pair);
-
-            let collide_axes = match (last_x_collided, last_y_collided) {
-                (false, true) => vec![Axis::X],
-                (true, false) => vec![Axis::Y],
-                (false, false) => vec![Axis::X, Axis::Y],
-                (true, true) => {
-                    let axis: Axis;
-                    unsafe {
-                        let obj1: *mut O = objects[*left_idx] as *mut O;
-                        let obj2: *mut O = objects[*right_idx] as *mut O;
-                        axis = self.unclip(&mut *obj1, &mut *obj2);
+        self.send_events(&contacts);
+        self.contacts = contacts;
+        self.last_colliding_x = colliding_x;
+        self.last_colliding_y = colliding_y;
+    }
+
+    /// sweep moves the bullet from its previous position to its current
+    /// one, stopping where it first touches one of the others, to resolve
+    /// that collision, and moving on with its new velocity. The bodies it
+    /// hits are added to the contacts right away, and reported as such.
+    pub fn sweep<O>(
+        &mut self,
+        space: &mut Space<O>,
+        bullet: ObjectKey,
+        others: &[ObjectKey],
+        delta_t: f32,
+    ) where
+        O: Object<T>,
+    {
+        let mov = match space.get(bullet) {
+            Some(obj) => &obj.hitbox().mov,
+            None => return,
+        };
+        let mut from = mov.prev_pos;
+        let mut motion = mov.pos - mov.prev_pos;
+        let mut time_left = T::from_f32(delta_t).unwrap();
+        for _ in 0..MAX_SWEEPS {
+            let hitbox = space.get(bullet).unwrap().hitbox();
+            let solid = Solid::new(
+                &hitbox.shape,
+                from,
+                T::from_usize(hitbox.width).unwrap(),
+                T::from_usize(hitbox.height).unwrap(),
+            );
+            let hit = others
+                .iter()
+                .filter(|other| **other != bullet)
+                .filter_map(|other| {
+                    let other_hitbox = space.get(*other)?.hitbox();
+                    if other_hitbox.sensor
+                        || !hitbox.interacts_with(other_hitbox)
+                        || !pushes(hitbox, other_hitbox)
+                    {
+                        return None;
                     }
-                    vec![axis]
+                    solid
+                        .time_of_impact(motion, &other_hitbox.solid())
+                        .map(|(t, normal)| (*other, t, normal))
+                })
+                .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+            let (other, t, normal) = match hit {
+                Some(hit) => hit,
+                None => {
+                    from = from + motion;
+                    break;
                 },
             };
-            unsafe {
-                let obj1: *mut O = objects[*left_idx] as *mut O;
-                let obj2: *mut O = objects[*right_idx] as *mut O;
-                for axis in collide_axes {
-                    collide(&mut *obj1, &mut *obj2, axis, self.cor);
-                }
+            from = from + motion * t;
+            let (a, b) = space.get_pair_mut(bullet, other).unwrap();
+            a.hitbox_mut().mov.pos = from;
+            let cor = combine(
+                a.hitbox().restitution,
+                b.hitbox().restitution,
+                self.cor,
+                T::max,
+            );
+            let friction = combine(
+                a.hitbox().friction,
+                b.hitbox().friction,
+                self.friction,
+                |a, b| (a * b).sqrt(),
+            );
+            collide(a, b, normal, cor, friction);
+            let pair = Pair::new(a.key(), b.key());
+            let pair_normal = match *pair.tuple().0 == a.key() {
+                true => normal,
+                false => normal * -T::one(),
+            };
+            if self.contacts.insert(pair, pair_normal).is_none() {
+                self.send(&pair, &pair_normal, CollisionEventKind::Begin);
             }
+            time_left = time_left * (T::one() - t);
+            motion = a.hitbox().mov.vel * time_left;
         }
-        self.last_colliding_x = colliding_x;
-        self.last_colliding_y = colliding_y;
+        space.get_mut(bullet).unwrap().hitbox_mut().mov.pos = from;
     }
 }
 
-fn scan_axis<O, T>(objects: &[&mut O], axis: Axis) -> Vec<(T, ObjectKey, bool)>
-where
-    T: Float,
-    O: Object<T>,
-{
-    let mut coords: Vec<(T, ObjectKey, bool)> = Vec::with_capacity(2 * objects.len());
-    for object in objects.iter() {
-        let hb = object.hitbox();
-        let c = *hb.mov.pos.ax(axis);
-        let w = T::from_usize(hb.dimension(axis)).unwrap();
-        coords.push((c, object.key(), true));
-        coords.push((c + w, object.key(), false));
-    }
-    coords.sort_by(|(c1, _, _), (c2, _, _)| c1.partial_cmp(c2).unwrap());
-    coords
+/// Dynamic bodies of finite mass moving slower than speed for steps
+/// ticks, along with all those they touch or are constrained to,
+/// fall asleep: they stop moving until something wakes them up.
+///
+/// Bodies resting on the floor bounce by about gravity times the step
+/// on each tick, and more in stacks, so speed should be a few times that.
+#[derive(Clone, Copy, Debug, PartialEq, Encode, Decode)]
+pub struct SleepThreshold<T> {
+    pub speed: T,
+    pub steps: usize,
 }
 
-fn find_axis_collisions<T>(scanlist: &[(T, ObjectKey, bool)]) -> HashSet<Pair<ObjectKey>>
-where
-    T: Float,
-{
-    if scanlist.is_empty() {
-        return HashSet::new();
-    }
-    let mut colliding: HashSet<Pair<ObjectKey>> = HashSet::new();
-    let mut intersecting: HashSet<ObjectKey> = HashSet::new();
-    for (_, id, is_in) in scanlist {
-        if *is_in {
-            for other_id in intersecting.iter() {
-                let p: Pair<ObjectKey> = Pair::new(*id, *other_id);
-                colliding.insert(p);
-            }
-            intersecting.insert(*id);
-        } else {
-            intersecting.remove(id);
+// A sleeping body, with its island of bodies which wake up together,
+// and the bodies it touched when it fell asleep
+#[derive(Encode, Decode)]
+struct Sleeper {
+    island: ObjectKey,
+    contacts: Vec<ObjectKey>,
+}
+
+// Union-find of bodies into islands
+#[derive(Default)]
+struct Islands {
+    parents: HashMap<ObjectKey, ObjectKey>,
+    awake: HashSet<ObjectKey>,
+}
+
+impl Islands {
+    fn find(&mut self, key: ObjectKey) -> ObjectKey {
+        let parent = *self.parents.get(&key).unwrap_or(&key);
+        if parent == key {
+            return key;
         }
+        let root = self.find(parent);
+        self.parents.insert(key, root);
+        root
+    }
+
+    fn union(&mut self, a: ObjectKey, b: ObjectKey) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            // The smallest key is the root, so islands are stable
+            self.parents.insert(a.max(b), a.min(b));
+        }
+    }
+
+    fn keep_awake(&mut self, key: ObjectKey) {
+        self.awake.insert(key);
     }
-    colliding
 }
 
 pub struct Box2DPhysics<T> {
     collider: Collider<T>,
+    gravity: V<T>,
+    fields: Vec<ForceField<T>>,
+    tilemap: Option<TileMap<T>>,
+    // Stepped in order of their keys, so that ticks are reproducible
+    controllers: BTreeMap<ObjectKey, CharacterController<T>>,
+    constraints: Constraints<T>,
+    sleep: Option<SleepThreshold<T>>,
+    // How many ticks each awake body has been still for
+    still: HashMap<ObjectKey, usize>,
+    asleep: HashSet<ObjectKey>,
+    sleepers: HashMap<ObjectKey, Sleeper>,
 }
 
 impl<T> Box2DPhysics<T>
@@ -318,8 +733,446 @@ where
     pub fn new(cor: T) -> Self {
         Self {
             collider: Collider::new(cor),
+            gravity: V::zero(),
+            fields: vec![],
+            tilemap: None,
+            controllers: BTreeMap::new(),
+            constraints: Constraints::new(),
+            sleep: None,
+            still: HashMap::new(),
+            asleep: HashSet::new(),
+            sleepers: HashMap::new(),
+        }
+    }
+
+    pub fn collider_mut(&mut self) -> &mut Collider<T> {
+        &mut self.collider
+    }
+
+    pub fn gravity(&self) -> V<T> {
+        self.gravity
+    }
+
+    /// set_gravity sets the acceleration of all bodies, except those
+    /// of infinite mass
+    pub fn set_gravity(&mut self, gravity: V<T>) {
+        self.gravity = gravity;
+        self.wake_all();
+    }
+
+    pub fn add_field(&mut self, field: ForceField<T>) {
+        self.fields.push(field);
+        self.wake_all();
+    }
+
+    pub fn fields(&self) -> &[ForceField<T>] {
+        &self.fields
+    }
+
+    pub fn fields_mut(&mut self) -> &mut Vec<ForceField<T>> {
+        self.wake_all();
+        &mut self.fields
+    }
+
+    /// set_tilemap sets static level geometry made of tiles, against
+    /// which hitboxes are moved axis by axis; see TileMap::sweep
+    pub fn set_tilemap(&mut self, tilemap: Option<TileMap<T>>) {
+        self.tilemap = tilemap;
+        self.wake_all();
+    }
+
+    pub fn tilemap(&self) -> Option<&TileMap<T>> {
+        self.tilemap.as_ref()
+    }
+
+    pub fn tilemap_mut(&mut self) -> Option<&mut TileMap<T>> {
+        self.wake_all();
+        self.tilemap.as_mut()
+    }
+
+    /// set_sleep_threshold lets still bodies fall asleep, so that they
+    /// aren't moved nor collided with each other until woken up; None,
+    /// the default, keeps all bodies awake
+    pub fn set_sleep_threshold(&mut self, sleep: Option<SleepThreshold<T>>) {
+        self.sleep = sleep;
+        if sleep.is_none() {
+            self.wake_all();
+        }
+    }
+
+    /// is_sleeping returns whether the body is asleep. Sleeping bodies
+    /// wake up when their velocity, acceleration or position is changed,
+    /// when a body they touch moves or is destroyed, or when gravity
+    /// or fields change.
+    pub fn is_sleeping(&self, key: ObjectKey) -> bool {
+        self.asleep.contains(&key)
+    }
+
+    /// wake wakes up the body, and all those of its island
+    pub fn wake(&mut self, key: ObjectKey) {
+        if let Some(sleeper) = self.sleepers.get(&key) {
+            let island = sleeper.island;
+            self.wake_islands(&HashSet::from([island]));
+        }
+    }
+
+    pub fn wake_all(&mut self) {
+        self.asleep.clear();
+        self.sleepers.clear();
+        self.still.clear();
+    }
+
+    fn wake_islands(&mut self, islands: &HashSet<ObjectKey>) {
+        if islands.is_empty() {
+            return;
+        }
+        self.sleepers.retain(|key, sleeper| {
+            let wakes = islands.contains(&sleeper.island);
+            if wakes {
+                self.asleep.remove(key);
+            }
+            !wakes
+        });
+    }
+
+    fn can_sleep(&self, key: ObjectKey, hitbox: &HitBox<T>) -> bool {
+        hitbox.is_dynamic() && hitbox.mass().is_some() && !self.controllers.contains_key(&key)
+    }
+
+    // Sleepers wake up when they are pushed, or a body they touched
+    // is destroyed or moves; nudges slower than the sleep speed are
+    // ignored
+    fn wake_disturbed<O>(&mut self, space: &mut Space<O>, delta_t: f32)
+    where
+        O: Object<T>,
+    {
+        let speed = match (self.sleep, self.sleepers.is_empty()) {
+            (Some(sleep), false) => sleep.speed,
+            _ => return,
+        };
+        let dt = T::from_f32(delta_t).unwrap();
+        let moved: HashSet<ObjectKey> = self
+            .sleepers
+            .values()
+            .flat_map(|sleeper| sleeper.contacts.iter())
+            .filter(|key| match space.get(**key) {
+                Some(obj) => obj.hitbox().mov.vel.mag() > speed,
+                None => true,
+            })
+            .copied()
+            .collect();
+        let mut disturbed = HashSet::new();
+        for (key, sleeper) in self.sleepers.iter() {
+            let mov = match space.get_mut(*key) {
+                Some(obj) => &mut obj.hitbox_mut().mov,
+                None => {
+                    disturbed.insert(sleeper.island);
+                    continue;
+                },
+            };
+            if mov.vel.mag() <= speed {
+                mov.vel = V::zero();
+            }
+            let nudged = (mov.pos - mov.prev_pos).mag() > speed * dt;
+            mov.prev_pos = mov.pos;
+            if mov.vel != V::zero()
+                || mov.acc != V::zero()
+                || nudged
+                || sleeper.contacts.iter().any(|key| moved.contains(key))
+            {
+                disturbed.insert(sleeper.island);
+            }
         }
+        self.wake_islands(&disturbed);
     }
+
+    // Bodies touching the still ones, or almost: resting bodies bounce
+    // by about the sleep speed
+    fn touching<O>(&self, space: &Space<O>, margin: T) -> HashMap<ObjectKey, Vec<ObjectKey>>
+    where
+        O: Object<T>,
+    {
+        let mut pairs: Vec<Pair<ObjectKey>> = space
+            .index()
+            .pairs_of(|key| self.still.contains_key(key))
+            .into_iter()
+            .collect();
+        pairs.sort();
+        let mut touching: HashMap<ObjectKey, Vec<ObjectKey>> = HashMap::new();
+        for pair in pairs {
+            let (a, b) = pair.tuple();
+            let (hb_a, hb_b) = match (space.get(*a), space.get(*b)) {
+                (Some(obj_a), Some(obj_b)) => (obj_a.hitbox(), obj_b.hitbox()),
+                _ => continue,
+            };
+            let (ax0, ax1, ay0, ay1) = hb_a.bounds();
+            let (bx0, bx1, by0, by1) = hb_b.bounds();
+            if ax0 <= bx1 + margin
+                && bx0 <= ax1 + margin
+                && ay0 <= by1 + margin
+                && by0 <= ay1 + margin
+                && hb_a.interacts_with(hb_b)
+            {
+                touching.entry(*a).or_default().push(*b);
+                touching.entry(*b).or_default().push(*a);
+            }
+        }
+        touching
+    }
+
+    // Islands of bodies which have all been still for long enough fall
+    // asleep; bodies are in the same island when they touch, or are
+    // constrained to each other
+    fn fall_asleep<O>(&mut self, space: &mut Space<O>, delta_t: f32)
+    where
+        O: Object<T>,
+    {
+        let (speed, steps) = match self.sleep {
+            Some(sleep) => (sleep.speed, sleep.steps),
+            None => return,
+        };
+        if self.still.values().all(|still| *still < steps) {
+            return;
+        }
+        let margin = speed * T::from_f32(delta_t).unwrap();
+        let mut touching = self.touching(space, margin);
+        let mut islands = Islands::default();
+        let links = touching
+            .iter()
+            .flat_map(|(a, others)| others.iter().map(|b| (*a, *b)))
+            .chain(
+                self.constraints
+                    .iter()
+                    .filter_map(|(_, c)| c.b.map(|b| (c.a, b))),
+            );
+        // Bodies which can sleep are tracked in still
+        for (a, b) in links {
+            let (a_still, b_still) = (self.still.contains_key(&a), self.still.contains_key(&b));
+            if a_still && b_still {
+                islands.union(a, b);
+            } else if a_still || b_still {
+                // Touching a moving body which can't sleep, e.g. the
+                // player, keeps bodies awake, but walls and floors don't
+                let (key, other) = if a_still { (a, b) } else { (b, a) };
+                let keeps_awake = !self.asleep.contains(&other)
+                    && space
+                        .get(other)
+                        .map(|obj| obj.hitbox().mov.vel.mag() > speed)
+                        .unwrap_or(false);
+                if keeps_awake {
+                    islands.keep_awake(key);
+                }
+            }
+        }
+        let mut restless = HashSet::new();
+        for (key, still) in self.still.iter() {
+            if *still < steps {
+                restless.insert(islands.find(*key));
+            }
+        }
+        for key in islands.awake.clone() {
+            restless.insert(islands.find(key));
+        }
+        let sleeping: Vec<ObjectKey> = self
+            .still
+            .keys()
+            .filter(|key| !restless.contains(&islands.find(**key)))
+            .copied()
+            .collect();
+        for key in sleeping {
+            self.still.remove(&key);
+            self.asleep.insert(key);
+            self.sleepers.insert(
+                key,
+                Sleeper {
+                    island: islands.find(key),
+                    contacts: touching.remove(&key).unwrap_or_default(),
+                },
+            );
+            if let Some(obj) = space.get_mut(key) {
+                let mov = &mut obj.hitbox_mut().mov;
+                mov.vel = V::zero();
+                mov.prev_pos = mov.pos;
+            }
+        }
+    }
+
+    /// add_controller makes a CharacterController move the body of key
+    /// on each tick; the body should be kinematic, so that it pushes
+    /// dynamic bodies, while the controller stops it against others
+    pub fn add_controller(&mut self, key: ObjectKey, controller: CharacterController<T>) {
+        self.controllers.insert(key, controller);
+    }
+
+    pub fn remove_controller(&mut self, key: ObjectKey) -> Option<CharacterController<T>> {
+        self.controllers.remove(&key)
+    }
+
+    pub fn controller(&self, key: ObjectKey) -> Option<&CharacterController<T>> {
+        self.controllers.get(&key)
+    }
+
+    pub fn controller_mut(&mut self, key: ObjectKey) -> Option<&mut CharacterController<T>> {
+        self.controllers.get_mut(&key)
+    }
+
+    pub fn constraints(&self) -> &Constraints<T> {
+        &self.constraints
+    }
+
+    /// constraints_mut gives access to the joints between bodies,
+    /// which are solved after collisions on each tick
+    pub fn constraints_mut(&mut self) -> &mut Constraints<T> {
+        &mut self.constraints
+    }
+
+    fn sweep_bullets<O>(&mut self, space: &mut Space<O>, delta_t: f32)
+    where
+        O: Object<T>,
+    {
+        let sweeps: Vec<(ObjectKey, Vec<ObjectKey>)> = space
+            .objects()
+            .filter(|obj| obj.hitbox().bullet && obj.hitbox().is_dynamic())
+            .filter(|obj| !self.asleep.contains(&obj.key()))
+            .filter(|obj| !self.controllers.contains_key(&obj.key()))
+            .map(|obj| {
+                let hitbox = obj.hitbox();
+                let (x0, x1, y0, y1) = hitbox.bounds();
+                let motion = hitbox.mov.prev_pos - hitbox.mov.pos;
+                let min = V::new(x0 + motion.x.min(T::zero()), y0 + motion.y.min(T::zero()));
+                let max = V::new(x1 + motion.x.max(T::zero()), y1 + motion.y.max(T::zero()));
+                (obj.key(), space.near_keys(min, max))
+            })
+            .collect();
+        for (bullet, near) in sweeps {
+            self.collider.sweep(space, bullet, &near, delta_t);
+        }
+    }
+
+    fn external_acceleration(&self, hitbox: &HitBox<T>) -> V<T> {
+        if hitbox.mass().is_none() {
+            return V::zero();
+        }
+        let center = hitbox.center();
+        self.fields
+            .iter()
+            .fold(self.gravity, |acc, field| acc + field.acceleration(center))
+    }
+
+    /// Send CollisionEvents and ConstraintBreakEvents into events;
+    /// see Events::collision_events and Events::constraint_break_events
+    pub fn attach_events(&mut self, events: &mut Events) {
+        self.collider.attach_events(events);
+        self.constraints.attach_events(events);
+    }
+}
+
+// Collider and Box2DPhysics are encoded without their event sinks,
+// and with their hash maps and sets sorted, so that equal states give
+// equal snapshots, e.g. to compare their checksums between peers
+impl<T> Encode for Collider<T>
+where
+    T: Float,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.cor.encode(encoder)?;
+        self.friction.encode(encoder)?;
+        sorted_set(&self.last_colliding_x).encode(encoder)?;
+        sorted_set(&self.last_colliding_y).encode(encoder)?;
+        sorted_map(&self.contacts).encode(encoder)
+    }
+}
+
+impl<T> Decode for Collider<T>
+where
+    T: Float,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cor: T::decode(decoder)?,
+            friction: T::decode(decoder)?,
+            last_colliding_x: HashSet::decode(decoder)?,
+            last_colliding_y: HashSet::decode(decoder)?,
+            contacts: HashMap::decode(decoder)?,
+            events: None,
+        })
+    }
+}
+
+impl<T> Restore for Collider<T>
+where
+    T: Float,
+{
+    fn restore(&mut self, saved: Self) {
+        let events = self.events.take();
+        *self = Self { events, ..saved };
+    }
+}
+
+/// Box2DPhysics is encoded without its tilemap either, which is level
+/// geometry rather than state; restore keeps the current one.
+impl<T> Encode for Box2DPhysics<T>
+where
+    T: Float,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.collider.encode(encoder)?;
+        self.gravity.encode(encoder)?;
+        self.fields.encode(encoder)?;
+        self.controllers.encode(encoder)?;
+        self.constraints.encode(encoder)?;
+        self.sleep.encode(encoder)?;
+        sorted_map(&self.still).encode(encoder)?;
+        sorted_set(&self.asleep).encode(encoder)?;
+        sorted_map(&self.sleepers).encode(encoder)
+    }
+}
+
+impl<T> Decode for Box2DPhysics<T>
+where
+    T: Float,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            collider: Collider::decode(decoder)?,
+            gravity: V::decode(decoder)?,
+            fields: Vec::decode(decoder)?,
+            tilemap: None,
+            controllers: BTreeMap::decode(decoder)?,
+            constraints: Constraints::decode(decoder)?,
+            sleep: Option::decode(decoder)?,
+            still: HashMap::decode(decoder)?,
+            asleep: HashSet::decode(decoder)?,
+            sleepers: HashMap::decode(decoder)?,
+        })
+    }
+}
+
+impl<T> Restore for Box2DPhysics<T>
+where
+    T: Float,
+{
+    fn restore(&mut self, saved: Self) {
+        let tilemap = self.tilemap.take();
+        let mut collider = std::mem::replace(&mut self.collider, Collider::new(T::zero()));
+        collider.restore(saved.collider);
+        let mut constraints = std::mem::take(&mut self.constraints);
+        constraints.restore(saved.constraints);
+        *self = Self {
+            collider,
+            constraints,
+            tilemap,
+            ..saved
+        };
+    }
+}
+
+fn sorted_set<K: Ord>(set: &HashSet<K>) -> BTreeSet<&K> {
+    set.iter().collect()
+}
+
+fn sorted_map<K: Ord, U>(map: &HashMap<K, U>) -> BTreeMap<&K, &U> {
+    map.iter().collect()
 }
 
 impl<T, O> Physics<O> for Box2DPhysics<T>
@@ -328,11 +1181,200 @@ where
     O: Object<T>,
 {
     fn tick(&mut self, space: &mut Space<O>, delta_t: f32) {
-        let mut objects: Vec<&mut O> = space.objects_mut().collect();
-        self.collider.collide::<O>(&mut objects);
-        for obj in objects {
-            obj.hitbox_mut().mov.update(delta_t);
+        // Objects may have been added or moved since the last tick
+        space.reindex();
+        let candidates = space.index().pairs_except(&self.asleep);
+        self.collider.collide(space, &candidates, &self.asleep);
+        self.constraints.solve(space, delta_t);
+        self.wake_disturbed(space, delta_t);
+        let sleep_speed = self.sleep.map(|sleep| sleep.speed);
+        for obj in space.objects_mut() {
+            let key = obj.key();
+            if self.controllers.contains_key(&key) {
+                continue;
+            }
+            if self.asleep.contains(&key) {
+                continue;
+            }
+            if let Some(speed) = sleep_speed {
+                if self.can_sleep(key, obj.hitbox()) {
+                    let mov = &obj.hitbox().mov;
+                    let still = self.still.entry(key).or_insert(0);
+                    *still = match mov.vel.mag() <= speed && mov.acc == V::zero() {
+                        true => *still + 1,
+                        false => 0,
+                    };
+                } else {
+                    self.still.remove(&key);
+                }
+            }
+            let ext_acc = self.external_acceleration(obj.hitbox());
+            let hitbox = obj.hitbox_mut();
+            if hitbox.kind == BodyKind::Static {
+                hitbox.mov.prev_pos = hitbox.mov.pos;
+                continue;
+            }
+            match hitbox.kind {
+                BodyKind::Kinematic => hitbox.mov.advance(delta_t),
+                _ => hitbox.mov.update_with(delta_t, ext_acc, hitbox.damping),
+            }
+            if let Some(tilemap) = &self.tilemap {
+                if tilemap.collides_with(hitbox) {
+                    hitbox.tile_contacts = tilemap.sweep(hitbox);
+                }
+            }
         }
+        space.reindex();
+        self.sweep_bullets(space, delta_t);
+        // Characters move after the platforms they stand on
+        self.controllers.retain(|key, _| space.contains(*key));
+        for (key, controller) in self.controllers.iter_mut() {
+            controller.step(*key, space, self.tilemap.as_ref(), delta_t);
+        }
+        self.still.retain(|key, _| space.contains(*key));
+        self.fall_asleep(space, delta_t);
+    }
+
+    fn index(&mut self, space: &mut Space<O>) {
+        space.reindex();
+    }
+}
+
+/// RayHit is where a ray cast through a Space hit an object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit<T> {
+    pub key: ObjectKey,
+    pub point: V<T>,
+    /// Normal of the surface of the object at point
+    pub normal: V<T>,
+    pub distance: T,
+}
+
+fn to_f64<T: Float>(v: V<T>) -> V<f64> {
+    V::new(v.x.to_f64().unwrap(), v.y.to_f64().unwrap())
+}
+
+/// Spatial queries, using the index of the space to only look at
+/// the objects near the query, and those added or moved since
+/// they were last indexed.
+impl<O> Space<O> {
+    /// reindex updates the spatial index with the current hitboxes
+    /// of the objects added or moved since they were last indexed
+    pub fn reindex<T>(&mut self)
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        self.index_with(|obj| {
+            let (x0, x1, y0, y1) = obj.hitbox().bounds();
+            (to_f64(V::new(x0, y0)), to_f64(V::new(x1, y1)))
+        });
+    }
+
+    fn near_keys<T>(&self, min: V<T>, max: V<T>) -> Vec<ObjectKey>
+    where
+        T: Float,
+        O: Identity,
+    {
+        let mut keys = self.index().query(to_f64(min), to_f64(max));
+        keys.extend(self.unindexed());
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    fn near<T>(&self, min: V<T>, max: V<T>) -> impl Iterator<Item = (ObjectKey, Solid<T>)> + '_
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        self.near_keys(min, max)
+            .into_iter()
+            .filter_map(|key| self.get(key))
+            .map(|obj| (obj.key(), obj.hitbox().solid()))
+    }
+
+    /// overlapping returns the objects overlapping the rectangle
+    /// from min to max
+    pub fn overlapping<T>(&self, min: V<T>, max: V<T>) -> Vec<ObjectKey>
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        let rect = Solid::Rect { min, max };
+        self.near(min, max)
+            .filter(|(_, solid)| rect.contact(solid).is_some())
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// at_point returns the objects containing point, e.g. for mouse picking
+    pub fn at_point<T>(&self, point: V<T>) -> Vec<ObjectKey>
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        self.near(point, point)
+            .filter(|(_, solid)| solid.contains(point))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// nearest returns the object closest to point, within max_distance,
+    /// and its distance
+    pub fn nearest<T>(&self, point: V<T>, max_distance: T) -> Option<(ObjectKey, T)>
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        let reach = V::new(max_distance, max_distance);
+        self.near(point - reach, point + reach)
+            .map(|(key, solid)| (key, solid.distance(point)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// raycast returns the first object hit by a ray from origin
+    /// towards dir, within max_distance
+    pub fn raycast<T>(&self, origin: V<T>, dir: V<T>, max_distance: T) -> Option<RayHit<T>>
+    where
+        T: Float,
+        O: Object<T>,
+    {
+        let dir = dir.norm();
+        let end = origin + dir * max_distance;
+        let min = V::new(origin.x.min(end.x), origin.y.min(end.y));
+        let max = V::new(origin.x.max(end.x), origin.y.max(end.y));
+        self.near(min, max)
+            .filter_map(|(key, solid)| {
+                solid
+                    .raycast(origin, dir, max_distance)
+                    .map(|(distance, normal)| RayHit {
+                        key,
+                        point: origin + dir * distance,
+                        normal,
+                        distance,
+                    })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+impl<T, O> Universe<Box2DPhysics<T>, O>
+where
+    T: Float,
+    O: Object<T>,
+{
+    pub fn set_gravity(&mut self, gravity: V<T>) {
+        self.physics_mut().set_gravity(gravity);
+    }
+
+    pub fn add_field(&mut self, field: ForceField<T>) {
+        self.physics_mut().add_field(field);
+    }
+
+    pub fn add_constraint(&mut self, constraint: Constraint<T>) -> ConstraintKey {
+        self.physics_mut().constraints_mut().add(constraint)
     }
 }
 
@@ -343,3 +1385,715 @@ where
 {
     Universe::new(Box2DPhysics::new(cor))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::testing::body;
+
+    type Body = crate::physics::testing::Body<f32>;
+
+    #[test]
+    fn test_circles_exchange_velocities() {
+        let mut universe = box2d_universe(1.0);
+        let space = universe.space_mut();
+        let a = space.add(body(
+            Shape::Circle,
+            V::new(0.0, 0.0),
+            V::new(1.0, 0.0),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        let b = space.add(body(
+            Shape::Circle,
+            V::new(9.0, 0.0),
+            V::new(-1.0, 0.0),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        universe.tick(0.0);
+        let space = universe.space();
+        assert_eq!(space.get(a).unwrap().hitbox.mov.vel, V::new(-1.0, 0.0));
+        assert_eq!(space.get(b).unwrap().hitbox.mov.vel, V::new(1.0, 0.0));
+        assert_eq!(space.get(a).unwrap().hitbox.mov.pos, V::new(-0.5, 0.0));
+    }
+
+    #[test]
+    fn test_circle_bounces_off_box_corner() {
+        let mut universe = box2d_universe(1.0);
+        let space = universe.space_mut();
+        // The ball hits the top left corner of the wall diagonally
+        let ball = space.add(body(
+            Shape::Circle,
+            V::new(-3.0, -3.0),
+            V::new(1.0, 1.0),
+            4,
+            Mass::Fixed(1.0),
+        ));
+        space.add(body(
+            Shape::Aabb,
+            V::new(0.0, 0.0),
+            V::zero(),
+            10,
+            Mass::Infinite,
+        ));
+        universe.tick(0.0);
+        let vel = universe.space().get(ball).unwrap().hitbox.mov.vel;
+        assert!((vel.x + 1.0).abs() < 1e-5 && (vel.y + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_ball_rolls_off_ramp() {
+        let mut universe = box2d_universe(0.0);
+        let space = universe.space_mut();
+        // Falling straight down onto a ramp rising towards the right
+        let ball = space.add(body(
+            Shape::Circle,
+            V::new(4.0, 3.0),
+            V::new(0.0, 1.0),
+            2,
+            Mass::Fixed(1.0),
+        ));
+        space.add(Body {
+            id: ObjectId::new(),
+            hitbox: HitBox {
+                shape: Shape::ramp(10.0, 10.0, true),
+                ..HitBox::new(
+                    10,
+                    10,
+                    Mov::new(V::zero(), V::zero(), V::zero()),
+                    Mass::Infinite,
+                )
+            },
+        });
+        universe.tick(0.0);
+        let vel = universe.space().get(ball).unwrap().hitbox.mov.vel;
+        // Only the velocity along the slope is left
+        assert!((vel.x + 0.5).abs() < 1e-5 && (vel.y - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sensor_events() {
+        use crate::event::Pump;
+
+        let (mut events, _) = Events::new();
+        let collisions = events.collision_events();
+        let mut physics = Box2DPhysics::new(1.0);
+        physics.attach_events(&mut events);
+        let mut universe = Universe::new(physics);
+        let space = universe.space_mut();
+        let player = space.add(body(
+            Shape::Aabb,
+            V::new(0.0, 0.0),
+            V::new(10.0, 0.0),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        let mut coin = body(
+            Shape::Circle,
+            V::new(15.0, 0.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        );
+        coin.hitbox.sensor = true;
+        let coin = space.add(coin);
+
+        let mut kinds = vec![];
+        for _ in 0..4 {
+            universe.tick(1.0);
+            events.pump();
+            while let Some(event) = collisions.recv() {
+                assert_eq!((event.a, event.b), (player, coin));
+                kinds.push(event.kind);
+            }
+        }
+        // Ticks collide before moving: the coin is touched from
+        // the second tick to the third
+        assert_eq!(
+            kinds,
+            vec![
+                CollisionEventKind::Begin,
+                CollisionEventKind::Stay,
+                CollisionEventKind::End,
+            ]
+        );
+        let player = universe.space().get(player).unwrap();
+        assert_eq!(player.hitbox.mov.vel, V::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn test_events_in_pair_order() {
+        use crate::event::Pump;
+
+        let (mut events, _) = Events::new();
+        let collisions = events.collision_events();
+        let mut physics = Box2DPhysics::new(0.0);
+        physics.attach_events(&mut events);
+        let mut universe = Universe::new(physics);
+        let space = universe.space_mut();
+        floor(space);
+        for i in 0..8 {
+            space.add(body(
+                Shape::Aabb,
+                V::new(20.0 * i as f32 - 80.0, 1.0),
+                V::zero(),
+                10,
+                Mass::Fixed(1.0),
+            ));
+        }
+        universe.tick(1.0);
+        events.pump();
+        let mut pairs = vec![];
+        while let Some(event) = collisions.recv() {
+            pairs.push(Pair::new(event.a, event.b));
+        }
+        assert_eq!(pairs.len(), 8);
+        assert!(pairs.is_sorted());
+    }
+
+    #[test]
+    fn test_collision_masks() {
+        const BULLETS: u32 = 2;
+        let mut universe = box2d_universe(1.0);
+        let space = universe.space_mut();
+        let mut bullets = vec![];
+        for (x, vx) in [(0.0, 1.0), (5.0, -1.0)] {
+            let mut bullet = body(
+                Shape::Circle,
+                V::new(x, 0.0),
+                V::new(vx, 0.0),
+                10,
+                Mass::Fixed(1.0),
+            );
+            bullet.hitbox.category = BULLETS;
+            bullet.hitbox.mask = !BULLETS;
+            bullets.push(space.add(bullet));
+        }
+        universe.tick(0.0);
+        let vel = universe.space().get(bullets[0]).unwrap().hitbox.mov.vel;
+        assert_eq!(vel, V::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn test_friction_with_gravity() {
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.1));
+        let space = universe.space_mut();
+        space.add(body(
+            Shape::Aabb,
+            V::new(-100.0, 10.0),
+            V::zero(),
+            200,
+            Mass::Infinite,
+        ));
+        let mut rough = body(
+            Shape::Aabb,
+            V::new(-80.0, 0.0),
+            V::new(1.0, 0.0),
+            10,
+            Mass::Fixed(1.0),
+        );
+        rough.hitbox.friction = Some(0.5);
+        let rough = space.add(rough);
+        let mut icy = body(
+            Shape::Aabb,
+            V::new(20.0, 0.0),
+            V::new(1.0, 0.0),
+            10,
+            Mass::Fixed(2.0),
+        );
+        icy.hitbox.friction = Some(0.0);
+        let icy = space.add(icy);
+        for _ in 0..40 {
+            universe.tick(1.0);
+        }
+        let space = universe.space();
+        let rough = &space.get(rough).unwrap().hitbox.mov;
+        assert!(rough.vel.x.abs() < 1e-5);
+        assert!((rough.pos.y - 0.0).abs() < 0.2);
+        let icy = &space.get(icy).unwrap().hitbox.mov;
+        assert_eq!(icy.vel.x, 1.0);
+    }
+
+    fn floor(space: &mut Space<Body>) {
+        space.add(body(
+            Shape::Aabb,
+            V::new(-100.0, 10.0),
+            V::zero(),
+            200,
+            Mass::Infinite,
+        ));
+    }
+
+    #[test]
+    fn test_resting_contact() {
+        let mut universe = box2d_universe(0.5);
+        universe.set_gravity(V::new(0.0, 0.1));
+        let space = universe.space_mut();
+        floor(space);
+        let crate_ = space.add(body(
+            Shape::Aabb,
+            V::new(0.0, -20.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        for _ in 0..200 {
+            universe.tick(1.0);
+        }
+        // At rest, only the gravity of the last tick is left
+        let mov = &universe.space().get(crate_).unwrap().hitbox.mov;
+        assert!((mov.pos.y - 0.0).abs() < 0.1, "{:?}", mov.pos);
+        assert!(mov.vel.y.abs() < 0.1, "{:?}", mov.vel);
+        assert_eq!(mov.pos.x, 0.0);
+    }
+
+    #[test]
+    fn test_stacking() {
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.1));
+        let space = universe.space_mut();
+        floor(space);
+        let stack: Vec<ObjectKey> = (0..3)
+            .map(|i| {
+                space.add(body(
+                    Shape::Aabb,
+                    V::new(0.0, -10.0 * i as f32),
+                    V::zero(),
+                    10,
+                    Mass::Fixed(1.0),
+                ))
+            })
+            .collect();
+        for _ in 0..1000 {
+            universe.tick(1.0);
+            let mut below_y = 10.0;
+            for key in stack.iter() {
+                let mov = &universe.space().get(*key).unwrap().hitbox.mov;
+                // Contacts are resolved pair by pair, so stacked crates
+                // sink into each other, but less than halfway
+                assert!(mov.pos.y + 10.0 < below_y + 5.0, "{:?}", mov.pos);
+                assert_eq!(mov.pos.x, 0.0);
+                below_y = mov.pos.y;
+            }
+        }
+        // Pinned, so that changes to the order of resolution show up
+        let ys: Vec<f32> = stack
+            .iter()
+            .map(|key| universe.space().get(*key).unwrap().hitbox.mov.pos.y)
+            .collect();
+        let pinned = [1.6929109, -8.307089, -16.71418];
+        for (y, pinned) in ys.iter().zip(pinned) {
+            assert!((y - pinned).abs() < 1e-3, "{:?}", ys);
+        }
+    }
+
+    #[test]
+    fn test_pile_up() {
+        let mut universe = box2d_universe(0.2);
+        universe.set_gravity(V::new(0.0, 0.1));
+        let space = universe.space_mut();
+        floor(space);
+        // A pit 40 wide, with crates falling in at odd offsets
+        for x in [-30.0, 20.0] {
+            let mut wall = body(
+                Shape::Aabb,
+                V::new(x, -200.0),
+                V::zero(),
+                10,
+                Mass::Infinite,
+            );
+            wall.hitbox.height = 210;
+            space.add(wall);
+        }
+        let crates: Vec<ObjectKey> = (0..8)
+            .map(|i| {
+                space.add(body(
+                    Shape::Aabb,
+                    V::new(-20.0 + (i * 7 % 30) as f32, -15.0 * i as f32 - 20.0),
+                    V::zero(),
+                    10,
+                    Mass::Fixed(1.0 + (i % 3) as f32),
+                ))
+            })
+            .collect();
+        for _ in 0..1000 {
+            universe.tick(1.0);
+        }
+        let space = universe.space();
+        let bounds: Vec<_> = crates
+            .iter()
+            .map(|key| space.get(*key).unwrap().hitbox.bounds())
+            .collect();
+        for (i, (x0, x1, y0, y1)) in bounds.iter().enumerate() {
+            // Still in the pit, on top of the floor
+            assert!(*x0 >= -20.0 && *x1 <= 20.0, "{:?}", bounds);
+            assert!(*y1 < 12.5, "{:?}", bounds);
+            // No crate went halfway through another
+            for (x0b, x1b, y0b, y1b) in bounds[i + 1..].iter() {
+                let overlap_x = x1.min(*x1b) - x0.max(*x0b);
+                let overlap_y = y1.min(*y1b) - y0.max(*y0b);
+                assert!(overlap_x < 5.0 || overlap_y < 5.0, "{:?}", bounds);
+            }
+        }
+        // Pinned, like in test_stacking
+        let pinned = [
+            V::new(-20.0, 0.024040885),
+            V::new(-10.000001, 0.012365229),
+            V::new(0.0, 0.06707663),
+            V::new(9.999999, 2.2999997),
+            V::new(9.999999, -7.8000007),
+            V::new(-15.0, -9.049383),
+            V::new(-0.7730875, -6.676138),
+            V::new(-1.0, -16.776138),
+        ];
+        for (key, pinned) in crates.iter().zip(pinned) {
+            let pos = space.get(*key).unwrap().hitbox.mov.pos;
+            assert!((pos - pinned).mag() < 1e-3, "{:?}", bounds);
+        }
+    }
+
+    #[test]
+    fn test_sleeping() {
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.01));
+        universe
+            .physics_mut()
+            .set_sleep_threshold(Some(SleepThreshold {
+                speed: 0.05,
+                steps: 10,
+            }));
+        let space = universe.space_mut();
+        let floor = space.add(body(
+            Shape::Aabb,
+            V::new(-100.0, 10.0),
+            V::zero(),
+            200,
+            Mass::Infinite,
+        ));
+        let bottom = space.add(body(
+            Shape::Aabb,
+            V::new(0.0, -10.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        for _ in 0..200 {
+            universe.tick(1.0);
+        }
+        assert!(universe.physics().is_sleeping(bottom));
+        let pos = universe.space().get(bottom).unwrap().hitbox.mov.pos;
+        assert!(pos.y.abs() < 0.2, "{:?}", pos);
+
+        // A crate falling on top wakes it up, then both fall asleep
+        let top = universe.space_mut().add(body(
+            Shape::Aabb,
+            V::new(0.0, -40.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        let mut woke = false;
+        for _ in 0..300 {
+            universe.tick(1.0);
+            woke |= !universe.physics().is_sleeping(bottom);
+        }
+        assert!(woke);
+        assert!(universe.physics().is_sleeping(bottom));
+        assert!(universe.physics().is_sleeping(top));
+        let pos = universe.space().get(top).unwrap().hitbox.mov.pos;
+        universe.tick(1.0);
+        assert_eq!(universe.space().get(top).unwrap().hitbox.mov.pos, pos);
+
+        // Pulling the floor from under them wakes both up
+        universe.space_mut().destroy(floor);
+        universe.tick(1.0);
+        assert!(!universe.physics().is_sleeping(bottom));
+        assert!(!universe.physics().is_sleeping(top));
+        for _ in 0..10 {
+            universe.tick(1.0);
+        }
+        assert!(universe.space().get(top).unwrap().hitbox.mov.pos.y > pos.y + 0.5);
+    }
+
+    #[test]
+    fn test_wake_on_force() {
+        let mut universe = box2d_universe(0.0);
+        universe
+            .physics_mut()
+            .set_sleep_threshold(Some(SleepThreshold {
+                speed: 0.001,
+                steps: 10,
+            }));
+        let crate_ = universe.space_mut().add(body(
+            Shape::Aabb,
+            V::zero(),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        for _ in 0..20 {
+            universe.tick(1.0);
+        }
+        assert!(universe.physics().is_sleeping(crate_));
+        let space = universe.space_mut();
+        space.get_mut(crate_).unwrap().hitbox.mov.acc = V::new(0.01, 0.0);
+        universe.tick(1.0);
+        assert!(!universe.physics().is_sleeping(crate_));
+        assert!(universe.space().get(crate_).unwrap().hitbox.mov.pos.x > 0.0);
+        // So does gravity
+        universe.space_mut().get_mut(crate_).unwrap().hitbox.mov.acc = V::zero();
+        universe.space_mut().get_mut(crate_).unwrap().hitbox.mov.vel = V::zero();
+        for _ in 0..20 {
+            universe.tick(1.0);
+        }
+        assert!(universe.physics().is_sleeping(crate_));
+        universe.set_gravity(V::new(0.0, 0.01));
+        assert!(!universe.physics().is_sleeping(crate_));
+    }
+
+    #[test]
+    fn test_restitution_override() {
+        let mut universe = box2d_universe(0.0);
+        let space = universe.space_mut();
+        let mut ball = body(
+            Shape::Circle,
+            V::new(0.0, -9.5),
+            V::new(0.0, 1.0),
+            10,
+            Mass::Fixed(1.0),
+        );
+        ball.hitbox.restitution = Some(1.0);
+        let ball = space.add(ball);
+        space.add(body(
+            Shape::Aabb,
+            V::new(-50.0, 0.0),
+            V::zero(),
+            100,
+            Mass::Infinite,
+        ));
+        universe.tick(0.0);
+        let vel = universe.space().get(ball).unwrap().hitbox.mov.vel;
+        assert_eq!(vel, V::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn test_damping() {
+        let mut mov = Mov::new(V::zero(), V::new(2.0, 0.0), V::zero());
+        mov.update_with(1.0, V::new(0.0, 1.0), 1.0);
+        assert_eq!(mov.vel, V::new(1.0, 0.5));
+    }
+
+    #[test]
+    fn test_kinematic_keeps_velocity() {
+        let mut universe = box2d_universe(1.0);
+        universe.set_gravity(V::new(0.0, 0.01));
+        let mut platform = body(Shape::Aabb, V::zero(), V::new(0.1, 0.0), 10, Mass::Infinite);
+        platform.hitbox.kind = BodyKind::Kinematic;
+        platform.hitbox.mov.acc = V::new(1.0, 1.0);
+        platform.hitbox.damping = 0.5;
+        let platform = universe.space_mut().add(platform);
+        universe.tick(10.0);
+        let mov = &universe.space().get(platform).unwrap().hitbox.mov;
+        assert_eq!(mov.vel, V::new(0.1, 0.0));
+        assert_eq!(mov.pos, V::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn test_bullet_does_not_tunnel() {
+        use crate::event::Pump;
+
+        let (mut events, _) = Events::new();
+        let collisions = events.collision_events();
+        let mut physics = Box2DPhysics::new(1.0);
+        physics.attach_events(&mut events);
+        let mut universe = Universe::new(physics);
+        let space = universe.space_mut();
+        let mut wall = body(
+            Shape::Aabb,
+            V::new(50.0, -50.0),
+            V::zero(),
+            2,
+            Mass::Infinite,
+        );
+        wall.hitbox.height = 100;
+        let wall = space.add(wall);
+        let mut bullet = body(
+            Shape::Aabb,
+            V::new(0.0, 0.0),
+            V::new(100.0, 0.0),
+            4,
+            Mass::Fixed(1.0),
+        );
+        bullet.hitbox.bullet = true;
+        let bullet = space.add(bullet);
+        let mut ghost = body(
+            Shape::Aabb,
+            V::new(0.0, 10.0),
+            V::new(100.0, 0.0),
+            4,
+            Mass::Fixed(1.0),
+        );
+        ghost.hitbox.category = 2;
+        ghost.hitbox.mask = 2;
+        ghost.hitbox.bullet = true;
+        let ghost = space.add(ghost);
+        universe.tick(1.0);
+        let space = universe.space();
+        // The bullet bounces off the wall halfway through the tick
+        let mov = &space.get(bullet).unwrap().hitbox.mov;
+        assert_eq!(mov.vel, V::new(-100.0, 0.0));
+        assert!((mov.pos.x - (-8.0)).abs() < 1e-3, "{:?}", mov.pos);
+        // Bodies which don't interact still go through
+        let mov = &space.get(ghost).unwrap().hitbox.mov;
+        assert_eq!(mov.pos, V::new(100.0, 10.0));
+
+        // The hit is reported during the same tick
+        let pair = Pair::new(bullet, wall);
+        assert!(universe.physics().collider.contacts().contains_key(&pair));
+        events.pump();
+        let event = collisions.recv().unwrap();
+        assert_eq!(Pair::new(event.a, event.b), pair);
+        assert_eq!(event.kind, CollisionEventKind::Begin);
+        assert!(collisions.recv().is_none());
+        universe.tick(1.0);
+        events.pump();
+        assert_eq!(collisions.recv().unwrap().kind, CollisionEventKind::End);
+    }
+
+    #[test]
+    fn test_fast_body_lands_on_tiles() {
+        let mut tiles = crate::grid::Grid::new(10, 10);
+        tiles.nowrap();
+        for x in 0..10 {
+            *tiles.get_mut(V::new(x, 8)) = Tile::SOLID;
+        }
+        let mut universe = box2d_universe(1.0);
+        universe
+            .physics_mut()
+            .set_tilemap(Some(TileMap::new(tiles, 10.0, V::zero())));
+        let falling = universe.space_mut().add(body(
+            Shape::Aabb,
+            V::new(40.0, 30.0),
+            V::new(0.0, 0.5),
+            6,
+            Mass::Fixed(1.0),
+        ));
+        // Past the whole row of tiles in one tick
+        universe.tick(100.0);
+        let hitbox = &universe.space().get(falling).unwrap().hitbox;
+        assert_eq!(hitbox.mov.pos, V::new(40.0, 74.0));
+        assert_eq!(hitbox.mov.vel, V::zero());
+        assert!(hitbox.tile_contacts.on_ground);
+    }
+
+    #[test]
+    fn test_space_queries() {
+        let mut universe = box2d_universe(1.0);
+        let space = universe.space_mut();
+        let ball = space.add(body(
+            Shape::Circle,
+            V::new(0.0, 0.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        let crate_ = space.add(body(
+            Shape::Aabb,
+            V::new(100.0, 0.0),
+            V::zero(),
+            10,
+            Mass::Fixed(1.0),
+        ));
+        let floor = space.add(Body {
+            id: ObjectId::new(),
+            hitbox: HitBox::new(
+                10_000,
+                10,
+                Mov::new(V::new(-5000.0, 20.0), V::zero(), V::zero()),
+                Mass::Infinite,
+            ),
+        });
+        // Found before they are indexed by the first tick
+        assert_eq!(space.at_point(V::new(5.0, 5.0)), vec![ball]);
+        universe.tick(0.0);
+        // And after being moved
+        let space = universe.space_mut();
+        space.get_mut(crate_).unwrap().hitbox.mov.pos = V::new(100.0, -200.0);
+        assert!(space.at_point(V::new(105.0, 5.0)).is_empty());
+        assert_eq!(space.at_point(V::new(105.0, -195.0)), vec![crate_]);
+        space.get_mut(crate_).unwrap().hitbox.mov.pos = V::new(100.0, 0.0);
+        universe.tick(0.0);
+        let space = universe.space();
+
+        assert_eq!(space.at_point(V::new(5.0, 5.0)), vec![ball]);
+        // In the bounding box of the ball, but not in the ball
+        assert!(space.at_point(V::new(0.5, 0.5)).is_empty());
+        let mut found = space.overlapping(V::new(-10.0, -10.0), V::new(200.0, 25.0));
+        found.sort();
+        let mut expected = vec![ball, crate_, floor];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(space.nearest(V::new(95.0, 5.0), 50.0), Some((crate_, 5.0)));
+        assert_eq!(space.nearest(V::new(-80.0, -50.0), 50.0), None);
+
+        let hit = space
+            .raycast(V::new(-50.0, 5.0), V::new(1.0, 0.0), 1000.0)
+            .unwrap();
+        assert_eq!(hit.key, ball);
+        assert_eq!(hit.point, V::new(0.0, 5.0));
+        assert_eq!(hit.normal, V::new(-1.0, 0.0));
+        let hit = space
+            .raycast(V::new(50.0, -50.0), V::new(0.0, 1.0), 1000.0)
+            .unwrap();
+        assert_eq!((hit.key, hit.distance), (floor, 70.0));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut universe = box2d_universe(0.5);
+        universe.set_gravity(V::new(0.0, 0.002));
+        universe.set_fixed_step(10.0, 4);
+        let space = universe.space_mut();
+        floor(space);
+        let crates: Vec<ObjectKey> = (0..4)
+            .map(|i| {
+                space.add(body(
+                    Shape::Aabb,
+                    V::new(12.0 * i as f32, -30.0 - 15.0 * i as f32),
+                    V::new(0.02 - 0.01 * i as f32, 0.0),
+                    10,
+                    Mass::Fixed(1.0),
+                ))
+            })
+            .collect();
+        let run = |universe: &mut Universe<Box2DPhysics<f32>, Body>| {
+            for _ in 0..40 {
+                universe.tick(16.0);
+            }
+            crates
+                .iter()
+                .map(|key| universe.space().get(*key).unwrap().hitbox.mov.pos)
+                .collect::<Vec<_>>()
+        };
+        run(&mut universe);
+        let snapshot = universe.snapshot().unwrap();
+        let later = run(&mut universe);
+        let later_snapshot = universe.snapshot().unwrap();
+
+        universe.restore(&snapshot).unwrap();
+        assert!(crates
+            .iter()
+            .all(|key| universe.space().index().contains(*key)));
+        assert!(universe.space().unindexed().is_empty());
+        assert_eq!(universe.snapshot().unwrap(), snapshot);
+        assert_eq!(run(&mut universe), later);
+        assert_eq!(universe.snapshot().unwrap(), later_snapshot);
+
+        // As if loading a save game
+        let mut loaded = box2d_universe(1.0);
+        loaded.restore(&snapshot).unwrap();
+        assert_eq!(run(&mut loaded), later);
+    }
+}
@@ -0,0 +1,444 @@
+use bincode::{Decode, Encode};
+
+use super::box2d::Object;
+use super::identity::ObjectKey;
+use super::shape::{Shape, Solid};
+use super::tilemap::{TileContacts, TileMap};
+use super::universe::Space;
+use crate::num::Float;
+use crate::vector::v2::V2 as V;
+
+// How many times a move is corrected against the bodies it runs into
+const MAX_SLIDES: usize = 4;
+
+// Contact normals closer to vertical than this are floors or ceilings
+const FLOOR_NORMAL: f32 = 0.7;
+
+/// CharacterController moves a body like the player of a platformer:
+/// it walks at a fixed speed, jumps, falls, slides along walls,
+/// walks up small ledges and is carried by the platforms it stands on.
+///
+/// The controller stops the body against static and kinematic bodies,
+/// dynamic bodies of infinite mass and tiles; dynamic bodies of
+/// finite mass are pushed out of the way instead, by the physics.
+/// See Box2DPhysics::add_controller.
+#[derive(Encode, Decode)]
+pub struct CharacterController<T> {
+    /// Horizontal speed when walking
+    pub speed: T,
+    /// Vertical speed at the start of a jump
+    pub jump_speed: T,
+    pub gravity: T,
+    pub max_fall_speed: T,
+    /// For how long after walking off a ledge jumping still works
+    pub coyote_time: T,
+    /// Height of the ledges walked up without jumping
+    pub step_height: T,
+    walk: T,
+    jump: bool,
+    vel: V<T>,
+    grounded: bool,
+    ground: Option<ObjectKey>,
+    airborne_time: T,
+    jumped: bool,
+    contacts: TileContacts,
+}
+
+impl<T> CharacterController<T>
+where
+    T: Float,
+{
+    pub fn new(speed: T, jump_speed: T, gravity: T) -> Self {
+        Self {
+            speed,
+            jump_speed,
+            gravity,
+            max_fall_speed: T::infinity(),
+            coyote_time: T::zero(),
+            step_height: T::zero(),
+            walk: T::zero(),
+            jump: false,
+            vel: V::zero(),
+            grounded: false,
+            ground: None,
+            airborne_time: T::zero(),
+            jumped: false,
+            contacts: TileContacts::default(),
+        }
+    }
+
+    /// walk sets the walking direction, from -1 (left) to 1 (right)
+    pub fn walk(&mut self, direction: T) {
+        self.walk = direction.max(-T::one()).min(T::one());
+    }
+
+    /// jump makes the character jump on the next step, if it's on the
+    /// ground or just walked off it
+    pub fn jump(&mut self) {
+        self.jump = true;
+    }
+
+    pub fn velocity(&self) -> V<T> {
+        self.vel
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// ground returns the body the character stands on, if it isn't a tile
+    pub fn ground(&self) -> Option<ObjectKey> {
+        self.ground
+    }
+
+    /// contacts returns how the character touched things during the last
+    /// step, including bodies and not just tiles
+    pub fn contacts(&self) -> TileContacts {
+        self.contacts
+    }
+
+    /// step moves the body of key in space for delta_t
+    pub fn step<O>(
+        &mut self,
+        key: ObjectKey,
+        space: &mut Space<O>,
+        tilemap: Option<&TileMap<T>>,
+        delta_t: f32,
+    ) where
+        O: Object<T>,
+    {
+        let dt = T::from_f32(delta_t).unwrap();
+        let carry = match self.ground.and_then(|ground| space.get(ground)) {
+            Some(ground) if self.grounded => ground.hitbox().mov.pos - ground.hitbox().mov.prev_pos,
+            _ => V::zero(),
+        };
+
+        self.vel.x = self.walk * self.speed;
+        if self.grounded {
+            self.airborne_time = T::zero();
+            self.jumped = false;
+        } else {
+            self.airborne_time = self.airborne_time + dt;
+        }
+        if self.jump && !self.jumped && self.airborne_time <= self.coyote_time {
+            self.vel.y = -self.jump_speed;
+            self.jumped = true;
+        }
+        self.jump = false;
+        self.vel.y = (self.vel.y + self.gravity * dt).min(self.max_fall_speed);
+        let motion = self.vel * dt + carry;
+
+        let (start, mover) = match space.get(key) {
+            Some(body) => (
+                body.hitbox().mov.pos,
+                Mover::new(body, space, tilemap, motion, self.step_height),
+            ),
+            None => return,
+        };
+        let mut slide = mover.slide(start, motion);
+        if self.grounded && self.step_height > T::zero() && slide.contacts.hit_wall {
+            let up = V::new(T::zero(), -self.step_height);
+            if mover.fits(start + up) {
+                let raised = mover.slide(start + up, V::new(motion.x, T::zero()));
+                let drop = self.step_height + motion.y.max(T::zero());
+                if let Some(pos) = mover.drop(raised.pos, drop) {
+                    if (pos.x - start.x).abs() > (slide.pos.x - start.x).abs() {
+                        slide = Slide {
+                            pos,
+                            ground: None,
+                            contacts: TileContacts {
+                                on_ground: true,
+                                ..raised.contacts
+                            },
+                        };
+                    }
+                }
+            }
+        }
+
+        if slide.contacts.on_ground && self.vel.y > T::zero() {
+            self.vel.y = T::zero();
+        }
+        if slide.contacts.hit_ceiling && self.vel.y < T::zero() {
+            self.vel.y = T::zero();
+        }
+        self.grounded = slide.contacts.on_ground;
+        self.ground = slide.ground;
+        self.contacts = slide.contacts;
+        if let Some(body) = space.get_mut(key) {
+            let mov = &mut body.hitbox_mut().mov;
+            mov.prev_pos = start;
+            mov.pos = slide.pos;
+            mov.vel = self.vel;
+        }
+    }
+}
+
+struct Slide<T> {
+    pos: V<T>,
+    ground: Option<ObjectKey>,
+    contacts: TileContacts,
+}
+
+// Mover moves a body among the bodies and tiles which block it
+struct Mover<'a, T> {
+    shape: Shape<T>,
+    size: V<T>,
+    tilemap: Option<&'a TileMap<T>>,
+    blockers: Vec<(ObjectKey, Solid<T>)>,
+}
+
+impl<'a, T> Mover<'a, T>
+where
+    T: Float,
+{
+    fn new<O>(
+        body: &O,
+        space: &Space<O>,
+        tilemap: Option<&'a TileMap<T>>,
+        motion: V<T>,
+        step_height: T,
+    ) -> Self
+    where
+        O: Object<T>,
+    {
+        let hitbox = body.hitbox();
+        let size = V::new(
+            T::from_usize(hitbox.width).unwrap(),
+            T::from_usize(hitbox.height).unwrap(),
+        );
+        let reach = motion.x.abs() + motion.y.abs() + step_height + T::one();
+        let min = hitbox.mov.pos - V::new(reach, reach);
+        let max = hitbox.mov.pos + size + V::new(reach, reach);
+        let blockers = space
+            .overlapping(min, max)
+            .into_iter()
+            .filter(|other| *other != body.key())
+            .filter_map(|other| space.get(other).map(|obj| (other, obj.hitbox())))
+            .filter(|(_, other)| {
+                !other.sensor
+                    && hitbox.interacts_with(other)
+                    && !(other.is_dynamic() && other.mass().is_some())
+            })
+            .map(|(other, hitbox)| (other, hitbox.solid()))
+            .collect();
+        Self {
+            shape: hitbox.shape.clone(),
+            size,
+            tilemap,
+            blockers,
+        }
+    }
+
+    fn solid(&self, pos: V<T>) -> Solid<T> {
+        Solid::new(&self.shape, pos, self.size.x, self.size.y)
+    }
+
+    fn fits(&self, pos: V<T>) -> bool {
+        let solid = self.solid(pos);
+        let tiles_fit = match self.tilemap {
+            Some(tilemap) => !tilemap.blocks(pos, pos + self.size),
+            None => true,
+        };
+        tiles_fit
+            && self
+                .blockers
+                .iter()
+                .all(|(_, other)| solid.contact(other).is_none())
+    }
+
+    // Move along motion, through the tiles axis by axis, then out of
+    // the bodies along the minimum translation vectors
+    fn slide(&self, start: V<T>, motion: V<T>) -> Slide<T> {
+        let (pos, contacts) = match self.tilemap {
+            Some(tilemap) => tilemap.sweep_box(start, start + motion, self.size),
+            None => (start + motion, TileContacts::default()),
+        };
+        let mut slide = Slide {
+            pos,
+            ground: None,
+            contacts,
+        };
+        let floor = T::from_f32(FLOOR_NORMAL).unwrap();
+        for _ in 0..MAX_SLIDES {
+            let solid = self.solid(slide.pos);
+            let deepest = self
+                .blockers
+                .iter()
+                .filter_map(|(key, other)| solid.contact(other).map(|contact| (*key, contact)))
+                .max_by(|(_, a), (_, b)| a.depth.total_cmp(&b.depth));
+            let (key, contact) = match deepest {
+                Some(deepest) => deepest,
+                None => break,
+            };
+            slide.pos = slide.pos - contact.normal * contact.depth;
+            // The normal points from the character to the other body
+            if contact.normal.y > floor {
+                slide.contacts.on_ground = true;
+                slide.ground = Some(key);
+            } else if contact.normal.y < -floor {
+                slide.contacts.hit_ceiling = true;
+            } else {
+                slide.contacts.hit_wall = true;
+            }
+        }
+        slide
+    }
+
+    // Move down from pos, by at most distance, until something is hit;
+    // None if nothing is
+    fn drop(&self, pos: V<T>, distance: T) -> Option<V<T>> {
+        if self.fits(pos + V::new(T::zero(), distance)) {
+            return None;
+        }
+        let two = T::from_f32(2.0).unwrap();
+        let (mut lo, mut hi) = (T::zero(), distance);
+        for _ in 0..16 {
+            let mid = (lo + hi) / two;
+            if self.fits(pos + V::new(T::zero(), mid)) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(pos + V::new(T::zero(), lo))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::box2d::{BodyKind, Box2DPhysics, HitBox, Mass, Mov};
+    use crate::physics::universe::Universe;
+
+    type Body = crate::physics::testing::Body<f32>;
+
+    fn block(x: f32, y: f32, width: usize, height: usize, kind: BodyKind) -> Body {
+        let mov = Mov::new(V::new(x, y), V::zero(), V::zero());
+        Body::new(HitBox {
+            kind,
+            ..HitBox::new(width, height, mov, Mass::Infinite)
+        })
+    }
+
+    // A level with a floor from x = -100 to 100 at y = 10, and a player
+    // standing on it at x
+    fn level(x: f32) -> (Universe<Box2DPhysics<f32>, Body>, ObjectKey) {
+        let mut universe = Universe::new(Box2DPhysics::new(0.0));
+        let space = universe.space_mut();
+        space.add(block(-100.0, 10.0, 200, 100, BodyKind::Static));
+        let player = space.add(block(x, 0.0, 10, 10, BodyKind::Kinematic));
+        let mut controller = CharacterController::new(1.0, 3.0, 0.1);
+        controller.coyote_time = 3.0;
+        controller.step_height = 4.0;
+        universe.physics_mut().add_controller(player, controller);
+        (universe, player)
+    }
+
+    fn pos(universe: &Universe<Box2DPhysics<f32>, Body>, key: ObjectKey) -> V<f32> {
+        universe.space().get(key).unwrap().hitbox.mov.pos
+    }
+
+    #[test]
+    fn test_walk_into_wall() {
+        let (mut universe, player) = level(0.0);
+        universe
+            .space_mut()
+            .add(block(20.0, -40.0, 50, 50, BodyKind::Static));
+        universe
+            .physics_mut()
+            .controller_mut(player)
+            .unwrap()
+            .walk(1.0);
+        for _ in 0..15 {
+            universe.tick(1.0);
+        }
+        let pos = pos(&universe, player);
+        assert_eq!(pos.x, 10.0);
+        assert!(pos.y.abs() < 1e-4);
+        let controller = universe.physics().controller(player).unwrap();
+        assert!(controller.is_grounded());
+        assert!(controller.contacts().hit_wall);
+    }
+
+    #[test]
+    fn test_step_up() {
+        let (mut universe, player) = level(0.0);
+        universe
+            .space_mut()
+            .add(block(20.0, 7.0, 30, 3, BodyKind::Static));
+        universe
+            .physics_mut()
+            .controller_mut(player)
+            .unwrap()
+            .walk(1.0);
+        for _ in 0..20 {
+            universe.tick(1.0);
+        }
+        let pos = pos(&universe, player);
+        assert_eq!(pos.x, 20.0);
+        assert!((pos.y + 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_coyote_time() {
+        for (coyote_time, jumps) in [(3.0, true), (1.0, false)] {
+            let (mut universe, player) = level(95.0);
+            let controller = universe.physics_mut().controller_mut(player).unwrap();
+            controller.coyote_time = coyote_time;
+            controller.walk(1.0);
+            universe.tick(1.0);
+            while universe.physics().controller(player).unwrap().is_grounded() {
+                universe.tick(1.0);
+            }
+            universe.tick(1.0);
+            universe.tick(1.0);
+            universe
+                .physics_mut()
+                .controller_mut(player)
+                .unwrap()
+                .jump();
+            universe.tick(1.0);
+            let vel = universe.physics().controller(player).unwrap().velocity();
+            assert_eq!(vel.y < 0.0, jumps);
+        }
+    }
+
+    #[test]
+    fn test_moving_platform() {
+        let mut universe = Universe::new(Box2DPhysics::new(0.0));
+        let space = universe.space_mut();
+        let mut platform = block(0.0, 10.0, 50, 10, BodyKind::Kinematic);
+        platform.hitbox.mov.vel = V::new(0.5, 0.0);
+        space.add(platform);
+        let player = space.add(block(10.0, 0.0, 10, 10, BodyKind::Kinematic));
+        universe
+            .physics_mut()
+            .add_controller(player, CharacterController::new(1.0, 3.0, 0.1));
+        for _ in 0..10 {
+            universe.tick(1.0);
+        }
+        let pos = pos(&universe, player);
+        assert!((pos.x - 14.5).abs() < 1e-4);
+        assert!(pos.y.abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_kinematic_pushes_dynamic() {
+        let mut universe = Universe::new(Box2DPhysics::new(0.0));
+        let space = universe.space_mut();
+        let mut pusher = block(0.0, 0.0, 10, 10, BodyKind::Kinematic);
+        pusher.hitbox.mov.vel = V::new(1.0, 0.0);
+        let pusher = space.add(pusher);
+        let mut crate_ = block(10.5, 0.0, 10, 10, BodyKind::Dynamic);
+        crate_.hitbox.mass = Mass::Fixed(1.0);
+        let crate_ = space.add(crate_);
+        for _ in 0..5 {
+            universe.tick(1.0);
+        }
+        assert_eq!(pos(&universe, pusher), V::new(5.0, 0.0));
+        let crate_ = universe.space().get(crate_).unwrap();
+        assert!(crate_.hitbox.mov.pos.x >= 14.0);
+        assert_eq!(crate_.hitbox.mov.vel.x, 1.0);
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::BTreeMap;
+
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+
+use super::box2d::{inverse_mass, Object};
+use super::identity::ObjectKey;
+use super::universe::{Restore, Space};
+use crate::event::{ConstraintBreakEvent, Event, Events, Sink};
+use crate::num::Float;
+use crate::vector::v2::V2 as V;
+
+// How much of the position error is corrected on each tick
+const BIAS: f32 = 0.2;
+
+pub const DEFAULT_ITERATIONS: usize = 8;
+
+/// ConstraintKey identifies a constraint added to Constraints.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Encode, Decode)]
+pub struct ConstraintKey(pub(super) u64);
+
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub enum ConstraintKind<T> {
+    /// Keeps the anchors length apart, like a rod
+    Distance { length: T },
+    /// Keeps the anchors at most length apart, like a rope
+    Rope { length: T },
+    /// Pulls or pushes the anchors towards length apart, with a force
+    /// of stiffness per unit of stretch, slowed down by damping
+    Spring { length: T, stiffness: T, damping: T },
+    /// Keeps the anchors together; as bodies don't rotate, a hinge
+    /// is a pin at the point the bodies turn around
+    Pin,
+}
+
+/// Constraint keeps an anchor on body a in a relation with an anchor
+/// on body b, or with a point of the world when b is None.
+///
+/// Anchors on bodies are relative to the center of their hitbox.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Constraint<T> {
+    pub a: ObjectKey,
+    pub anchor_a: V<T>,
+    pub b: Option<ObjectKey>,
+    pub anchor_b: V<T>,
+    pub kind: ConstraintKind<T>,
+    /// The constraint breaks when it pulls or pushes harder than this
+    pub max_force: Option<T>,
+}
+
+impl<T> Constraint<T>
+where
+    T: Float,
+{
+    pub fn new(a: ObjectKey, b: Option<ObjectKey>, kind: ConstraintKind<T>) -> Self {
+        Self {
+            a,
+            anchor_a: V::zero(),
+            b,
+            anchor_b: V::zero(),
+            kind,
+            max_force: None,
+        }
+    }
+
+    pub fn distance(a: ObjectKey, b: Option<ObjectKey>, length: T) -> Self {
+        Self::new(a, b, ConstraintKind::Distance { length })
+    }
+
+    pub fn rope(a: ObjectKey, b: Option<ObjectKey>, length: T) -> Self {
+        Self::new(a, b, ConstraintKind::Rope { length })
+    }
+
+    pub fn spring(a: ObjectKey, b: Option<ObjectKey>, length: T, stiffness: T, damping: T) -> Self {
+        Self::new(
+            a,
+            b,
+            ConstraintKind::Spring {
+                length,
+                stiffness,
+                damping,
+            },
+        )
+    }
+
+    pub fn pin(a: ObjectKey, b: Option<ObjectKey>) -> Self {
+        Self::new(a, b, ConstraintKind::Pin)
+    }
+
+    pub fn with_anchors(self, anchor_a: V<T>, anchor_b: V<T>) -> Self {
+        Self {
+            anchor_a,
+            anchor_b,
+            ..self
+        }
+    }
+
+    pub fn breakable(self, max_force: T) -> Self {
+        Self {
+            max_force: Some(max_force),
+            ..self
+        }
+    }
+}
+
+// Where the anchors of a constraint are, and how they move
+struct Ends<T> {
+    pos_a: V<T>,
+    vel_a: V<T>,
+    inv_mass_a: T,
+    pos_b: V<T>,
+    vel_b: V<T>,
+    inv_mass_b: T,
+}
+
+impl<T> Ends<T>
+where
+    T: Float,
+{
+    fn new<O>(constraint: &Constraint<T>, space: &Space<O>) -> Option<Self>
+    where
+        O: Object<T>,
+    {
+        let a = space.get(constraint.a)?.hitbox();
+        let (pos_b, vel_b, inv_mass_b) = match constraint.b {
+            Some(b) => {
+                let b = space.get(b)?.hitbox();
+                (
+                    b.center() + constraint.anchor_b,
+                    b.mov.vel,
+                    inverse_mass(b.mass()),
+                )
+            },
+            None => (constraint.anchor_b, V::zero(), T::zero()),
+        };
+        Some(Self {
+            pos_a: a.center() + constraint.anchor_a,
+            vel_a: a.mov.vel,
+            inv_mass_a: inverse_mass(a.mass()),
+            pos_b,
+            vel_b,
+            inv_mass_b,
+        })
+    }
+
+    fn inv_mass(&self) -> T {
+        self.inv_mass_a + self.inv_mass_b
+    }
+}
+
+/// Constraints between the bodies of a Space, solved with impulses
+/// on their velocities; see Box2DPhysics::constraints_mut.
+pub struct Constraints<T> {
+    constraints: BTreeMap<ConstraintKey, Constraint<T>>,
+    next_key: u64,
+    iterations: usize,
+    events: Option<Sink<Event>>,
+}
+
+impl<T> Constraints<T>
+where
+    T: Float,
+{
+    pub fn new() -> Self {
+        Self {
+            constraints: BTreeMap::new(),
+            next_key: 0,
+            iterations: DEFAULT_ITERATIONS,
+            events: None,
+        }
+    }
+
+    pub fn add(&mut self, constraint: Constraint<T>) -> ConstraintKey {
+        let key = ConstraintKey(self.next_key);
+        self.next_key += 1;
+        self.constraints.insert(key, constraint);
+        key
+    }
+
+    pub fn remove(&mut self, key: ConstraintKey) -> Option<Constraint<T>> {
+        self.constraints.remove(&key)
+    }
+
+    pub fn get(&self, key: ConstraintKey) -> Option<&Constraint<T>> {
+        self.constraints.get(&key)
+    }
+
+    pub fn get_mut(&mut self, key: ConstraintKey) -> Option<&mut Constraint<T>> {
+        self.constraints.get_mut(&key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ConstraintKey, &Constraint<T>)> {
+        self.constraints.iter().map(|(key, c)| (*key, c))
+    }
+
+    /// set_iterations sets how many times the constraints are solved
+    /// on each tick; more is stiffer, e.g. for long chains
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Send ConstraintBreakEvents into events
+    pub fn attach_events(&mut self, events: &mut Events) {
+        self.events = Some(events.sink());
+    }
+
+    /// solve changes the velocities of the bodies to satisfy the
+    /// constraints, and breaks those which take too much force.
+    /// Constraints on destroyed bodies are removed.
+    pub fn solve<O>(&mut self, space: &mut Space<O>, delta_t: f32)
+    where
+        O: Object<T>,
+    {
+        self.constraints
+            .retain(|_, c| space.contains(c.a) && c.b.map(|b| space.contains(b)).unwrap_or(true));
+        let dt = T::from_f32(delta_t).unwrap();
+        if dt <= T::zero() {
+            return;
+        }
+        // Total impulse of each constraint on this tick
+        let mut impulses: BTreeMap<ConstraintKey, V<T>> = BTreeMap::new();
+        for (key, constraint) in self.constraints.iter() {
+            if let ConstraintKind::Spring { .. } = constraint.kind {
+                if let Some(impulse) = spring(constraint, space, dt) {
+                    impulses.insert(*key, impulse);
+                }
+            }
+        }
+        for _ in 0..self.iterations {
+            for (key, constraint) in self.constraints.iter() {
+                let total = impulses.entry(*key).or_insert(V::zero());
+                rigid(constraint, space, dt, total);
+            }
+        }
+        let mut broken = vec![];
+        for (key, constraint) in self.constraints.iter() {
+            let (max_force, impulse) = match (constraint.max_force, impulses.get(key)) {
+                (Some(max_force), Some(impulse)) => (max_force, impulse),
+                _ => continue,
+            };
+            let force = impulse.mag() / dt;
+            if force > max_force {
+                broken.push((*key, force));
+            }
+        }
+        for (key, force) in broken {
+            let constraint = self.constraints.remove(&key).unwrap();
+            if let Some(sink) = &self.events {
+                sink.send(Event::ConstraintBreak(ConstraintBreakEvent {
+                    constraint: key,
+                    a: constraint.a,
+                    b: constraint.b,
+                    force: force.to_f32().unwrap(),
+                }));
+            }
+        }
+    }
+}
+
+impl<T> Default for Constraints<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Constraints are encoded without their event sink
+impl<T> Encode for Constraints<T>
+where
+    T: Float,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.constraints.encode(encoder)?;
+        self.next_key.encode(encoder)?;
+        self.iterations.encode(encoder)
+    }
+}
+
+impl<T> Decode for Constraints<T>
+where
+    T: Float,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            constraints: BTreeMap::decode(decoder)?,
+            next_key: u64::decode(decoder)?,
+            iterations: usize::decode(decoder)?,
+            events: None,
+        })
+    }
+}
+
+impl<T> Restore for Constraints<T>
+where
+    T: Float,
+{
+    fn restore(&mut self, saved: Self) {
+        let events = self.events.take();
+        *self = Self { events, ..saved };
+    }
+}
+
+// Apply impulse to b, and its opposite to a
+fn apply<T, O>(constraint: &Constraint<T>, space: &mut Space<O>, ends: &Ends<T>, impulse: V<T>)
+where
+    T: Float,
+    O: Object<T>,
+{
+    if let Some(a) = space.get_mut(constraint.a) {
+        a.hitbox_mut().mov.vel = ends.vel_a - impulse * ends.inv_mass_a;
+    }
+    if let Some(b) = constraint.b.and_then(|b| space.get_mut(b)) {
+        b.hitbox_mut().mov.vel = ends.vel_b + impulse * ends.inv_mass_b;
+    }
+}
+
+// Direction from a to b, and how far apart they are
+fn direction<T: Float>(ends: &Ends<T>) -> Option<(V<T>, T)> {
+    let d = ends.pos_b - ends.pos_a;
+    let len = d.mag();
+    match len > T::epsilon() {
+        true => Some((d / len, len)),
+        false => None,
+    }
+}
+
+// Springs push once per tick, with Hooke's law and damping
+fn spring<T, O>(constraint: &Constraint<T>, space: &mut Space<O>, dt: T) -> Option<V<T>>
+where
+    T: Float,
+    O: Object<T>,
+{
+    let (length, stiffness, damping) = match constraint.kind {
+        ConstraintKind::Spring {
+            length,
+            stiffness,
+            damping,
+        } => (length, stiffness, damping),
+        _ => return None,
+    };
+    let ends = Ends::new(constraint, space)?;
+    if ends.inv_mass() == T::zero() {
+        return None;
+    }
+    let (n, len) = direction(&ends)?;
+    let stretch = len - length;
+    let rel_vel = (ends.vel_b - ends.vel_a).dot(&n);
+    let force = n * -(stiffness * stretch + damping * rel_vel);
+    let impulse = force * dt;
+    apply(constraint, space, &ends, impulse);
+    Some(impulse)
+}
+
+// Rods, ropes and pins cancel the relative velocity of their anchors,
+// plus some of their position error, accumulating into total
+fn rigid<T, O>(constraint: &Constraint<T>, space: &mut Space<O>, dt: T, total: &mut V<T>)
+where
+    T: Float,
+    O: Object<T>,
+{
+    let ends = match Ends::new(constraint, space) {
+        Some(ends) => ends,
+        None => return,
+    };
+    let inv_mass = ends.inv_mass();
+    if inv_mass == T::zero() {
+        return;
+    }
+    let bias = T::from_f32(BIAS).unwrap() / dt;
+    let rel_vel = ends.vel_b - ends.vel_a;
+    let impulse = match constraint.kind {
+        ConstraintKind::Pin => {
+            let error = ends.pos_b - ends.pos_a;
+            (error * bias + rel_vel) / -inv_mass
+        },
+        ConstraintKind::Distance { length } => {
+            let (n, len) = match direction(&ends) {
+                Some(dir) => dir,
+                None => return,
+            };
+            n * ((bias * (len - length) + rel_vel.dot(&n)) / -inv_mass)
+        },
+        ConstraintKind::Rope { length } => {
+            let (n, len) = match direction(&ends) {
+                Some(dir) => dir,
+                None => return,
+            };
+            let j = (bias * (len - length) + rel_vel.dot(&n)) / -inv_mass;
+            // Ropes only pull: clamp the total along n to pulling
+            let before = total.dot(&n);
+            let after = (before + j).min(T::zero());
+            n * (after - before)
+        },
+        ConstraintKind::Spring { .. } => return,
+    };
+    *total = *total + impulse;
+    apply(constraint, space, &ends, impulse);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::Pump;
+    use crate::physics::box2d::{box2d_universe, HitBox, Mass, Mov};
+
+    type Body = crate::physics::testing::Body<f32>;
+
+    // A 2x2 body centered on pos
+    fn body(pos: V<f32>, mass: Mass<f32>) -> Body {
+        let mov = Mov::new(pos - V::new(1.0, 1.0), V::zero(), V::zero());
+        Body::new(HitBox::new(2, 2, mov, mass))
+    }
+
+    fn center(space: &Space<Body>, key: ObjectKey) -> V<f32> {
+        space.get(key).unwrap().hitbox.center()
+    }
+
+    #[test]
+    fn test_pendulum() {
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.001));
+        let bob = universe
+            .space_mut()
+            .add(body(V::new(10.0, 0.0), Mass::Fixed(1.0)));
+        universe.add_constraint(Constraint::distance(bob, None, 10.0));
+        let mut lowest: f32 = 0.0;
+        for _ in 0..500 {
+            universe.tick(1.0);
+            let pos = center(universe.space(), bob);
+            assert!((pos.mag() - 10.0).abs() < 0.1, "{:?}", pos);
+            lowest = lowest.max(pos.y);
+        }
+        // It swung through the bottom
+        assert!(lowest > 9.9);
+    }
+
+    #[test]
+    fn test_rope_and_pin() {
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.001));
+        let space = universe.space_mut();
+        let a = space.add(body(V::new(0.0, 5.0), Mass::Fixed(1.0)));
+        let b = space.add(body(V::new(20.0, 0.0), Mass::Fixed(1.0)));
+        let door = space.add(body(V::new(-20.0, 0.0), Mass::Fixed(1.0)));
+        let constraints = universe.physics_mut().constraints_mut();
+        // Slack rope: a falls freely until it's 10 below the anchor
+        constraints.add(Constraint::rope(a, None, 10.0));
+        // b hangs from a pin 1 above its center
+        constraints
+            .add(Constraint::pin(b, None).with_anchors(V::new(0.0, -1.0), V::new(20.0, -1.0)));
+        // The door hinges on a wall
+        let hinge = Constraint::pin(door, None).with_anchors(V::new(-1.0, 0.0), V::new(-21.0, 0.0));
+        constraints.add(hinge);
+        for _ in 0..10 {
+            universe.tick(1.0);
+        }
+        assert!(center(universe.space(), a).y > 5.0);
+        for _ in 0..500 {
+            universe.tick(1.0);
+        }
+        let space = universe.space();
+        assert!((center(space, a).y - 10.0).abs() < 0.1);
+        assert!((center(space, b) - V::new(20.0, 0.0)).mag() < 0.1);
+        // Bodies don't rotate, so the door holds its place
+        assert!((center(space, door) - V::new(-20.0, 0.0)).mag() < 0.1);
+    }
+
+    #[test]
+    fn test_spring() {
+        let mut universe = box2d_universe(0.0);
+        let space = universe.space_mut();
+        let a = space.add(body(V::new(0.0, 0.0), Mass::Fixed(1.0)));
+        let b = space.add(body(V::new(15.0, 0.0), Mass::Fixed(1.0)));
+        let spring = Constraint::spring(a, Some(b), 10.0, 0.001, 0.01);
+        universe.physics_mut().constraints_mut().add(spring);
+        for _ in 0..2000 {
+            universe.tick(1.0);
+        }
+        let space = universe.space();
+        let (a, b) = (center(space, a), center(space, b));
+        assert!(((b - a).mag() - 10.0).abs() < 0.1, "{:?} {:?}", a, b);
+        // Momentum is conserved
+        assert!(((a + b).x - 15.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_breakable() {
+        let (mut events, _) = Events::new();
+        let breaks = events.constraint_break_events();
+        let mut universe = box2d_universe(0.0);
+        universe.set_gravity(V::new(0.0, 0.001));
+        universe.physics_mut().attach_events(&mut events);
+        let space = universe.space_mut();
+        let light = space.add(body(V::new(0.0, 10.0), Mass::Fixed(1.0)));
+        let heavy = space.add(body(V::new(10.0, 10.0), Mass::Fixed(10.0)));
+        let constraints = universe.physics_mut().constraints_mut();
+        let holds = constraints.add(Constraint::rope(light, None, 10.0).breakable(0.005));
+        let snaps = constraints.add(
+            Constraint::rope(heavy, None, 10.0)
+                .with_anchors(V::zero(), V::new(10.0, 0.0))
+                .breakable(0.005),
+        );
+        for _ in 0..10 {
+            universe.tick(1.0);
+        }
+        events.pump();
+        let broken: Vec<ConstraintBreakEvent> = std::iter::from_fn(|| breaks.recv()).collect();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].constraint, snaps);
+        assert_eq!((broken[0].a, broken[0].b), (heavy, None));
+        let constraints = universe.physics().constraints();
+        assert!(constraints.get(holds).is_some());
+        assert!(constraints.get(snaps).is_none());
+        // Constraints go with their bodies
+        universe.space_mut().destroy(light);
+        universe.tick(1.0);
+        assert!(universe.physics().constraints().get(holds).is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use bincode::{Decode, Encode};
+
+use crate::num::Float;
+use crate::vector::v2::V2 as V;
+
+#[derive(Encode, Decode)]
+pub enum FieldKind<T> {
+    /// Constant acceleration, e.g. wind or a conveyor belt
+    Uniform(V<T>),
+    /// Acceleration of constant magnitude towards center;
+    /// a negative strength pushes away from it instead
+    Attractor { center: V<T>, strength: T },
+}
+
+/// ForceField accelerates the bodies whose center is within its region.
+///
+/// Like gravity, fields accelerate all bodies the same regardless
+/// of their mass, except bodies of infinite mass, which don't move.
+#[derive(Encode, Decode)]
+pub struct ForceField<T> {
+    /// Area covered by the field, as (top left, bottom right);
+    /// None for the whole space
+    pub region: Option<(V<T>, V<T>)>,
+    pub kind: FieldKind<T>,
+}
+
+impl<T> ForceField<T>
+where
+    T: Float,
+{
+    pub fn uniform(region: Option<(V<T>, V<T>)>, acc: V<T>) -> Self {
+        Self {
+            region,
+            kind: FieldKind::Uniform(acc),
+        }
+    }
+
+    pub fn attractor(region: Option<(V<T>, V<T>)>, center: V<T>, strength: T) -> Self {
+        Self {
+            region,
+            kind: FieldKind::Attractor { center, strength },
+        }
+    }
+
+    pub fn contains(&self, pos: V<T>) -> bool {
+        match &self.region {
+            Some((tl, br)) => pos.x >= tl.x && pos.x < br.x && pos.y >= tl.y && pos.y < br.y,
+            None => true,
+        }
+    }
+
+    /// acceleration returns the acceleration of a body centered on pos
+    pub fn acceleration(&self, pos: V<T>) -> V<T> {
+        if !self.contains(pos) {
+            return V::zero();
+        }
+        match &self.kind {
+            FieldKind::Uniform(acc) => *acc,
+            FieldKind::Attractor { center, strength } => {
+                let d = *center - pos;
+                let dist = d.dot(&d).sqrt();
+                if dist <= T::epsilon() {
+                    V::zero()
+                } else {
+                    d * (*strength / dist)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let wind = ForceField::uniform(
+            Some((V::new(0.0, 0.0), V::new(10.0, 10.0))),
+            V::new(1.0, 0.0),
+        );
+        assert_eq!(wind.acceleration(V::new(5.0, 5.0)), V::new(1.0, 0.0));
+        assert_eq!(wind.acceleration(V::new(15.0, 5.0)), V::zero());
+
+        let hole = ForceField::attractor(None, V::new(0.0, 0.0), 2.0);
+        assert_eq!(hole.acceleration(V::new(0.0, -10.0)), V::new(0.0, 2.0));
+        assert_eq!(hole.acceleration(V::zero()), V::zero());
+    }
+}
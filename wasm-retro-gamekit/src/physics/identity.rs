@@ -1,12 +1,19 @@
 use std::sync::atomic;
 
+use bincode::de::Decoder;
+use bincode::error::DecodeError;
+use bincode::{impl_borrow_decode, Decode, Encode};
+
 /// ObjectId is a globally unique identifier for objects in the physics system.
-#[derive(PartialEq, Eq)]
+///
+/// Decoded ids are kept, e.g. by Universe::restore, and ids allocated
+/// afterwards by ObjectId::new don't collide with them.
+#[derive(PartialEq, Eq, Encode)]
 pub struct ObjectId(pub(super) u64);
 
 /// ObjectKey is the externally copyable version of ObjectId.
 /// It can be copied and used to index objects, but not to identify them.
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Encode, Decode)]
 pub struct ObjectKey(pub(super) u64);
 
 pub trait Identity {
@@ -28,10 +35,35 @@ where
 
 static OBJECT_ID_COUNTER: atomic::AtomicU64 = atomic::AtomicU64::new(0);
 
+// Ids allocated by spaces have the top bit set, so that they never
+// collide with those of the global counter; see Space::new_id
+const SPACE_ID: u64 = 1 << 63;
+
 impl ObjectId {
     pub fn new() -> Self {
         let n = OBJECT_ID_COUNTER.fetch_add(1, atomic::Ordering::SeqCst);
-        assert_ne!(n, u64::MAX, "allocated u64::MAX ObjectIds!?");
+        assert!(n < SPACE_ID, "allocated 2^63 ObjectIds!?");
         Self(n)
     }
+
+    pub(super) fn in_space(n: u64) -> Self {
+        assert!(n < SPACE_ID, "allocated 2^63 ObjectIds in a space!?");
+        Self(SPACE_ID | n)
+    }
 }
+
+/// Decoding an ObjectId which wasn't allocated by a space moves the
+/// global counter of ObjectId::new past it, as a side effect, so that
+/// ids allocated afterwards don't collide with decoded ones. Decoding
+/// ids which are never used, e.g. from another game, still uses them up.
+impl Decode for ObjectId {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let id = u64::decode(decoder)?;
+        if id < SPACE_ID {
+            OBJECT_ID_COUNTER.fetch_max(id + 1, atomic::Ordering::SeqCst);
+        }
+        Ok(Self(id))
+    }
+}
+
+impl_borrow_decode!(ObjectId);
@@ -1,3 +1,11 @@
 pub mod box2d;
+pub mod character;
+pub mod constraint;
+pub mod field;
 pub mod identity;
+pub mod shape;
+pub mod spatial;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod tilemap;
 pub mod universe;
@@ -0,0 +1,778 @@
+use bincode::{Decode, Encode};
+
+use crate::num::Float;
+use crate::vector::v2::{Axis, V2 as V};
+
+/// Shape of a HitBox, fitted into its width and height.
+#[derive(Clone, Debug, Default, PartialEq, Encode, Decode)]
+#[bincode(
+    encode_bounds = "T: Encode + 'static",
+    decode_bounds = "T: Decode + 'static",
+    borrow_decode_bounds = "T: bincode::BorrowDecode<'__de> + 'static"
+)]
+pub enum Shape<T> {
+    /// Fills the whole hitbox
+    #[default]
+    Aabb,
+    /// Centered, with a diameter of the smallest of width and height
+    Circle,
+    /// Rounded at both ends of the longest of width and height
+    Capsule,
+    /// Convex polygon, with vertices relative to the hitbox position.
+    /// The vertices should fit within the hitbox width and height,
+    /// which are used to find potential collisions.
+    Polygon(Vec<V<T>>),
+}
+
+impl<T> Shape<T>
+where
+    T: Float,
+{
+    /// A right triangle filling half of the hitbox, sloping up towards
+    /// the right when rising, or towards the left otherwise
+    pub fn ramp(width: T, height: T, rising: bool) -> Self {
+        let o = T::zero();
+        let corner = match rising {
+            true => V::new(width, o),
+            false => V::new(o, o),
+        };
+        Shape::Polygon(vec![V::new(o, height), corner, V::new(width, height)])
+    }
+
+    pub fn area(&self, width: T, height: T) -> T {
+        let d = width.min(height);
+        let r = d / T::from_f32(2.0).unwrap();
+        let circle = T::from_f64(std::f64::consts::PI).unwrap() * r * r;
+        match self {
+            Shape::Aabb => width * height,
+            Shape::Circle => circle,
+            Shape::Capsule => circle + d * (width.max(height) - d),
+            Shape::Polygon(points) => {
+                // Shoelace formula
+                let mut twice_area = T::zero();
+                for (i, p) in points.iter().enumerate() {
+                    let q = points[(i + 1) % points.len()];
+                    twice_area = twice_area + p.x * q.y - q.x * p.y;
+                }
+                twice_area.abs() / T::from_f32(2.0).unwrap()
+            },
+        }
+    }
+}
+
+/// Contact between two shapes: normal points from the first shape
+/// to the second, and depth is how much they overlap along it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact<T> {
+    pub normal: V<T>,
+    pub depth: T,
+}
+
+impl<T> Contact<T>
+where
+    T: Float,
+{
+    fn flip(self) -> Self {
+        Self {
+            normal: self.normal * -T::one(),
+            depth: self.depth,
+        }
+    }
+}
+
+/// A shape placed in world coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub enum Solid<T> {
+    Rect {
+        min: V<T>,
+        max: V<T>,
+    },
+    /// All points within radius of the segment from a to b;
+    /// a circle when a == b
+    Round {
+        a: V<T>,
+        b: V<T>,
+        radius: T,
+    },
+    Polygon(Vec<V<T>>),
+}
+
+impl<T> Solid<T>
+where
+    T: Float,
+{
+    pub fn new(shape: &Shape<T>, pos: V<T>, width: T, height: T) -> Self {
+        let two = T::from_f32(2.0).unwrap();
+        let radius = width.min(height) / two;
+        let center = pos + V::new(width / two, height / two);
+        match shape {
+            Shape::Aabb => Solid::Rect {
+                min: pos,
+                max: pos + V::new(width, height),
+            },
+            Shape::Circle => Solid::Round {
+                a: center,
+                b: center,
+                radius,
+            },
+            Shape::Capsule => {
+                let half = if width > height {
+                    V::new(width / two - radius, T::zero())
+                } else {
+                    V::new(T::zero(), height / two - radius)
+                };
+                Solid::Round {
+                    a: center - half,
+                    b: center + half,
+                    radius,
+                }
+            },
+            Shape::Polygon(points) => Solid::Polygon(points.iter().map(|p| *p + pos).collect()),
+        }
+    }
+
+    /// contact returns how self and other overlap, if they do;
+    /// the normal and depth give the minimum translation to separate them
+    pub fn contact(&self, other: &Self) -> Option<Contact<T>> {
+        match (self, other) {
+            (Solid::Polygon(_), _) | (_, Solid::Polygon(_)) => sat(self, other),
+            (Solid::Rect { min: a0, max: a1 }, Solid::Rect { min: b0, max: b1 }) => {
+                rect_rect(*a0, *a1, *b0, *b1)
+            },
+            (
+                Solid::Round {
+                    a: a0,
+                    b: a1,
+                    radius: ra,
+                },
+                Solid::Round {
+                    a: b0,
+                    b: b1,
+                    radius: rb,
+                },
+            ) => {
+                let (pa, pb) = closest_segment_points(*a0, *a1, *b0, *b1);
+                circle_circle(pa, *ra, pb, *rb)
+            },
+            (Solid::Round { a, b, radius }, Solid::Rect { min, max }) => {
+                round_rect(*a, *b, *radius, *min, *max)
+            },
+            (Solid::Rect { min, max }, Solid::Round { a, b, radius }) => {
+                round_rect(*a, *b, *radius, *min, *max).map(Contact::flip)
+            },
+        }
+    }
+
+    /// axis_contact returns how two rectangles overlap along an axis
+    pub fn axis_contact(&self, other: &Self, axis: Axis) -> Option<Contact<T>> {
+        match (self, other) {
+            (Solid::Rect { min: a0, max: a1 }, Solid::Rect { min: b0, max: b1 }) => {
+                let overlap = a1.ax(axis).min(*b1.ax(axis)) - a0.ax(axis).max(*b0.ax(axis));
+                let delta = *b0.ax(axis) + *b1.ax(axis) - *a0.ax(axis) - *a1.ax(axis);
+                let mut normal = V::zero();
+                *normal.ax_mut(axis) = if delta < T::zero() {
+                    -T::one()
+                } else {
+                    T::one()
+                };
+                Some(Contact {
+                    normal,
+                    depth: overlap,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, p: V<T>) -> bool {
+        match self {
+            Solid::Rect { min, max } => {
+                p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y
+            },
+            Solid::Round { a, b, radius } => {
+                (p - closest_point_on_segment(*a, *b, p)).mag() <= *radius
+            },
+            Solid::Polygon(points) if points.is_empty() => false,
+            Solid::Polygon(points) => {
+                // On the same side of every edge
+                let mut sides = points.iter().enumerate().map(|(i, q)| {
+                    let edge = points[(i + 1) % points.len()] - *q;
+                    cross(edge, p - *q)
+                });
+                sides.clone().all(|s| s >= T::zero()) || sides.all(|s| s <= T::zero())
+            },
+        }
+    }
+
+    /// distance returns how far p is from the solid, 0 if it's inside
+    pub fn distance(&self, p: V<T>) -> T {
+        if self.contains(p) {
+            return T::zero();
+        }
+        match self {
+            Solid::Rect { min, max } => {
+                (p - V::new(clamp(p.x, min.x, max.x), clamp(p.y, min.y, max.y))).mag()
+            },
+            Solid::Round { a, b, radius } => {
+                (p - closest_point_on_segment(*a, *b, p)).mag() - *radius
+            },
+            Solid::Polygon(points) => points
+                .iter()
+                .enumerate()
+                .map(|(i, q)| {
+                    let r = points[(i + 1) % points.len()];
+                    (p - closest_point_on_segment(*q, r, p)).mag()
+                })
+                .fold(T::infinity(), T::min),
+        }
+    }
+
+    /// raycast returns the distance along the ray from origin towards
+    /// the unit vector dir at which it enters the solid, and the normal
+    /// of the surface hit there. Rays starting inside the solid hit it
+    /// at distance 0, with the normal opposite to dir.
+    pub fn raycast(&self, origin: V<T>, dir: V<T>, max_distance: T) -> Option<(T, V<T>)> {
+        if self.contains(origin) {
+            return Some((T::zero(), dir * -T::one()));
+        }
+        let hit = match self {
+            Solid::Rect { .. } | Solid::Polygon(_) => raycast_convex(&self.points(), origin, dir),
+            Solid::Round { a, b, radius } => raycast_round(*a, *b, *radius, origin, dir),
+        };
+        hit.filter(|(t, _)| *t <= max_distance)
+    }
+
+    /// time_of_impact returns when self, moving by motion, first touches
+    /// other, as a fraction of motion from 0 to 1, and the contact normal,
+    /// from self to other; None if they don't touch, already overlap,
+    /// or only touch while moving apart
+    pub fn time_of_impact(&self, motion: V<T>, other: &Self) -> Option<(T, V<T>)> {
+        let distance = motion.mag();
+        if distance == T::zero() || self.contact(other).is_some() {
+            return None;
+        }
+        let dir = motion / distance;
+        // self touches other when motion reaches the Minkowski difference
+        // of other and self: the convex hull of the differences of their
+        // points, rounded by the sum of their radiuses
+        let mut diffs = vec![];
+        for q in other.points() {
+            for p in self.points() {
+                diffs.push(q - p);
+            }
+        }
+        let hull = convex_hull(diffs);
+        let radius = self.radius() + other.radius();
+        let origin = V::zero();
+        let hit = match hull.len() {
+            1 | 2 => raycast_round(hull[0], hull[hull.len() - 1], radius, origin, dir),
+            _ if radius == T::zero() => raycast_convex(&hull, origin, dir),
+            _ => {
+                let edges = hull.iter().enumerate().map(|(i, p)| {
+                    raycast_round(*p, hull[(i + 1) % hull.len()], radius, origin, dir)
+                });
+                edges.flatten().min_by(|a, b| a.0.total_cmp(&b.0))
+            },
+        };
+        // Touching solids moving apart don't collide
+        hit.filter(|(t, normal)| *t <= distance && dir.dot(normal) < T::zero())
+            .map(|(t, normal)| (t / distance, normal * -T::one()))
+    }
+
+    fn radius(&self) -> T {
+        match self {
+            Solid::Round { radius, .. } => *radius,
+            _ => T::zero(),
+        }
+    }
+
+    fn points(&self) -> Vec<V<T>> {
+        match self {
+            Solid::Rect { min, max } => {
+                vec![*min, V::new(max.x, min.y), *max, V::new(min.x, max.y)]
+            },
+            Solid::Round { a, b, .. } => vec![*a, *b],
+            Solid::Polygon(points) => points.clone(),
+        }
+    }
+
+    // Projection on an axis, as (min, max)
+    fn project(&self, axis: V<T>) -> (T, T) {
+        let mut lo = T::infinity();
+        let mut hi = T::neg_infinity();
+        for p in self.points() {
+            let d = p.dot(&axis);
+            lo = lo.min(d);
+            hi = hi.max(d);
+        }
+        match self {
+            Solid::Round { radius, .. } => (lo - *radius, hi + *radius),
+            _ => (lo, hi),
+        }
+    }
+
+    // Candidate separating axes contributed by self
+    fn sat_axes(&self, other: &Self) -> Vec<V<T>> {
+        let (o, l) = (T::zero(), T::one());
+        match self {
+            Solid::Rect { .. } => vec![V::new(l, o), V::new(o, l)],
+            Solid::Polygon(points) => points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let edge = points[(i + 1) % points.len()] - *p;
+                    V::new(edge.y, -edge.x).norm()
+                })
+                .collect(),
+            Solid::Round { a, b, .. } => {
+                let edge = *b - *a;
+                let mut axes = vec![V::new(edge.y, -edge.x).norm()];
+                for p in other.points() {
+                    axes.push((p - closest_point_on_segment(*a, *b, p)).norm());
+                }
+                axes
+            },
+        }
+    }
+}
+
+// Separating axis theorem: the solids overlap when their projections
+// overlap on every candidate axis, and the axis with the least overlap
+// gives the minimum translation vector
+fn sat<T: Float>(a: &Solid<T>, b: &Solid<T>) -> Option<Contact<T>> {
+    let mut best: Option<Contact<T>> = None;
+    for axis in a.sat_axes(b).into_iter().chain(b.sat_axes(a)) {
+        if axis == V::zero() {
+            continue;
+        }
+        let (a_lo, a_hi) = a.project(axis);
+        let (b_lo, b_hi) = b.project(axis);
+        // Moving b forward, or backward, along the axis
+        let forward = a_hi - b_lo;
+        let backward = b_hi - a_lo;
+        if forward <= T::zero() || backward <= T::zero() {
+            return None;
+        }
+        let contact = if forward < backward {
+            Contact {
+                normal: axis,
+                depth: forward,
+            }
+        } else {
+            Contact {
+                normal: axis * -T::one(),
+                depth: backward,
+            }
+        };
+        if best.map(|best| contact.depth < best.depth).unwrap_or(true) {
+            best = Some(contact);
+        }
+    }
+    best
+}
+
+fn clamp<T: Float>(x: T, lo: T, hi: T) -> T {
+    x.max(lo).min(hi)
+}
+
+fn cross<T: Float>(a: V<T>, b: V<T>) -> T {
+    a.x * b.y - a.y * b.x
+}
+
+// Andrew's monotone chain; collinear points are dropped, so the hull
+// of points on a line is its two ends
+fn convex_hull<T: Float>(mut points: Vec<V<T>>) -> Vec<V<T>> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<V<T>> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        for p in points.iter() {
+            while hull.len() >= start + 2
+                && cross(
+                    hull[hull.len() - 1] - hull[hull.len() - 2],
+                    *p - hull[hull.len() - 2],
+                ) <= T::zero()
+            {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        hull.pop();
+        if pass == 0 {
+            points.reverse();
+        }
+    }
+    hull
+}
+
+// Cyrus-Beck clipping of the ray against each edge of a convex polygon
+fn raycast_convex<T: Float>(points: &[V<T>], origin: V<T>, dir: V<T>) -> Option<(T, V<T>)> {
+    let n = T::from_usize(points.len()).unwrap();
+    let centroid = points.iter().fold(V::zero(), |sum, p| sum + *p) / n;
+    let mut t_enter = T::zero();
+    let mut t_exit = T::infinity();
+    let mut normal = None;
+    for (i, p) in points.iter().enumerate() {
+        let edge = points[(i + 1) % points.len()] - *p;
+        let mut outward = V::new(edge.y, -edge.x).norm();
+        if outward.dot(&(centroid - *p)) > T::zero() {
+            outward = outward * -T::one();
+        }
+        // Inside the edge where outward . (origin + t * dir - p) <= 0
+        let denom = outward.dot(&dir);
+        let num = outward.dot(&(*p - origin));
+        if denom == T::zero() {
+            if num < T::zero() {
+                return None;
+            }
+        } else if denom < T::zero() {
+            let t = num / denom;
+            if t > t_enter || normal.is_none() {
+                t_enter = t_enter.max(t);
+                normal = Some(outward);
+            }
+        } else {
+            t_exit = t_exit.min(num / denom);
+        }
+        // Only grazing the hull, or leaving it from its boundary, misses it
+        if t_enter >= t_exit {
+            return None;
+        }
+    }
+    normal.map(|normal| (t_enter, normal))
+}
+
+fn raycast_round<T: Float>(
+    a: V<T>,
+    b: V<T>,
+    radius: T,
+    origin: V<T>,
+    dir: V<T>,
+) -> Option<(T, V<T>)> {
+    let mut best: Option<(T, V<T>)> = None;
+    let mut consider = |hit: Option<(T, V<T>)>| {
+        if let Some((t, normal)) = hit {
+            if best.map(|(best_t, _)| t < best_t).unwrap_or(true) {
+                best = Some((t, normal));
+            }
+        }
+    };
+    consider(raycast_circle(a, radius, origin, dir));
+    consider(raycast_circle(b, radius, origin, dir));
+    if a != b {
+        // The two straight sides of a capsule
+        let ab = b - a;
+        let side = V::new(ab.y, -ab.x).norm();
+        for side in [side, side * -T::one()] {
+            let offset = side * radius;
+            if side.dot(&dir) < T::zero() {
+                consider(raycast_segment(a + offset, b + offset, origin, dir).map(|t| (t, side)));
+            }
+        }
+    }
+    best
+}
+
+fn raycast_circle<T: Float>(center: V<T>, radius: T, origin: V<T>, dir: V<T>) -> Option<(T, V<T>)> {
+    let m = origin - center;
+    let b = m.dot(&dir);
+    let c = m.dot(&m) - radius * radius;
+    let disc = b * b - c;
+    if disc < T::zero() {
+        return None;
+    }
+    let t = -b - disc.sqrt();
+    if t < T::zero() {
+        return None;
+    }
+    Some((t, (origin + dir * t - center) / radius))
+}
+
+fn raycast_segment<T: Float>(p0: V<T>, p1: V<T>, origin: V<T>, dir: V<T>) -> Option<T> {
+    let edge = p1 - p0;
+    let denom = cross(dir, edge);
+    if denom == T::zero() {
+        return None;
+    }
+    let w = p0 - origin;
+    let t = cross(w, edge) / denom;
+    let s = cross(w, dir) / denom;
+    if t >= T::zero() && s >= T::zero() && s <= T::one() {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+fn rect_rect<T: Float>(a0: V<T>, a1: V<T>, b0: V<T>, b1: V<T>) -> Option<Contact<T>> {
+    let overlap_x = a1.x.min(b1.x) - a0.x.max(b0.x);
+    let overlap_y = a1.y.min(b1.y) - a0.y.max(b0.y);
+    if overlap_x <= T::zero() || overlap_y <= T::zero() {
+        return None;
+    }
+    // b is on the side of a where the centers are
+    let delta = (b0 + b1) - (a0 + a1);
+    let sign = |d: T| if d < T::zero() { -T::one() } else { T::one() };
+    if overlap_x < overlap_y {
+        Some(Contact {
+            normal: V::new(sign(delta.x), T::zero()),
+            depth: overlap_x,
+        })
+    } else {
+        Some(Contact {
+            normal: V::new(T::zero(), sign(delta.y)),
+            depth: overlap_y,
+        })
+    }
+}
+
+fn circle_circle<T: Float>(ca: V<T>, ra: T, cb: V<T>, rb: T) -> Option<Contact<T>> {
+    let delta = cb - ca;
+    let distance = delta.mag();
+    if distance >= ra + rb {
+        return None;
+    }
+    let normal = if distance == T::zero() {
+        // Any direction works for concentric circles
+        V::new(T::one(), T::zero())
+    } else {
+        delta / distance
+    };
+    Some(Contact {
+        normal,
+        depth: ra + rb - distance,
+    })
+}
+
+fn round_rect<T: Float>(a: V<T>, b: V<T>, radius: T, min: V<T>, max: V<T>) -> Option<Contact<T>> {
+    // Closest point of the segment to the rectangle; this is exact
+    // for the axis-aligned segments of capsules
+    let center = (min + max) / T::from_f32(2.0).unwrap();
+    let p = closest_point_on_segment(a, b, center);
+    let q = V::new(clamp(p.x, min.x, max.x), clamp(p.y, min.y, max.y));
+    let p = closest_point_on_segment(a, b, q);
+    let q = V::new(clamp(p.x, min.x, max.x), clamp(p.y, min.y, max.y));
+
+    if p != q {
+        let delta = q - p;
+        let distance = delta.mag();
+        if distance >= radius {
+            return None;
+        }
+        return Some(Contact {
+            normal: delta / distance,
+            depth: radius - distance,
+        });
+    }
+    // The segment is inside the rectangle: leave by the nearest side
+    let (o, l) = (T::zero(), T::one());
+    let exits = [
+        (p.x - min.x, V::new(l, o)),
+        (max.x - p.x, V::new(-l, o)),
+        (p.y - min.y, V::new(o, l)),
+        (max.y - p.y, V::new(o, -l)),
+    ];
+    let (penetration, normal) = exits.iter().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+    Some(Contact {
+        normal: *normal,
+        depth: *penetration + radius,
+    })
+}
+
+fn closest_point_on_segment<T: Float>(a: V<T>, b: V<T>, p: V<T>) -> V<T> {
+    let ab = b - a;
+    let len2 = ab.dot(&ab);
+    if len2 == T::zero() {
+        return a;
+    }
+    let t = clamp((p - a).dot(&ab) / len2, T::zero(), T::one());
+    a + ab * t
+}
+
+// Closest points between segments a0-a1 and b0-b1,
+// from Ericson's Real-Time Collision Detection
+fn closest_segment_points<T: Float>(a0: V<T>, a1: V<T>, b0: V<T>, b1: V<T>) -> (V<T>, V<T>) {
+    let (zero, one) = (T::zero(), T::one());
+    let da = a1 - a0;
+    let db = b1 - b0;
+    let r = a0 - b0;
+    let a = da.dot(&da);
+    let e = db.dot(&db);
+    let f = db.dot(&r);
+    if a == zero && e == zero {
+        return (a0, b0);
+    }
+    let (s, t) = if a == zero {
+        (zero, clamp(f / e, zero, one))
+    } else {
+        let c = da.dot(&r);
+        if e == zero {
+            (clamp(-c / a, zero, one), zero)
+        } else {
+            let b = da.dot(&db);
+            let denom = a * e - b * b;
+            let s = if denom != zero {
+                clamp((b * f - c * e) / denom, zero, one)
+            } else {
+                zero
+            };
+            let t = (b * s + f) / e;
+            if t < zero {
+                (clamp(-c / a, zero, one), zero)
+            } else if t > one {
+                (clamp((b - c) / a, zero, one), one)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (a0 + da * s, b0 + db * t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn solid(shape: Shape<f32>, x: f32, y: f32, width: f32, height: f32) -> Solid<f32> {
+        Solid::new(&shape, V::new(x, y), width, height)
+    }
+
+    #[test]
+    fn test_circle_circle() {
+        let a = solid(Shape::Circle, 0.0, 0.0, 4.0, 4.0);
+        let b = solid(Shape::Circle, 3.0, 0.0, 4.0, 4.0);
+        let contact = a.contact(&b).unwrap();
+        assert_eq!(contact.normal, V::new(1.0, 0.0));
+        assert_eq!(contact.depth, 1.0);
+        assert_eq!(b.contact(&a).unwrap().normal, V::new(-1.0, 0.0));
+
+        // Overlapping bounding boxes, but not circles
+        let c = solid(Shape::Circle, 3.5, 3.5, 4.0, 4.0);
+        assert_eq!(a.contact(&c), None);
+    }
+
+    #[test]
+    fn test_circle_rect() {
+        let rect = solid(Shape::Aabb, 0.0, 0.0, 10.0, 10.0);
+        let above = solid(Shape::Circle, 4.0, -3.0, 4.0, 4.0);
+        let contact = above.contact(&rect).unwrap();
+        assert_eq!(contact.normal, V::new(0.0, 1.0));
+        assert_eq!(contact.depth, 1.0);
+        assert_eq!(rect.contact(&above).unwrap().normal, V::new(0.0, -1.0));
+
+        // Near the corner, the normal is diagonal
+        let corner = solid(Shape::Circle, -3.0, -3.0, 4.0, 4.0);
+        let contact = corner.contact(&rect).unwrap();
+        assert!((contact.normal.x - contact.normal.y).abs() < 1e-6);
+        assert_eq!(
+            solid(Shape::Circle, -3.9, -3.9, 4.0, 4.0).contact(&rect),
+            None
+        );
+
+        // Center inside the rectangle
+        let inside = solid(Shape::Circle, 6.0, 3.0, 2.0, 2.0);
+        let contact = inside.contact(&rect).unwrap();
+        assert_eq!(contact.normal, V::new(-1.0, 0.0));
+        assert_eq!(contact.depth, 4.0);
+    }
+
+    #[test]
+    fn test_capsule() {
+        // Horizontal capsule, segment from (2, 2) to (8, 2)
+        let capsule = solid(Shape::Capsule, 0.0, 0.0, 10.0, 4.0);
+        let ball = solid(Shape::Circle, 4.0, 3.0, 2.0, 2.0);
+        let contact = capsule.contact(&ball).unwrap();
+        assert_eq!(contact.normal, V::new(0.0, 1.0));
+        assert_eq!(contact.depth, 1.0);
+
+        let floor = solid(Shape::Aabb, -10.0, 3.0, 30.0, 10.0);
+        let contact = capsule.contact(&floor).unwrap();
+        assert_eq!(contact.normal, V::new(0.0, 1.0));
+        assert_eq!(contact.depth, 1.0);
+
+        let wall = solid(Shape::Capsule, 9.0, -10.0, 4.0, 30.0);
+        let contact = capsule.contact(&wall).unwrap();
+        assert_eq!(contact.normal, V::new(1.0, 0.0));
+        assert_eq!(contact.depth, 1.0);
+    }
+
+    #[test]
+    fn test_polygon() {
+        // Ramp rising towards the right, from (0, 10) to (10, 0)
+        let ramp = solid(Shape::ramp(10.0, 10.0, true), 0.0, 0.0, 10.0, 10.0);
+        let ball = solid(Shape::Circle, 3.5, 3.5, 2.0, 2.0);
+        let contact = ball.contact(&ramp).unwrap();
+        let diagonal = (0.5f32).sqrt();
+        assert!((contact.normal.x - diagonal).abs() < 1e-5);
+        assert!((contact.normal.y - diagonal).abs() < 1e-5);
+        assert!((contact.depth - (1.0 - diagonal)).abs() < 1e-5);
+        assert_eq!(
+            solid(Shape::Circle, 1.0, 1.0, 2.0, 2.0).contact(&ramp),
+            None
+        );
+
+        let block = solid(Shape::Aabb, 7.0, -3.0, 4.0, 4.0);
+        let contact = ramp.contact(&block).unwrap();
+        assert_eq!(contact.normal, V::new(0.0, -1.0));
+        assert_eq!(contact.depth, 1.0);
+        assert_eq!(Shape::ramp(10.0, 10.0, false).area(10.0, 10.0), 50.0);
+    }
+
+    #[test]
+    fn test_queries() {
+        let rect = solid(Shape::Aabb, 0.0, 0.0, 10.0, 10.0);
+        let ball = solid(Shape::Circle, 20.0, 0.0, 10.0, 10.0);
+        let ramp = solid(Shape::ramp(10.0, 10.0, true), 0.0, 20.0, 10.0, 10.0);
+        assert!(rect.contains(V::new(10.0, 5.0)));
+        assert!(!ball.contains(V::new(21.0, 1.0)));
+        assert!(ramp.contains(V::new(9.0, 29.0)));
+        assert!(!ramp.contains(V::new(1.0, 21.0)));
+        assert_eq!(rect.distance(V::new(13.0, 14.0)), 5.0);
+        assert_eq!(ball.distance(V::new(25.0, -5.0)), 5.0);
+
+        let right = V::new(1.0, 0.0);
+        let hit = rect.raycast(V::new(-5.0, 5.0), right, 100.0);
+        assert_eq!(hit, Some((5.0, V::new(-1.0, 0.0))));
+        assert_eq!(rect.raycast(V::new(-5.0, 5.0), right, 4.0), None);
+        assert_eq!(rect.raycast(V::new(-5.0, 15.0), right, 100.0), None);
+        let hit = ball.raycast(V::new(0.0, 5.0), right, 100.0);
+        assert_eq!(hit, Some((20.0, V::new(-1.0, 0.0))));
+        // Down onto the slope of the ramp
+        let (t, normal) = ramp
+            .raycast(V::new(5.0, 0.0), V::new(0.0, 1.0), 100.0)
+            .unwrap();
+        let diagonal = (0.5f32).sqrt();
+        assert!((t - 25.0).abs() < 1e-5);
+        assert!((normal.x + diagonal).abs() < 1e-5 && (normal.y + diagonal).abs() < 1e-5);
+
+        let capsule = solid(Shape::Capsule, 0.0, 40.0, 30.0, 10.0);
+        let hit = capsule.raycast(V::new(15.0, 30.0), V::new(0.0, 1.0), 100.0);
+        assert_eq!(hit, Some((10.0, V::new(0.0, -1.0))));
+
+        let empty: Solid<f32> = Solid::Polygon(vec![]);
+        assert!(!empty.contains(V::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_time_of_impact() {
+        let wall = solid(Shape::Aabb, 10.0, -50.0, 1.0, 100.0);
+        let bullet = solid(Shape::Aabb, 0.0, 0.0, 2.0, 2.0);
+        let (t, normal) = bullet.time_of_impact(V::new(100.0, 0.0), &wall).unwrap();
+        assert!((t - 0.08).abs() < 1e-6);
+        assert_eq!(normal, V::new(1.0, 0.0));
+        assert_eq!(bullet.time_of_impact(V::new(0.0, 100.0), &wall), None);
+        assert_eq!(bullet.time_of_impact(V::new(5.0, 0.0), &wall), None);
+
+        let ball = solid(Shape::Circle, 0.0, 0.0, 2.0, 2.0);
+        let (t, normal) = ball.time_of_impact(V::new(100.0, 0.0), &wall).unwrap();
+        assert!((t - 0.08).abs() < 1e-6);
+        assert!((normal.x - 1.0).abs() < 1e-6);
+        let target = solid(Shape::Circle, 20.0, 0.0, 2.0, 2.0);
+        let (t, _) = ball.time_of_impact(V::new(40.0, 0.0), &target).unwrap();
+        assert!((t - 0.45).abs() < 1e-6);
+    }
+}
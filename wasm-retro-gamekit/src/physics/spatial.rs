@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use super::identity::ObjectKey;
+use crate::pair::Pair;
+use crate::vector::v2::V2;
+
+pub const DEFAULT_CELL_SIZE: f64 = 64.0;
+
+// Objects covering more cells are kept aside instead, e.g. walls
+const MAX_CELLS: i64 = 64;
+
+// Where an object is in the hash
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    // The range of cells it covers, as (x0, y0, x1, y1) inclusive
+    Cells(i64, i64, i64, i64),
+    Large,
+}
+
+/// SpatialHash buckets objects by the square cells of a grid
+/// their bounding boxes overlap, to find the objects near
+/// each other without comparing all of them.
+pub struct SpatialHash {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<ObjectKey>>,
+    // Objects too large to be bucketed, which are near everything
+    large: Vec<ObjectKey>,
+    entries: HashMap<ObjectKey, Entry>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            large: vec![],
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// set_cell_size clears the hash; cells should be about
+    /// the size of the most common objects
+    pub fn set_cell_size(&mut self, cell_size: f64) {
+        self.cell_size = cell_size;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.large.clear();
+        self.entries.clear();
+    }
+
+    pub fn contains(&self, key: ObjectKey) -> bool {
+        self.entries.contains_key(&key)
+    }
+
+    // Range of cells covered by a box, as (x0, y0, x1, y1) inclusive,
+    // and how many cells that is
+    fn cell_range(&self, min: V2<f64>, max: V2<f64>) -> ((i64, i64, i64, i64), i64) {
+        let x0 = (min.x / self.cell_size).floor() as i64;
+        let y0 = (min.y / self.cell_size).floor() as i64;
+        let x1 = (max.x / self.cell_size).floor() as i64;
+        let y1 = (max.y / self.cell_size).floor() as i64;
+        let count = x1
+            .saturating_sub(x0)
+            .saturating_add(1)
+            .saturating_mul(y1.saturating_sub(y0).saturating_add(1));
+        ((x0, y0, x1, y1), count)
+    }
+
+    /// insert inserts the object with the box from min to max, or moves
+    /// it there if it's already in the hash; objects which stay in the
+    /// same cells aren't touched
+    pub fn insert(&mut self, key: ObjectKey, min: V2<f64>, max: V2<f64>) {
+        let ((x0, y0, x1, y1), count) = self.cell_range(min, max);
+        let entry = match count > MAX_CELLS {
+            true => Entry::Large,
+            false => Entry::Cells(x0, y0, x1, y1),
+        };
+        if self.entries.get(&key) == Some(&entry) {
+            return;
+        }
+        self.remove(key);
+        self.entries.insert(key, entry);
+        match entry {
+            Entry::Large => self.large.push(key),
+            Entry::Cells(x0, y0, x1, y1) => {
+                for x in x0..=x1 {
+                    for y in y0..=y1 {
+                        self.cells.entry((x, y)).or_default().push(key);
+                    }
+                }
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: ObjectKey) {
+        match self.entries.remove(&key) {
+            Some(Entry::Large) => self.large.retain(|other| *other != key),
+            Some(Entry::Cells(x0, y0, x1, y1)) => {
+                for x in x0..=x1 {
+                    for y in y0..=y1 {
+                        let cell = self.cells.get_mut(&(x, y)).unwrap();
+                        cell.retain(|other| *other != key);
+                        if cell.is_empty() {
+                            self.cells.remove(&(x, y));
+                        }
+                    }
+                }
+            },
+            None => {},
+        }
+    }
+
+    /// query returns the objects which may overlap the box from min to max
+    pub fn query(&self, min: V2<f64>, max: V2<f64>) -> Vec<ObjectKey> {
+        let ((x0, y0, x1, y1), count) = self.cell_range(min, max);
+        let mut keys = self.large.clone();
+        if count > self.cells.len() as i64 {
+            for ((x, y), cell) in self.cells.iter() {
+                if (x0..=x1).contains(x) && (y0..=y1).contains(y) {
+                    keys.extend(cell);
+                }
+            }
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        keys.extend(cell);
+                    }
+                }
+            }
+        }
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// pairs returns the pairs of objects which may overlap
+    pub fn pairs(&self) -> HashSet<Pair<ObjectKey>> {
+        self.pairs_of(|_| true)
+    }
+
+    /// pairs_except returns the pairs of objects which may overlap,
+    /// except the pairs of two skipped objects, e.g. sleeping ones
+    pub fn pairs_except(&self, skipped: &HashSet<ObjectKey>) -> HashSet<Pair<ObjectKey>> {
+        self.pairs_of(|key| !skipped.contains(key))
+    }
+
+    /// pairs_of returns the pairs of objects which may overlap, of which
+    /// at least one is active; pairs of inactive objects aren't visited
+    pub fn pairs_of(&self, active: impl Fn(&ObjectKey) -> bool) -> HashSet<Pair<ObjectKey>> {
+        let mut pairs = HashSet::new();
+        let mut insert = |a: &ObjectKey, b: &ObjectKey| {
+            pairs.insert(Pair::new(*a, *b));
+        };
+        for cell in self.cells.values() {
+            let (on, off) = split(cell.iter(), &active);
+            for (i, a) in on.iter().enumerate() {
+                for b in on[i + 1..].iter().chain(off.iter()) {
+                    insert(a, b);
+                }
+            }
+        }
+        let keys = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| **entry != Entry::Large)
+            .map(|(key, _)| key);
+        let (keys_on, keys_off) = split(keys, &active);
+        let (large_on, large_off) = split(self.large.iter(), &active);
+        for (i, a) in large_on.iter().enumerate() {
+            let others = keys_on.iter().chain(keys_off.iter());
+            for b in others
+                .chain(large_on[i + 1..].iter())
+                .chain(large_off.iter())
+            {
+                insert(a, b);
+            }
+        }
+        for a in large_off.iter() {
+            for b in keys_on.iter() {
+                insert(a, b);
+            }
+        }
+        pairs
+    }
+}
+
+// Split keys into the active ones and the others
+fn split<'a>(
+    keys: impl Iterator<Item = &'a ObjectKey>,
+    active: &impl Fn(&ObjectKey) -> bool,
+) -> (Vec<ObjectKey>, Vec<ObjectKey>) {
+    keys.partition(|key| active(key))
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spatial_hash() {
+        let (a, b, c, wall) = (ObjectKey(0), ObjectKey(1), ObjectKey(2), ObjectKey(3));
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(a, V2::new(0.0, 0.0), V2::new(5.0, 5.0));
+        hash.insert(b, V2::new(8.0, 8.0), V2::new(12.0, 12.0));
+        hash.insert(c, V2::new(50.0, 50.0), V2::new(55.0, 55.0));
+        hash.insert(wall, V2::new(-1000.0, 100.0), V2::new(1000.0, 200.0));
+
+        let pairs = hash.pairs();
+        assert!(pairs.contains(&Pair::new(a, b)));
+        assert!(!pairs.contains(&Pair::new(a, c)));
+        assert!(pairs.contains(&Pair::new(c, wall)));
+        assert_eq!(pairs.len(), 4);
+        let skipped = HashSet::from([b, wall]);
+        assert_eq!(hash.pairs_except(&skipped).len(), 3);
+        let of_c = hash.pairs_of(|key| *key == c);
+        assert_eq!(of_c, HashSet::from([Pair::new(c, wall)]));
+
+        let near = hash.query(V2::new(11.0, 11.0), V2::new(12.0, 12.0));
+        assert_eq!(near, vec![b, wall]);
+        let all = hash.query(V2::new(-1e9, -1e9), V2::new(1e9, 1e9));
+        assert_eq!(all, vec![a, b, c, wall]);
+
+        // Moving b away from a, and removing the wall
+        hash.insert(b, V2::new(80.0, 8.0), V2::new(85.0, 12.0));
+        hash.remove(wall);
+        assert!(hash.pairs().is_empty());
+        assert_eq!(hash.query(V2::new(0.0, 0.0), V2::new(20.0, 20.0)), vec![a]);
+        hash.insert(wall, V2::new(-1000.0, 100.0), V2::new(1000.0, 200.0));
+        hash.insert(wall, V2::new(0.0, 0.0), V2::new(1.0, 1.0));
+        assert_eq!(hash.pairs(), HashSet::from([Pair::new(a, wall)]));
+    }
+}
@@ -0,0 +1,57 @@
+use bincode::{Decode, Encode};
+
+use super::box2d::{HitBox, Mass, Mov, Object};
+use super::identity::{Identity, ObjectId};
+use super::shape::Shape;
+use crate::num::Float;
+use crate::vector::v2::V2 as V;
+
+// A body which is nothing but its hitbox, shared by the physics tests
+#[derive(Encode, Decode)]
+#[bincode(
+    encode_bounds = "T: Encode + 'static",
+    decode_bounds = "T: Decode + 'static",
+    borrow_decode_bounds = "T: bincode::BorrowDecode<'__de> + 'static"
+)]
+pub(crate) struct Body<T> {
+    pub(crate) id: ObjectId,
+    pub(crate) hitbox: HitBox<T>,
+}
+
+impl<T> Body<T> {
+    pub(crate) fn new(hitbox: HitBox<T>) -> Self {
+        Self {
+            id: ObjectId::new(),
+            hitbox,
+        }
+    }
+}
+
+impl<T: Float> Identity for Body<T> {
+    fn id(&self) -> &ObjectId {
+        &self.id
+    }
+}
+
+impl<T: Float> Object<T> for Body<T> {
+    fn hitbox(&self) -> &HitBox<T> {
+        &self.hitbox
+    }
+    fn hitbox_mut(&mut self) -> &mut HitBox<T> {
+        &mut self.hitbox
+    }
+}
+
+// A square body of size moving from pos at vel
+pub(crate) fn body(
+    shape: Shape<f32>,
+    pos: V<f32>,
+    vel: V<f32>,
+    size: usize,
+    mass: Mass<f32>,
+) -> Body<f32> {
+    Body::new(HitBox {
+        shape,
+        ..HitBox::new(size, size, Mov::new(pos, vel, V::zero()), mass)
+    })
+}
@@ -0,0 +1,386 @@
+use std::ops::BitOr;
+
+use bincode::{Decode, Encode};
+
+use super::box2d::HitBox;
+use crate::grid::Grid;
+use crate::num::Float;
+use crate::vector::v2::V2 as V;
+
+/// Tile flags, which can be combined, e.g. Tile::ONE_WAY | Tile::LADDER
+/// for the top of a ladder which can be stood on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile(pub u8);
+
+impl Tile {
+    pub const EMPTY: Tile = Tile(0);
+    /// Blocks from all sides
+    pub const SOLID: Tile = Tile(1);
+    /// Platform which can be jumped through from below, and landed on
+    pub const ONE_WAY: Tile = Tile(1 << 1);
+    /// Floor sloping up towards the right, like Shape::ramp when rising
+    pub const SLOPE_UP: Tile = Tile(1 << 2);
+    /// Floor sloping down towards the right
+    pub const SLOPE_DOWN: Tile = Tile(1 << 3);
+    /// Doesn't block; see TileContacts::on_ladder
+    pub const LADDER: Tile = Tile(1 << 4);
+
+    pub fn contains(self, flags: Tile) -> bool {
+        self.0 & flags.0 == flags.0 && flags.0 != 0
+    }
+}
+
+impl BitOr for Tile {
+    type Output = Tile;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Tile(self.0 | rhs.0)
+    }
+}
+
+/// TileContacts is how a hitbox touched the tiles during the last tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct TileContacts {
+    /// Standing on a solid tile, platform or slope
+    pub on_ground: bool,
+    /// Overlapping a ladder tile
+    pub on_ladder: bool,
+    pub hit_ceiling: bool,
+    pub hit_wall: bool,
+}
+
+/// TileMap is static level geometry made of square tiles.
+///
+/// Tiles outside of the grid are empty, even if it wraps.
+pub struct TileMap<T> {
+    pub tiles: Grid<Tile>,
+    pub tile_size: T,
+    /// World position of the top left corner of tile (0, 0)
+    pub origin: V<T>,
+    /// Collision layers of the tiles, see HitBox::mask
+    pub category: u32,
+}
+
+impl<T> TileMap<T>
+where
+    T: Float,
+{
+    pub fn new(tiles: Grid<Tile>, tile_size: T, origin: V<T>) -> Self {
+        Self {
+            tiles,
+            tile_size,
+            origin,
+            category: 1,
+        }
+    }
+
+    pub fn tile(&self, v: V<i64>) -> Tile {
+        let (w, h) = (self.tiles.width() as i64, self.tiles.height() as i64);
+        if v.x < 0 || v.y < 0 || v.x >= w || v.y >= h {
+            return Tile::EMPTY;
+        }
+        *self.tiles.get(v)
+    }
+
+    /// tile_pos returns the coordinates of the tile at a world position
+    pub fn tile_pos(&self, pos: V<T>) -> V<i64> {
+        let rel = (pos - self.origin) / self.tile_size;
+        V::new(
+            rel.x.floor().to_i64().unwrap_or(i64::MIN),
+            rel.y.floor().to_i64().unwrap_or(i64::MIN),
+        )
+    }
+
+    pub fn tile_at(&self, pos: V<T>) -> Tile {
+        self.tile(self.tile_pos(pos))
+    }
+
+    // World bounds of a tile, as (top left, bottom right)
+    fn tile_rect(&self, v: V<i64>) -> (V<T>, V<T>) {
+        let min = self.origin
+            + V::new(
+                T::from_i64(v.x).unwrap() * self.tile_size,
+                T::from_i64(v.y).unwrap() * self.tile_size,
+            );
+        (min, min + V::new(self.tile_size, self.tile_size))
+    }
+
+    // Non-empty tiles overlapping the box from min to max
+    fn overlapping(&self, min: V<T>, max: V<T>) -> Vec<(V<i64>, Tile)> {
+        let (w, h) = (self.tiles.width() as i64, self.tiles.height() as i64);
+        let tl = self.tile_pos(min);
+        let br = self.tile_pos(max);
+        let mut tiles = vec![];
+        for y in tl.y.max(0)..=br.y.min(h - 1) {
+            for x in tl.x.max(0)..=br.x.min(w - 1) {
+                let v = V::new(x, y);
+                let tile = self.tile(v);
+                if tile != Tile::EMPTY {
+                    tiles.push((v, tile));
+                }
+            }
+        }
+        tiles
+    }
+
+    // Height of the floor of a tile under a box spanning x0 to x1;
+    // slopes are stood on by the corner of the box on their high side
+    fn floor(&self, v: V<i64>, tile: Tile, x0: T, x1: T) -> Option<T> {
+        let (min, max) = self.tile_rect(v);
+        let clamp = |x: T| x.max(min.x).min(max.x);
+        if tile.contains(Tile::SOLID) || tile.contains(Tile::ONE_WAY) {
+            Some(min.y)
+        } else if tile.contains(Tile::SLOPE_UP) {
+            Some(max.y - (clamp(x1) - min.x))
+        } else if tile.contains(Tile::SLOPE_DOWN) {
+            Some(min.y + (clamp(x0) - min.x))
+        } else {
+            None
+        }
+    }
+
+    /// collides_with returns whether a hitbox should be blocked
+    /// by the tiles; sensors and bodies of infinite mass aren't
+    pub fn collides_with(&self, hitbox: &HitBox<T>) -> bool {
+        !hitbox.sensor && hitbox.mass().is_some() && (hitbox.mask & self.category) != 0
+    }
+
+    // Tolerance for boxes resting exactly against tiles
+    fn skin(&self) -> T {
+        self.tile_size / T::from_f32(1000.0).unwrap()
+    }
+
+    /// blocks returns whether a solid tile overlaps the box from min to max
+    pub fn blocks(&self, min: V<T>, max: V<T>) -> bool {
+        let skin = V::new(self.skin(), self.skin());
+        self.overlapping(min + skin, max - skin)
+            .iter()
+            .any(|(_, tile)| tile.contains(Tile::SOLID))
+    }
+
+    // Whether a solid tile is in the columns and rows, inclusive
+    fn solid_in(&self, columns: (i64, i64), rows: (i64, i64)) -> bool {
+        (rows.0..=rows.1)
+            .any(|y| (columns.0..=columns.1).any(|x| self.tile(V::new(x, y)).contains(Tile::SOLID)))
+    }
+
+    /// sweep moves the hitbox from its previous position to its current
+    /// one, first horizontally then vertically, stopping it against tiles.
+    /// The velocity along an axis is dropped when the hitbox is stopped.
+    pub fn sweep(&self, hitbox: &mut HitBox<T>) -> TileContacts {
+        let size = V::new(
+            T::from_usize(hitbox.width).unwrap(),
+            T::from_usize(hitbox.height).unwrap(),
+        );
+        let (pos, contacts) = self.sweep_box(hitbox.mov.prev_pos, hitbox.mov.pos, size);
+        let vel = &mut hitbox.mov.vel;
+        if contacts.hit_wall {
+            vel.x = T::zero();
+        }
+        if contacts.hit_ceiling || (contacts.on_ground && vel.y > T::zero()) {
+            vel.y = T::zero();
+        }
+        hitbox.mov.pos = pos;
+        contacts
+    }
+
+    /// sweep_box moves a box of size from start towards end like sweep,
+    /// and returns where it stopped. The box is moved tile by tile, so
+    /// that fast boxes don't pass through tiles, and only tiles ahead of
+    /// it stop it, so that boxes starting inside a tile aren't snapped
+    /// back.
+    pub fn sweep_box(&self, start: V<T>, end: V<T>, size: V<T>) -> (V<T>, TileContacts) {
+        let skin = self.skin();
+        let shrunk = |pos: V<T>| (pos + V::new(skin, skin), pos + size - V::new(skin, skin));
+        let mut contacts = TileContacts::default();
+        let (w, h) = (self.tiles.width(), self.tiles.height());
+
+        // Horizontally, only solid tiles block
+        let mut pos = V::new(end.x, start.y);
+        let dx = end.x - start.x;
+        let (min, max) = shrunk(start);
+        let (end_min, end_max) = shrunk(end);
+        let rows = (self.tile_pos(min).y, self.tile_pos(max).y);
+        let columns = match dx > T::zero() {
+            true => between(
+                self.tile_pos(max).x.saturating_add(1),
+                self.tile_pos(end_max).x,
+                w,
+            ),
+            false => between(
+                self.tile_pos(min).x.saturating_sub(1),
+                self.tile_pos(end_min).x,
+                w,
+            ),
+        };
+        let wall = columns
+            .filter(|_| dx != T::zero())
+            .find(|x| self.solid_in((*x, *x), rows));
+        if let Some(x) = wall {
+            let (tile_min, tile_max) = self.tile_rect(V::new(x, rows.0));
+            pos.x = match dx > T::zero() {
+                true => tile_min.x - size.x,
+                false => tile_max.x,
+            };
+            contacts.hit_wall = true;
+        }
+
+        // Vertically, land on the highest floor, or hit the ceiling
+        pos.y = end.y;
+        let dy = end.y - start.y;
+        let prev_bottom = start.y + size.y;
+        let (min, max) = shrunk(V::new(pos.x, start.y));
+        let columns = (self.tile_pos(min).x, self.tile_pos(max).x);
+        if dy < T::zero() {
+            let ceiling = between(
+                self.tile_pos(min).y.saturating_sub(1),
+                self.tile_pos(end_min).y,
+                h,
+            )
+            .find(|y| self.solid_in(columns, (*y, *y)));
+            if let Some(y) = ceiling {
+                pos.y = self.tile_rect(V::new(columns.0, y)).1.y;
+                contacts.hit_ceiling = true;
+            }
+        } else {
+            // From the top of the box, as slopes lift it when walked onto
+            for y in between(self.tile_pos(min).y, self.tile_pos(end_max).y, h) {
+                let floor = (columns.0..=columns.1)
+                    .filter_map(|x| {
+                        let v = V::new(x, y);
+                        let tile = self.tile(v);
+                        let floor = self.floor(v, tile, pos.x, pos.x + size.x)?;
+                        let lands = if tile.contains(Tile::SOLID) || tile.contains(Tile::ONE_WAY) {
+                            dy > T::zero() && prev_bottom <= floor + skin
+                        } else {
+                            true
+                        };
+                        (lands && pos.y + size.y > floor).then_some(floor)
+                    })
+                    .reduce(T::min);
+                if let Some(floor) = floor {
+                    pos.y = floor - size.y;
+                    contacts.on_ground = true;
+                    break;
+                }
+            }
+        }
+
+        // Resting exactly on a floor counts as standing on it
+        let bottom = pos.y + size.y;
+        let probe_min = V::new(pos.x + skin, bottom - skin);
+        let probe_max = V::new(pos.x + size.x - skin, bottom + skin);
+        for (v, tile) in self.overlapping(probe_min, probe_max) {
+            if let Some(floor) = self.floor(v, tile, pos.x, pos.x + size.x) {
+                if (floor - bottom).abs() <= skin {
+                    contacts.on_ground = true;
+                }
+            }
+        }
+
+        let (min, max) = shrunk(pos);
+        contacts.on_ladder = self
+            .overlapping(min, max)
+            .iter()
+            .any(|(_, tile)| tile.contains(Tile::LADDER));
+        (pos, contacts)
+    }
+}
+
+// Columns, or rows, from one to the other, in that order, skipping
+// those outside of the len columns, or rows, of the grid
+fn between(from: i64, to: i64, len: usize) -> Box<dyn Iterator<Item = i64>> {
+    let (lo, hi) = (from.min(to).max(0), from.max(to).min(len as i64 - 1));
+    match from <= to {
+        true => Box::new(lo..=hi),
+        false => Box::new((lo..=hi).rev()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::physics::box2d::{Mass, Mov};
+
+    // 10x10 tiles of size 10: a floor on row 8, a wall on column 9,
+    // a platform at (4, 5), a slope at (2, 7) and a ladder at (6, 7)
+    fn level() -> TileMap<f32> {
+        let mut tiles: Grid<Tile> = Grid::new(10, 10);
+        tiles.nowrap();
+        for x in 0..10 {
+            *tiles.get_mut(V::new(x, 8)) = Tile::SOLID;
+        }
+        for y in 0..10 {
+            *tiles.get_mut(V::new(9, y)) = Tile::SOLID;
+        }
+        *tiles.get_mut(V::new(4, 5)) = Tile::ONE_WAY;
+        *tiles.get_mut(V::new(2, 7)) = Tile::SLOPE_UP;
+        *tiles.get_mut(V::new(6, 7)) = Tile::LADDER;
+        TileMap::new(tiles, 10.0, V::zero())
+    }
+
+    fn moved(from: (f32, f32), to: (f32, f32)) -> HitBox<f32> {
+        let mut mov = Mov::new(V::new(from.0, from.1), V::new(1.0, 1.0), V::zero());
+        mov.pos = V::new(to.0, to.1);
+        HitBox::new(6, 6, mov, Mass::Fixed(1.0))
+    }
+
+    #[test]
+    fn test_floor_and_wall() {
+        let tiles = level();
+        let mut hitbox = moved((70.0, 70.0), (85.0, 76.0));
+        let contacts = tiles.sweep(&mut hitbox);
+        assert_eq!(hitbox.mov.pos, V::new(84.0, 74.0));
+        assert_eq!(hitbox.mov.vel, V::new(0.0, 0.0));
+        assert!(contacts.on_ground && contacts.hit_wall);
+
+        // Standing still on the floor
+        let mut hitbox = moved((50.0, 74.0), (50.0, 74.0));
+        let contacts = tiles.sweep(&mut hitbox);
+        assert!(contacts.on_ground && !contacts.hit_wall);
+    }
+
+    #[test]
+    fn test_no_tunneling() {
+        let tiles = level();
+        // Past the floor, and past the wall, in one tick
+        let mut hitbox = moved((50.0, 60.0), (50.0, 120.0));
+        assert!(tiles.sweep(&mut hitbox).on_ground);
+        assert_eq!(hitbox.mov.pos, V::new(50.0, 74.0));
+        let mut hitbox = moved((70.0, 30.0), (120.0, 30.0));
+        assert!(tiles.sweep(&mut hitbox).hit_wall);
+        assert_eq!(hitbox.mov.pos, V::new(84.0, 30.0));
+
+        // Starting inside the wall, moving right out of it
+        let mut hitbox = moved((88.0, 30.0), (95.0, 30.0));
+        assert!(!tiles.sweep(&mut hitbox).hit_wall);
+        assert_eq!(hitbox.mov.pos, V::new(95.0, 30.0));
+    }
+
+    #[test]
+    fn test_one_way_platform() {
+        let tiles = level();
+        // Jumping through from below
+        let mut hitbox = moved((42.0, 58.0), (42.0, 52.0));
+        tiles.sweep(&mut hitbox);
+        assert_eq!(hitbox.mov.pos, V::new(42.0, 52.0));
+        // Falling onto it
+        let mut hitbox = moved((42.0, 42.0), (42.0, 47.0));
+        let contacts = tiles.sweep(&mut hitbox);
+        assert_eq!(hitbox.mov.pos, V::new(42.0, 44.0));
+        assert!(contacts.on_ground);
+    }
+
+    #[test]
+    fn test_slope_and_ladder() {
+        let tiles = level();
+        // Walking right onto the slope, whose floor is at y = 76 at x = 24
+        let mut hitbox = moved((12.0, 74.0), (18.0, 74.0));
+        let contacts = tiles.sweep(&mut hitbox);
+        assert_eq!(hitbox.mov.pos, V::new(18.0, 70.0));
+        assert!(contacts.on_ground);
+
+        let mut hitbox = moved((62.0, 74.0), (62.0, 74.0));
+        assert!(tiles.sweep(&mut hitbox).on_ladder);
+    }
+}
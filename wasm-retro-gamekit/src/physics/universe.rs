@@ -1,15 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::identity::{Identity, IdentityKey, ObjectKey};
-use crate::graphics::{Paint, Scene, Viewport};
+use bincode::de::Decoder;
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{config, decode_from_slice, encode_to_vec, Decode, Encode};
+
+use super::identity::{Identity, IdentityKey, ObjectId, ObjectKey};
+use super::spatial::SpatialHash;
+use crate::graphics::{Paint, Scene, Sprite, Viewport};
+use crate::vector::v2::V2;
 
 pub trait Physics<T> {
     fn tick(&mut self, space: &mut Space<T>, delta_t: f32);
+
+    /// index updates the spatial index of the space with the objects
+    /// added or moved since it was last updated, e.g. after restoring
+    /// a snapshot; physics which don't use the index leave it empty
+    fn index(&mut self, _space: &mut Space<T>) {}
+}
+
+/// Restore is implemented by physics which can be saved in snapshots
+/// of a Universe, along with its space; see Universe::snapshot.
+pub trait Restore {
+    /// restore takes over the state of saved, decoded from a snapshot,
+    /// keeping what isn't saved, e.g. attached event sinks
+    fn restore(&mut self, saved: Self);
+}
+
+/// Interpolate paints objects between their positions at the
+/// previous and the current physics step.
+pub trait Interpolate {
+    /// alpha goes from 0, at the previous step, to 1, at the current one
+    fn paint_interpolated(&self, alpha: f32) -> Option<Sprite>;
+}
+
+#[derive(Encode, Decode)]
+struct FixedStep {
+    step: f32,
+    max_substeps: usize,
+    accumulator: f32,
 }
 
 pub struct Universe<P, T> {
     physics: P,
     space: Space<T>,
+    fixed_step: Option<FixedStep>,
 }
 
 impl<P, T> Universe<P, T>
@@ -21,9 +56,42 @@ where
         Self {
             physics,
             space: Space::new(),
+            fixed_step: None,
         }
     }
 
+    /// Step physics by a fixed delta_t of step, instead of the delta_t
+    /// passed to tick, which is accumulated; at most max_substeps steps
+    /// are run per tick, and the time left over after them is dropped.
+    ///
+    /// Panics if step isn't positive or max_substeps is 0.
+    pub fn set_fixed_step(&mut self, step: f32, max_substeps: usize) {
+        assert!(step > 0.0, "fixed step must be positive, got {step}");
+        assert!(max_substeps > 0, "max_substeps must be at least 1");
+        self.fixed_step = Some(FixedStep {
+            step,
+            max_substeps,
+            accumulator: 0.0,
+        });
+    }
+
+    /// alpha returns how far between the last two fixed steps time is,
+    /// from 0 to 1, for interpolation; without fixed steps, it's always 1
+    pub fn alpha(&self) -> f32 {
+        match &self.fixed_step {
+            Some(fixed_step) => fixed_step.accumulator / fixed_step.step,
+            None => 1.0,
+        }
+    }
+
+    pub fn physics(&self) -> &P {
+        &self.physics
+    }
+
+    pub fn physics_mut(&mut self) -> &mut P {
+        &mut self.physics
+    }
+
     pub fn space(&self) -> &Space<T> {
         &self.space
     }
@@ -33,12 +101,106 @@ where
     }
 
     pub fn tick(&mut self, delta_t: f32) {
-        self.physics.tick(&mut self.space, delta_t)
+        let fixed_step = match &mut self.fixed_step {
+            Some(fixed_step) => fixed_step,
+            None => {
+                self.physics.tick(&mut self.space, delta_t);
+                return;
+            },
+        };
+        fixed_step.accumulator += delta_t;
+        let mut substeps = 0;
+        while fixed_step.accumulator >= fixed_step.step {
+            if substeps == fixed_step.max_substeps {
+                // Slow down rather than fall further behind
+                fixed_step.accumulator %= fixed_step.step;
+                break;
+            }
+            self.physics.tick(&mut self.space, fixed_step.step);
+            fixed_step.accumulator -= fixed_step.step;
+            substeps += 1;
+        }
+    }
+}
+
+impl<P, T> Universe<P, T>
+where
+    T: Identity + Encode + Decode + 'static,
+    P: Physics<T> + Restore + Encode + Decode,
+{
+    /// snapshot saves the state of the universe with bincode, e.g. for
+    /// save games, rewinding time, or rolling back to it when inputs
+    /// from the network arrive late
+    pub fn snapshot(&self) -> Result<Vec<u8>, EncodeError> {
+        encode_to_vec(self, config::standard())
+    }
+
+    /// restore returns the universe to the state saved by snapshot.
+    /// Objects keep their ObjectIds, so ObjectKeys stay valid, and
+    /// the spatial index is rebuilt by the physics; see Physics::index
+    pub fn restore(&mut self, snapshot: &[u8]) -> Result<(), DecodeError> {
+        let (saved, _): (Self, usize) = decode_from_slice(snapshot, config::standard())?;
+        self.physics.restore(saved.physics);
+        let cell_size = self.space.index.cell_size();
+        self.space = saved.space;
+        self.space.set_cell_size(cell_size);
+        self.physics.index(&mut self.space);
+        self.fixed_step = saved.fixed_step;
+        Ok(())
+    }
+}
+
+impl<P, T> Encode for Universe<P, T>
+where
+    P: Encode,
+    T: Encode + 'static,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.physics.encode(encoder)?;
+        self.space.encode(encoder)?;
+        self.fixed_step.encode(encoder)
+    }
+}
+
+impl<P, T> Decode for Universe<P, T>
+where
+    P: Decode,
+    T: Identity + Decode + 'static,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            physics: P::decode(decoder)?,
+            space: Space::decode(decoder)?,
+            fixed_step: Option::decode(decoder)?,
+        })
+    }
+}
+
+impl<P, T> Universe<P, T>
+where
+    T: Identity + Interpolate,
+    P: Physics<T>,
+{
+    /// paint paints the objects of the space, interpolated between
+    /// the last two fixed steps
+    pub fn paint(&self, viewport: &Viewport) -> Scene {
+        self.space.paint_interpolated(viewport, self.alpha())
     }
 }
 
+/// Space stores objects in slots, which are reused once their
+/// object is destroyed, and finds them by key.
 pub struct Space<T> {
-    objects: HashMap<ObjectKey, T>,
+    slots: Vec<Option<T>>,
+    free: Vec<usize>,
+    // Keys come from the ids of the objects, which are chosen before
+    // they are added, e.g. to be the same on all peers, so they can't
+    // be slot indices, and slots are found by key through this map
+    slot_by_key: HashMap<ObjectKey, usize>,
+    index: SpatialHash,
+    // Objects added or borrowed mutably since they were last indexed
+    unindexed: HashSet<ObjectKey>,
+    next_id: u64,
 }
 
 impl<T> Space<T>
@@ -47,40 +209,178 @@ where
 {
     pub fn new() -> Self {
         Self {
-            objects: HashMap::new(),
+            slots: vec![],
+            free: vec![],
+            slot_by_key: HashMap::new(),
+            index: SpatialHash::default(),
+            unindexed: HashSet::new(),
+            next_id: 0,
         }
     }
 
+    /// new_id allocates an ObjectId for an object of this space.
+    /// Unlike ObjectId::new, spaces allocate ids in order and save
+    /// where they are in snapshots, so the same game gives the same
+    /// ids, e.g. on all the peers of a networked game.
+    pub fn new_id(&mut self) -> ObjectId {
+        self.next_id += 1;
+        ObjectId::in_space(self.next_id - 1)
+    }
+
     pub fn add(&mut self, object: T) -> ObjectKey {
         let key = object.key();
-        if self.objects.insert(key, object).is_some() {
+        if self.slot_by_key.contains_key(&key) {
             panic!("objects with same ObjectId({})", key.0)
         };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(object);
+                slot
+            },
+            None => {
+                self.slots.push(Some(object));
+                self.slots.len() - 1
+            },
+        };
+        self.slot_by_key.insert(key, slot);
+        self.unindexed.insert(key);
         key
     }
 
     pub fn destroy(&mut self, key: ObjectKey) {
-        self.objects.remove(&key);
+        if let Some(slot) = self.slot_by_key.remove(&key) {
+            self.slots[slot] = None;
+            self.free.push(slot);
+            self.index.remove(key);
+            self.unindexed.remove(&key);
+        }
     }
 
     pub fn get(&self, key: ObjectKey) -> Option<&T> {
-        self.objects.get(&key)
+        let slot = *self.slot_by_key.get(&key)?;
+        self.slots[slot].as_ref()
     }
 
     pub fn get_mut(&mut self, key: ObjectKey) -> Option<&mut T> {
-        self.objects.get_mut(&key)
+        let slot = *self.slot_by_key.get(&key)?;
+        self.unindexed.insert(key);
+        self.slots[slot].as_mut()
+    }
+
+    /// get_pair_mut borrows two different objects at once;
+    /// None if either doesn't exist, or a and b are the same
+    pub fn get_pair_mut(&mut self, a: ObjectKey, b: ObjectKey) -> Option<(&mut T, &mut T)> {
+        let slot_a = *self.slot_by_key.get(&a)?;
+        let slot_b = *self.slot_by_key.get(&b)?;
+        if slot_a == slot_b {
+            return None;
+        }
+        self.unindexed.extend([a, b]);
+        let (lo, hi) = self.slots.split_at_mut(slot_a.max(slot_b));
+        let (low, high) = (lo[slot_a.min(slot_b)].as_mut()?, hi[0].as_mut()?);
+        match slot_a < slot_b {
+            true => Some((low, high)),
+            false => Some((high, low)),
+        }
     }
 
     pub fn contains(&self, key: ObjectKey) -> bool {
-        self.objects.contains_key(&key)
+        self.slot_by_key.contains_key(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slot_by_key.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.slot_by_key.is_empty()
+    }
+
+    /// objects iterates over the objects in the order of their slots
     pub fn objects(&self) -> impl Iterator<Item = &T> {
-        self.objects.values()
+        self.slots.iter().flatten()
     }
 
     pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut T> {
-        self.objects.values_mut()
+        self.unindexed.extend(self.slot_by_key.keys());
+        self.slots.iter_mut().flatten()
+    }
+
+    /// index returns the spatial index of the objects, which is
+    /// maintained by the physics, e.g. Space::reindex for Box2DPhysics.
+    /// Objects added or borrowed mutably since they were last indexed
+    /// may be missing from it, or at their old place; see unindexed.
+    pub fn index(&self) -> &SpatialHash {
+        &self.index
+    }
+
+    /// unindexed returns the objects which may have moved since
+    /// they were last indexed
+    pub fn unindexed(&self) -> &HashSet<ObjectKey> {
+        &self.unindexed
+    }
+
+    /// set_cell_size sets the cell size of the index, which is
+    /// rebuilt; see SpatialHash::set_cell_size
+    pub fn set_cell_size(&mut self, cell_size: f64) {
+        self.index.set_cell_size(cell_size);
+        self.unindexed.extend(self.slot_by_key.keys());
+    }
+
+    // index_with indexes the unindexed objects by their bounding boxes
+    pub(super) fn index_with(&mut self, bounds: impl Fn(&T) -> (V2<f64>, V2<f64>)) {
+        for key in self.unindexed.drain() {
+            if let Some(slot) = self.slot_by_key.get(&key) {
+                let (min, max) = bounds(self.slots[*slot].as_ref().unwrap());
+                self.index.insert(key, min, max);
+            }
+        }
+    }
+}
+
+// Spaces are encoded without their index, which is rebuilt by the physics
+impl<T> Encode for Space<T>
+where
+    T: Encode + 'static,
+{
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.slots.encode(encoder)?;
+        self.free.encode(encoder)?;
+        self.next_id.encode(encoder)
+    }
+}
+
+impl<T> Decode for Space<T>
+where
+    T: Identity + Decode + 'static,
+{
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let slots: Vec<Option<T>> = Vec::decode(decoder)?;
+        let free: Vec<usize> = Vec::decode(decoder)?;
+        let next_id = u64::decode(decoder)?;
+        if free
+            .iter()
+            .any(|slot| !matches!(slots.get(*slot), Some(None)))
+        {
+            return Err(DecodeError::Other("free slot of space in use"));
+        }
+        let mut slot_by_key = HashMap::new();
+        for (slot, object) in slots.iter().enumerate() {
+            if let Some(object) = object {
+                if slot_by_key.insert(object.key(), slot).is_some() {
+                    return Err(DecodeError::Other("objects with same ObjectId"));
+                }
+            }
+        }
+        let unindexed = slot_by_key.keys().copied().collect();
+        Ok(Self {
+            slots,
+            free,
+            slot_by_key,
+            index: SpatialHash::default(),
+            unindexed,
+            next_id,
+        })
     }
 }
 
@@ -99,22 +399,40 @@ where
     T: Paint,
 {
     pub fn paint(&self, viewport: &Viewport) -> Scene {
-        let mut scene = Scene::new(viewport.width, viewport.height);
-        for obj in self.objects.values() {
-            if let Some(mut sprite) = obj.paint() {
-                if viewport.overlaps(&sprite) {
-                    sprite.shift_pos(viewport.pos * -1);
-                    scene.add_sprite(sprite);
-                }
-            }
+        paint_sprites(viewport, self.slots.iter().flatten().filter_map(T::paint))
+    }
+}
+
+impl<T> Space<T>
+where
+    T: Interpolate,
+{
+    pub fn paint_interpolated(&self, viewport: &Viewport, alpha: f32) -> Scene {
+        paint_sprites(
+            viewport,
+            self.slots
+                .iter()
+                .flatten()
+                .filter_map(|obj| obj.paint_interpolated(alpha)),
+        )
+    }
+}
+
+pub(crate) fn paint_sprites(viewport: &Viewport, sprites: impl Iterator<Item = Sprite>) -> Scene {
+    let mut scene = Scene::new(viewport.width, viewport.height);
+    for mut sprite in sprites {
+        if viewport.overlaps(&sprite) {
+            sprite.shift_pos(viewport.pos * -1);
+            scene.add_sprite(sprite);
         }
-        scene
     }
+    scene
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::physics::identity::ObjectId;
     use crate::vector::v2::V2;
 
     #[test]
@@ -129,4 +447,88 @@ mod test {
         let rp = viewport.relative_pos(V2::new(0.0, 0.5));
         assert_eq!(rp, V2::new(-500, 0));
     }
+
+    #[test]
+    fn test_viewport_scene_to_world() {
+        let viewport = Viewport::new(V2::new(-500, -500), 1000, 1000);
+        let wp = viewport.scene_to_world(V2::new(250.4, 500.0));
+        assert_eq!(wp, V2::new(-250, 0));
+        assert_eq!(viewport.world_to_scene(wp), V2::new(250, 500));
+    }
+
+    struct Thing(ObjectId);
+
+    impl Identity for Thing {
+        fn id(&self) -> &ObjectId {
+            &self.0
+        }
+    }
+
+    // Steps records the delta_t of each physics step
+    struct Steps(Vec<f32>);
+
+    impl Physics<Thing> for Steps {
+        fn tick(&mut self, _space: &mut Space<Thing>, delta_t: f32) {
+            self.0.push(delta_t);
+        }
+    }
+
+    #[test]
+    fn test_fixed_step() {
+        let mut universe = Universe::new(Steps(vec![]));
+        universe.set_fixed_step(10.0, 3);
+        universe.tick(25.0);
+        assert_eq!(universe.physics().0, vec![10.0, 10.0]);
+        assert_eq!(universe.alpha(), 0.5);
+        universe.tick(5.0);
+        assert_eq!(universe.physics().0.len(), 3);
+        assert_eq!(universe.alpha(), 0.0);
+        // Long frames are capped at max_substeps
+        universe.tick(100.0);
+        assert_eq!(universe.physics().0.len(), 6);
+        assert_eq!(universe.alpha(), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fixed_step_zero() {
+        Universe::new(Steps(vec![])).set_fixed_step(0.0, 3);
+    }
+
+    #[test]
+    fn test_space_slots() {
+        let mut space = Space::new();
+        let a = space.add(Thing(ObjectId::new()));
+        let b = space.add(Thing(ObjectId::new()));
+        let (obj_b, obj_a) = space.get_pair_mut(b, a).unwrap();
+        assert_eq!((obj_a.key(), obj_b.key()), (a, b));
+        assert!(space.get_pair_mut(a, a).is_none());
+
+        space.destroy(a);
+        assert!(space.get_pair_mut(a, b).is_none());
+        // The slot of a is reused
+        let c = space.add(Thing(ObjectId::new()));
+        assert_eq!(space.len(), 2);
+        let keys: Vec<ObjectKey> = space.objects().map(|obj| obj.key()).collect();
+        assert_eq!(keys, vec![c, b]);
+    }
+
+    #[test]
+    fn test_space_ids() {
+        let mut a: Space<Thing> = Space::new();
+        let mut b: Space<Thing> = Space::new();
+        ObjectId::new();
+        let id = a.new_id();
+        let key_a = a.add(Thing(id));
+        let id = b.new_id();
+        assert_eq!(b.add(Thing(id)), key_a);
+
+        // Decoded ids are kept, and later ones don't collide with them
+        let far = ObjectId(ObjectId::new().0 + 1000);
+        let bytes = encode_to_vec(&far, config::standard()).unwrap();
+        let (decoded, _): (ObjectId, usize) =
+            decode_from_slice(&bytes, config::standard()).unwrap();
+        assert!(decoded == far);
+        assert!(ObjectId::new().0 > far.0);
+    }
 }
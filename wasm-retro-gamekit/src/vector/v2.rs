@@ -1,5 +1,7 @@
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
+use bincode::{Decode, Encode};
+
 use crate::num::{Float, One, Zero};
 
 #[derive(Clone, Copy, Debug)]
@@ -15,7 +17,7 @@ pub enum Direction {
     Right,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct V2<T> {
     pub x: T,
     pub y: T,
@@ -47,6 +49,10 @@ where
         T::sqrt(self.x.powi(2) + self.y.powi(2))
     }
 
+    pub fn dot(&self, other: &Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
     pub fn norm(&self) -> Self {
         let mag = self.mag();
         if mag == T::zero() {